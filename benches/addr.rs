@@ -0,0 +1,88 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Benchmarks of the calls which return a socket address, and so have the
+//! kernel fill in an address storage on every call.
+
+#[macro_use]
+extern crate criterion;
+extern crate socket2;
+
+use std::net::SocketAddr;
+
+use criterion::Criterion;
+use socket2::{Domain, Socket, Type};
+
+fn any_v4() -> socket2::SockAddr {
+    "127.0.0.1:0".parse::<SocketAddr>().unwrap().into()
+}
+
+fn udp_pair() -> (Socket, Socket) {
+    let a = Socket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+    let b = Socket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+    a.bind(&any_v4()).unwrap();
+    b.bind(&any_v4()).unwrap();
+    a.connect(&b.local_addr().unwrap()).unwrap();
+    b.connect(&a.local_addr().unwrap()).unwrap();
+    (a, b)
+}
+
+fn recv_from(c: &mut Criterion) {
+    let (a, b) = udp_pair();
+    let mut buf = [0; 64];
+    c.bench_function("recv_from", |bench| {
+        bench.iter(|| {
+            a.send(b"ping").unwrap();
+            b.recv_from(&mut buf).unwrap()
+        })
+    });
+}
+
+fn local_addr(c: &mut Criterion) {
+    let (a, _b) = udp_pair();
+    c.bench_function("local_addr", |bench| {
+        bench.iter(|| a.local_addr().unwrap())
+    });
+}
+
+fn peer_addr(c: &mut Criterion) {
+    let (a, _b) = udp_pair();
+    c.bench_function("peer_addr", |bench| {
+        bench.iter(|| a.peer_addr().unwrap())
+    });
+}
+
+// Unix domain sockets are used so that the connections don't use up the
+// ephemeral ports.
+#[cfg(unix)]
+fn accept(c: &mut Criterion) {
+    let dir = std::env::temp_dir();
+    let path = dir.join(format!("socket2-bench-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let addr = socket2::SockAddr::unix(&path).unwrap();
+    let listener = Socket::new(Domain::unix(), Type::stream(), None).unwrap();
+    listener.bind(&addr).unwrap();
+    listener.listen(128).unwrap();
+    c.bench_function("accept", |bench| {
+        bench.iter(|| {
+            let client = Socket::new(Domain::unix(), Type::stream(), None)
+                .unwrap();
+            client.connect(&addr).unwrap();
+            listener.accept().unwrap()
+        })
+    });
+    let _ = std::fs::remove_file(&path);
+}
+
+#[cfg(not(unix))]
+fn accept(_c: &mut Criterion) {}
+
+criterion_group!(benches, recv_from, local_addr, peer_addr, accept);
+criterion_main!(benches);
@@ -0,0 +1,91 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Benchmarks of the send and receive paths, each of which should make a
+//! single system call. The `hot_path_syscalls` test checks the latter.
+
+#[macro_use]
+extern crate criterion;
+extern crate socket2;
+
+use std::io::{IoSlice, IoSliceMut};
+use std::net::SocketAddr;
+
+use criterion::Criterion;
+#[cfg(unix)]
+use socket2::{MsgHdr, MsgHdrMut};
+use socket2::{Domain, Socket, Type};
+
+fn udp_pair() -> (Socket, Socket) {
+    let any = "127.0.0.1:0".parse::<SocketAddr>().unwrap().into();
+    let a = Socket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+    let b = Socket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+    a.bind(&any).unwrap();
+    b.bind(&any).unwrap();
+    a.connect(&b.local_addr().unwrap()).unwrap();
+    b.connect(&a.local_addr().unwrap()).unwrap();
+    (a, b)
+}
+
+fn send_recv(c: &mut Criterion) {
+    let (a, b) = udp_pair();
+    let mut buf = [0; 64];
+    c.bench_function("send_recv", |bench| {
+        bench.iter(|| {
+            a.send(b"ping").unwrap();
+            b.recv(&mut buf).unwrap()
+        })
+    });
+}
+
+fn send_to_recv_from(c: &mut Criterion) {
+    let (a, b) = udp_pair();
+    let addr = b.local_addr().unwrap();
+    let mut buf = [0; 64];
+    c.bench_function("send_to_recv_from", |bench| {
+        bench.iter(|| {
+            a.send_to(b"ping", &addr).unwrap();
+            b.recv_from(&mut buf).unwrap()
+        })
+    });
+}
+
+fn vectored(c: &mut Criterion) {
+    let (a, b) = udp_pair();
+    let (mut head, mut body) = ([0; 8], [0; 64]);
+    c.bench_function("vectored", |bench| {
+        bench.iter(|| {
+            a.send_vectored(&[IoSlice::new(b"head"), IoSlice::new(b"body")])
+                .unwrap();
+            b.recv_vectored(&mut [IoSliceMut::new(&mut head),
+                                  IoSliceMut::new(&mut body)]).unwrap()
+        })
+    });
+}
+
+#[cfg(unix)]
+fn msg(c: &mut Criterion) {
+    let (a, b) = udp_pair();
+    let mut buf = [0; 64];
+    c.bench_function("msg", |bench| {
+        bench.iter(|| {
+            let bufs = [IoSlice::new(b"ping")];
+            a.send_msg(&MsgHdr::new().with_buffers(&bufs)).unwrap();
+            let mut bufs = [IoSliceMut::new(&mut buf)];
+            b.recv_msg(&mut MsgHdrMut::new().with_buffers(&mut bufs)).unwrap()
+        })
+    });
+}
+
+#[cfg(not(unix))]
+fn msg(_c: &mut Criterion) {}
+
+criterion_group!(benches, send_recv, send_to_recv_from, vectored, msg);
+criterion_main!(benches);
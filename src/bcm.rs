@@ -0,0 +1,232 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::io;
+use std::mem;
+use std::ptr;
+use std::time::Duration;
+
+use libc::{self, c_long};
+
+/// A classic CAN frame, carrying up to 8 bytes of data.
+///
+/// This is a wrapper around the kernel's `can_frame` structure, used to build
+/// the frames of a `BcmMsg`.
+///
+/// This type is only available on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanFrame {
+    id: u32,
+    len: u8,
+    data: [u8; 8],
+}
+
+impl CanFrame {
+    /// Creates a frame with the identifier `id` carrying `data`.
+    ///
+    /// `id` may include the `CAN_EFF_FLAG`, `CAN_RTR_FLAG` and `CAN_ERR_FLAG`
+    /// flags. An error of kind `InvalidInput` is returned if `data` is longer
+    /// than 8 bytes.
+    pub fn new(id: u32, data: &[u8]) -> io::Result<CanFrame> {
+        let mut frame = CanFrame {
+            id,
+            len: data.len() as u8,
+            data: [0; 8],
+        };
+        if data.len() > frame.data.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "CAN frame data must be at most 8 bytes"))
+        }
+        frame.data[..data.len()].copy_from_slice(data);
+        Ok(frame)
+    }
+
+    /// Returns the identifier of this frame, including its flags.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the data of this frame.
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+}
+
+/// A message to the CAN broadcast manager, which is written to a `CAN_BCM`
+/// socket with `Socket::send`.
+///
+/// This is a builder around the kernel's `bcm_msg_head` structure and the
+/// frames following it. It can set up cyclic transmission of frames, or
+/// filtering of received frames by their content, which the kernel then
+/// handles without waking up the process for every frame.
+///
+/// This type is only available on Linux.
+#[derive(Debug, Clone)]
+pub struct BcmMsg {
+    opcode: u32,
+    flags: u32,
+    count: u32,
+    ival1: Duration,
+    ival2: Duration,
+    can_id: u32,
+    frames: Vec<CanFrame>,
+}
+
+impl BcmMsg {
+    fn new(opcode: u32, can_id: u32) -> BcmMsg {
+        BcmMsg {
+            opcode,
+            flags: 0,
+            count: 0,
+            ival1: Duration::from_secs(0),
+            ival2: Duration::from_secs(0),
+            can_id,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Creates a `TX_SETUP` message, which sets up or updates the cyclic
+    /// transmission of the frames with the identifier `can_id`.
+    ///
+    /// The frames to send are set with [`with_frames`] and the cycle with
+    /// [`with_initial`] and [`with_interval`].
+    ///
+    /// [`with_frames`]: #method.with_frames
+    /// [`with_initial`]: #method.with_initial
+    /// [`with_interval`]: #method.with_interval
+    pub fn tx_setup(can_id: u32) -> BcmMsg {
+        BcmMsg::new(libc::TX_SETUP, can_id)
+    }
+
+    /// Creates a `TX_DELETE` message, which stops the cyclic transmission of
+    /// the frames with the identifier `can_id`.
+    pub fn tx_delete(can_id: u32) -> BcmMsg {
+        BcmMsg::new(libc::TX_DELETE, can_id)
+    }
+
+    /// Creates a `TX_SEND` message, which sends the frame set with
+    /// [`with_frames`] once.
+    ///
+    /// [`with_frames`]: #method.with_frames
+    pub fn tx_send(can_id: u32) -> BcmMsg {
+        BcmMsg::new(libc::TX_SEND, can_id)
+    }
+
+    /// Creates an `RX_SETUP` message, which sets up or updates a filter on
+    /// the received frames with the identifier `can_id`.
+    ///
+    /// If frames are set with [`with_frames`], their data is used as a mask
+    /// and a received frame is only passed on when the masked bits of its
+    /// data change. Otherwise every received frame with the identifier is
+    /// passed on, using the `RX_FILTER_ID` flag.
+    ///
+    /// [`with_frames`]: #method.with_frames
+    pub fn rx_setup(can_id: u32) -> BcmMsg {
+        BcmMsg::new(libc::RX_SETUP, can_id)
+    }
+
+    /// Creates an `RX_DELETE` message, which removes the filter on the
+    /// received frames with the identifier `can_id`.
+    pub fn rx_delete(can_id: u32) -> BcmMsg {
+        BcmMsg::new(libc::RX_DELETE, can_id)
+    }
+
+    /// Sets the frames of this message.
+    ///
+    /// For `TX_SETUP` these are the frames sent in turn, and for `RX_SETUP`
+    /// the content filters applied to received frames.
+    pub fn with_frames(mut self, frames: &[CanFrame]) -> BcmMsg {
+        self.frames = frames.to_vec();
+        self
+    }
+
+    /// Sends `count` frames `interval` apart before switching to the interval
+    /// set with [`with_interval`], for `TX_SETUP` messages.
+    ///
+    /// [`with_interval`]: #method.with_interval
+    pub fn with_initial(mut self, count: u32, interval: Duration) -> BcmMsg {
+        self.count = count;
+        self.ival1 = interval;
+        self.flags |= libc::SETTIMER | libc::STARTTIMER;
+        self
+    }
+
+    /// Sends frames every `interval`, for `TX_SETUP` messages.
+    pub fn with_interval(mut self, interval: Duration) -> BcmMsg {
+        self.ival2 = interval;
+        self.flags |= libc::SETTIMER | libc::STARTTIMER;
+        self
+    }
+
+    /// Reports an `RX_TIMEOUT` message if no matching frame is received for
+    /// `timeout`, for `RX_SETUP` messages.
+    pub fn with_timeout(mut self, timeout: Duration) -> BcmMsg {
+        self.ival1 = timeout;
+        self.flags |= libc::SETTIMER | libc::STARTTIMER;
+        self
+    }
+
+    /// Passes on changed frames at most once every `interval`, for
+    /// `RX_SETUP` messages.
+    pub fn with_throttle(mut self, interval: Duration) -> BcmMsg {
+        self.ival2 = interval;
+        self.flags |= libc::SETTIMER;
+        self
+    }
+
+    /// Adds the given flags, such as `TX_ANNOUNCE` or `RX_CHECK_DLC`, to this
+    /// message.
+    pub fn with_flags(mut self, flags: u32) -> BcmMsg {
+        self.flags |= flags;
+        self
+    }
+
+    /// Encodes this message in the layout the kernel expects, ready to be
+    /// written to a `CAN_BCM` socket.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut flags = self.flags;
+        if self.opcode == libc::RX_SETUP && self.frames.is_empty() {
+            flags |= libc::RX_FILTER_ID;
+        }
+        let head_len = mem::size_of::<libc::bcm_msg_head>();
+        let frame_len = mem::size_of::<libc::can_frame>();
+        // The fields are written one by one into a zeroed buffer, so any
+        // padding between them is zero rather than uninitialized.
+        let mut bytes = vec![0u8; head_len + self.frames.len() * frame_len];
+        unsafe {
+            let head = bytes.as_mut_ptr() as *mut libc::bcm_msg_head;
+            ptr::addr_of_mut!((*head).opcode).write_unaligned(self.opcode);
+            ptr::addr_of_mut!((*head).flags).write_unaligned(flags);
+            ptr::addr_of_mut!((*head).count).write_unaligned(self.count);
+            ptr::addr_of_mut!((*head).ival1)
+                .write_unaligned(to_bcm_timeval(self.ival1));
+            ptr::addr_of_mut!((*head).ival2)
+                .write_unaligned(to_bcm_timeval(self.ival2));
+            ptr::addr_of_mut!((*head).can_id).write_unaligned(self.can_id);
+            ptr::addr_of_mut!((*head).nframes)
+                .write_unaligned(self.frames.len() as u32);
+            for (i, frame) in self.frames.iter().enumerate() {
+                let raw = bytes.as_mut_ptr().add(head_len + i * frame_len)
+                    as *mut libc::can_frame;
+                ptr::addr_of_mut!((*raw).can_id).write_unaligned(frame.id);
+                ptr::addr_of_mut!((*raw).can_dlc).write_unaligned(frame.len);
+                ptr::addr_of_mut!((*raw).data).write_unaligned(frame.data);
+            }
+        }
+        bytes
+    }
+}
+
+fn to_bcm_timeval(dur: Duration) -> libc::bcm_timeval {
+    libc::bcm_timeval {
+        tv_sec: dur.as_secs() as c_long,
+        tv_usec: dur.subsec_micros() as c_long,
+    }
+}
@@ -20,13 +20,14 @@
 //! # Examples
 //!
 //! ```no_run
+//! use std::net::SocketAddr;
 //! use socket2::{Socket, Domain, Type};
 //!
 //! // create a TCP listener bound to two addresses
 //! let socket = Socket::new(Domain::ipv4(), Type::stream(), None).unwrap();
 //!
-//! socket.bind(&"127.0.0.1:12345".parse().unwrap()).unwrap();
-//! socket.bind(&"127.0.0.1:12346".parse().unwrap()).unwrap();
+//! socket.bind(&"127.0.0.1:12345".parse::<SocketAddr>().unwrap().into()).unwrap();
+//! socket.bind(&"127.0.0.1:12346".parse::<SocketAddr>().unwrap().into()).unwrap();
 //! socket.listen(128).unwrap();
 //!
 //! let listener = socket.into_tcp_listener();
@@ -43,14 +44,44 @@
 #[cfg(windows)] extern crate winapi;
 #[cfg(windows)] extern crate ws2_32;
 
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
 use utils::NetInt;
 
+#[cfg(target_os = "linux")]
+mod bcm;
+#[cfg(unix)]
+mod msg;
+mod sockaddr;
 mod socket;
 mod utils;
 
+#[cfg(feature = "presets")]
+pub mod presets;
+#[cfg(feature = "proxy-client")]
+pub mod proxy_client;
+#[cfg(feature = "proxy-protocol")]
+pub mod proxy_protocol;
+
 #[cfg(unix)] #[path = "sys/unix/mod.rs"] mod sys;
 #[cfg(windows)] #[path = "sys/windows.rs"] mod sys;
 
+pub use sockaddr::SockAddr;
+#[cfg(target_os = "linux")]
+pub use bcm::{BcmMsg, CanFrame};
+#[cfg(unix)]
+pub use msg::{cmsg_space, BufferProvider, CMsg, CMsgBuf, CMsgIter, MsgHdr,
+              MsgHdrMut, RecvFlags, RecvScratch};
+#[cfg(target_os = "linux")]
+pub use msg::Timestamps;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use msg::ExtendedError;
+#[cfg(any(target_os = "linux", target_os = "android",
+          target_os = "macos", target_os = "ios",
+          target_os = "freebsd"))]
+pub use msg::RecvMsg;
+
 /// Newtype, owned, wrapper around a system socket.
 ///
 /// This type simply wraps an instance of a file descriptor (`c_int`) on Unix
@@ -60,25 +91,45 @@ mod utils;
 /// precisely one libc or OS API call which is essentially just a "Rustic
 /// translation" of what's below.
 ///
+/// Sockets created by this crate, including those returned by `accept`,
+/// `pair` and `try_clone`, are not inherited by child processes: they have
+/// the close-on-exec flag set on Unix and are not inheritable on Windows. On
+/// Unix this can be opted out of with `set_cloexec`.
+///
 /// # Examples
 ///
 /// ```no_run
+/// use std::net::SocketAddr;
 /// use socket2::{Socket, Domain, Type};
 ///
 /// // create a TCP listener bound to two addresses
 /// let socket = Socket::new(Domain::ipv4(), Type::stream(), None).unwrap();
 ///
-/// socket.bind(&"127.0.0.1:12345".parse().unwrap()).unwrap();
-/// socket.bind(&"127.0.0.1:12346".parse().unwrap()).unwrap();
+/// socket.bind(&"127.0.0.1:12345".parse::<SocketAddr>().unwrap().into()).unwrap();
+/// socket.bind(&"127.0.0.1:12346".parse::<SocketAddr>().unwrap().into()).unwrap();
 /// socket.listen(128).unwrap();
 ///
 /// let listener = socket.into_tcp_listener();
 /// // ...
 /// ```
+///
+/// # Thread safety
+///
+/// `Socket` is both `Send` and `Sync`, and all operations take `&self`. Note
+/// though that options such as the read and write timeouts are shared by all
+/// handles to the same socket, including those created with `try_clone`. When
+/// sharing a socket between threads prefer the per-call variants such as
+/// `recv_deadline` over changing these options around each call.
 pub struct Socket {
     inner: sys::Socket,
 }
 
+#[allow(dead_code)]
+fn assert_send_sync() {
+    fn is_send_sync<T: Send + Sync>() {}
+    is_send_sync::<Socket>();
+}
+
 /// Specification of the communication domain for a socket.
 ///
 /// This is a newtype wrapper around an integer which provides a nicer API in
@@ -110,6 +161,245 @@ pub struct Type(i32);
 /// value needs to be provided.
 pub struct Protocol(i32);
 
+/// Link-layer membership of a packet socket, used with
+/// `Socket::join_packet_membership` and `Socket::leave_packet_membership`.
+///
+/// This type is only available on Linux.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy)]
+pub enum MulticastMode<'a> {
+    /// Receive all packets on the interface, corresponding to
+    /// `PACKET_MR_PROMISC`.
+    Promiscuous,
+    /// Receive all multicast packets on the interface, corresponding to
+    /// `PACKET_MR_ALLMULTI`.
+    AllMulticast,
+    /// Receive packets sent to the given link-layer multicast address,
+    /// corresponding to `PACKET_MR_MULTICAST`. The address can be at most 8
+    /// bytes long.
+    Multicast(&'a [u8]),
+}
+
+/// A Windows event object, used with `Socket::event_select` to wait for
+/// network events on sockets together with other kernel objects, for example
+/// with `WaitForMultipleObjects`.
+///
+/// The event is created with `WSACreateEvent` and closed when dropped. Its
+/// handle is available through `AsRawHandle`.
+///
+/// This type is only available on Windows.
+#[cfg(windows)]
+#[derive(Debug)]
+pub struct Event {
+    inner: sys::Event,
+}
+
+/// A set of network events which can be signaled through an `Event`,
+/// corresponding to the `FD_*` flags of `WSAEventSelect`.
+///
+/// Sets can be combined with the `|` operator.
+///
+/// This type is only available on Windows.
+#[cfg(windows)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkEvents(i32);
+
+/// Credentials of the process on the other end of a Unix domain socket, as
+/// returned by `Socket::peer_cred`.
+///
+/// This type is only available on Linux, Android, macOS, iOS and the BSDs.
+#[cfg(any(target_os = "linux", target_os = "android",
+      target_os = "macos", target_os = "ios",
+      target_os = "freebsd", target_os = "dragonfly",
+      target_os = "openbsd", target_os = "netbsd"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UCred {
+    uid: u32,
+    gid: u32,
+    pid: Option<i32>,
+}
+
+/// The minimum security level of the calls made on an RxRPC socket, used
+/// with `Socket::set_rxrpc_min_security_level`.
+///
+/// This type is only available on Linux.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RxrpcSecurityLevel {
+    /// Calls aren't secured, corresponding to `RXRPC_SECURITY_PLAIN`.
+    Plain,
+    /// Packets are authenticated, corresponding to `RXRPC_SECURITY_AUTH`.
+    Auth,
+    /// Packets are authenticated and encrypted, corresponding to
+    /// `RXRPC_SECURITY_ENCRYPT`.
+    Encrypt,
+}
+
+/// The clock used for the receive timestamps of a socket, used with
+/// `Socket::set_timestamp_clock`.
+///
+/// This type is only available on FreeBSD.
+#[cfg(target_os = "freebsd")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampClock {
+    /// The realtime clock with microsecond resolution, reported as a
+    /// `timeval` in `SCM_TIMESTAMP` messages. This is the default.
+    RealtimeMicro,
+    /// The realtime clock, reported as a `bintime` in `SCM_BINTIME`
+    /// messages.
+    Bintime,
+    /// The realtime clock with nanosecond resolution, reported as a
+    /// `timespec` in `SCM_REALTIME` messages.
+    Realtime,
+    /// The monotonic clock with nanosecond resolution, reported as a
+    /// `timespec` in `SCM_MONOTONIC` messages.
+    Monotonic,
+}
+
+/// A filter on the identifiers of the frames received by a raw CAN socket,
+/// used with `Socket::set_can_filters`.
+///
+/// A frame passes the filter if `frame_id & mask == id & mask`. This is a
+/// wrapper around the kernel's `can_filter` structure.
+///
+/// This type is only available on Linux.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanFilter {
+    id: u32,
+    mask: u32,
+}
+
+/// A snapshot of the commonly inspected options of a socket, as returned by
+/// `Socket::dump_options`.
+///
+/// Each value is `None` if the option couldn't be read, for example because
+/// it doesn't apply to the socket's type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketOptions {
+    reuse_address: Option<bool>,
+    nodelay: Option<bool>,
+    recv_buffer_size: Option<usize>,
+    send_buffer_size: Option<usize>,
+    read_timeout: Option<Option<Duration>>,
+    write_timeout: Option<Option<Duration>>,
+    linger: Option<Option<Duration>>,
+    keepalive: Option<Option<Duration>>,
+    ttl: Option<u32>,
+}
+
+/// Parameters of TCP keepalive, used with `Socket::set_tcp_keepalive`.
+///
+/// Parameters which aren't set are left at the system's defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TcpKeepalive {
+    time: Option<Duration>,
+    interval: Option<Duration>,
+    retries: Option<u32>,
+}
+
+/// The communication domain, type and protocol of a socket, as returned by
+/// `Socket::metadata`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    domain: i32,
+    ty: i32,
+    protocol: Option<i32>,
+}
+
+/// The QUIC-related features enabled by `Socket::configure_for_quic`.
+///
+/// This type is only available on Linux, Android, macOS, iOS and FreeBSD,
+/// when the `quic` feature is enabled.
+#[cfg(all(feature = "quic",
+          any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuicFeatures {
+    pktinfo: bool,
+    ecn: bool,
+    gso: bool,
+    gro: bool,
+    recverr: bool,
+}
+
+/// Memory accounting information of a socket, as returned by
+/// `Socket::meminfo`.
+///
+/// This is a wrapper around the kernel's `SK_MEMINFO_*` array. Values which
+/// are not reported by the running kernel are zero.
+///
+/// This type is only available on Linux.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy)]
+pub struct MemInfo {
+    raw: [u32; 9],
+}
+
+/// Transport statistics of a TCP connection, as returned by
+/// `Socket::tcp_info`.
+///
+/// This type is only available on Linux, FreeBSD and Windows.
+#[cfg(any(target_os = "linux", target_os = "freebsd", windows))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpInfo {
+    state: TcpState,
+    rtt: Duration,
+    retransmits: u32,
+    delivery_rate: Option<u64>,
+    cwnd: u32,
+}
+
+/// The state of a TCP connection, as reported by `TcpInfo::state`.
+///
+/// This type is only available on Linux, FreeBSD and Windows.
+#[cfg(any(target_os = "linux", target_os = "freebsd", windows))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpState {
+    /// The connection is closed.
+    Closed,
+    /// The socket is listening for connections.
+    Listen,
+    /// A `SYN` was sent and the connection is being established.
+    SynSent,
+    /// A `SYN` was received and the connection is being established.
+    SynReceived,
+    /// The connection is established.
+    Established,
+    /// The connection was shut down locally and the `FIN` not acknowledged.
+    FinWait1,
+    /// The connection was shut down locally and waits for the peer's `FIN`.
+    FinWait2,
+    /// The peer shut down the connection and waits for the local side.
+    CloseWait,
+    /// Both sides shut down the connection at the same time.
+    Closing,
+    /// The connection was shut down locally after the peer did, and the
+    /// `FIN` not acknowledged.
+    LastAck,
+    /// The connection is closed and waits for delayed segments to expire.
+    TimeWait,
+}
+
+/// The header of an IPv4 packet received on a raw socket, as returned by
+/// `Socket::recv_from_ipv4`.
+///
+/// The length and fragment fields are normalized, so they have the same
+/// meaning on all platforms regardless of how the system reports them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ipv4Header {
+    header_len: usize,
+    total_len: usize,
+    tos: u8,
+    identification: u16,
+    flags_fragment: u16,
+    ttl: u8,
+    protocol: u8,
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+}
+
 fn hton<I: NetInt>(i: I) -> I { i.to_be() }
 
 fn ntoh<I: NetInt>(i: I) -> I { I::from_be(i) }
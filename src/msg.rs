@@ -0,0 +1,999 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cmp;
+use std::fmt;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::io;
+use std::io::{IoSlice, IoSliceMut};
+use std::mem::{self, MaybeUninit};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ptr;
+#[cfg(target_os = "linux")]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use libc::{self, c_int, c_uint, c_void, sockaddr_storage, socklen_t};
+
+use SockAddr;
+
+// From linux/socket.h, which libc doesn't bind.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const SCM_SECURITY: c_int = 0x03;
+
+/// A message to send with `Socket::send_msg`.
+///
+/// This is a builder around the system's `msghdr` structure, combining the
+/// data to send, the destination address and any control messages.
+///
+/// This type is only available on Unix.
+#[derive(Debug)]
+pub struct MsgHdr<'a> {
+    addr: Option<&'a SockAddr>,
+    bufs: &'a [IoSlice<'a>],
+    control: &'a [u8],
+    flags: c_int,
+}
+
+impl<'a> MsgHdr<'a> {
+    /// Creates an empty message.
+    pub fn new() -> MsgHdr<'a> {
+        MsgHdr {
+            addr: None,
+            bufs: &[],
+            control: &[],
+            flags: 0,
+        }
+    }
+
+    /// Sets the destination address of the message, which is only needed for
+    /// sockets which aren't connected.
+    pub fn with_addr(mut self, addr: &'a SockAddr) -> MsgHdr<'a> {
+        self.addr = Some(addr);
+        self
+    }
+
+    /// Sets the data of the message, which is sent from `bufs` in order.
+    pub fn with_buffers(mut self, bufs: &'a [IoSlice<'a>]) -> MsgHdr<'a> {
+        self.bufs = bufs;
+        self
+    }
+
+    /// Sets the control messages sent along with the data, which are usually
+    /// built with a `CMsgBuf`.
+    pub fn with_control(mut self, control: &'a [u8]) -> MsgHdr<'a> {
+        self.control = control;
+        self
+    }
+
+    /// Sets the `MSG_*` flags passed to `sendmsg`, such as `MSG_EOR` to end
+    /// a record with this message.
+    pub fn with_flags(mut self, flags: i32) -> MsgHdr<'a> {
+        self.flags = flags;
+        self
+    }
+
+    pub(crate) fn flags(&self) -> c_int {
+        self.flags
+    }
+
+    pub(crate) fn raw(&self) -> libc::msghdr {
+        unsafe {
+            let mut msg: libc::msghdr = mem::zeroed();
+            if let Some(addr) = self.addr {
+                msg.msg_name = addr.as_ptr() as *mut c_void;
+                msg.msg_namelen = addr.len();
+            }
+            // `IoSlice` is guaranteed to be ABI compatible with `iovec`.
+            msg.msg_iov = self.bufs.as_ptr() as *mut libc::iovec;
+            msg.msg_iovlen = cmp::min(self.bufs.len(), c_int::MAX as usize) as _;
+            if !self.control.is_empty() {
+                msg.msg_control = self.control.as_ptr() as *mut c_void;
+                msg.msg_controllen = self.control.len() as _;
+            }
+            msg
+        }
+    }
+}
+
+impl<'a> Default for MsgHdr<'a> {
+    fn default() -> MsgHdr<'a> {
+        MsgHdr::new()
+    }
+}
+
+/// Buffers to receive a message into with `Socket::recv_msg`, and the
+/// message's metadata once it's received.
+///
+/// This is a builder around the system's `msghdr` structure. After a message
+/// is received the sender's address, the `msg_flags` reported by the system
+/// and the received control messages can be read back from it.
+///
+/// This type is only available on Unix.
+pub struct MsgHdrMut<'a> {
+    bufs: &'a mut [IoSliceMut<'a>],
+    control: &'a mut [u8],
+    flags: c_int,
+    addr: Option<SockAddr>,
+    control_len: usize,
+    recv_flags: c_int,
+}
+
+impl<'a> MsgHdrMut<'a> {
+    /// Creates a message without any buffers.
+    pub fn new() -> MsgHdrMut<'a> {
+        MsgHdrMut {
+            bufs: &mut [],
+            control: &mut [],
+            flags: 0,
+            addr: None,
+            control_len: 0,
+            recv_flags: 0,
+        }
+    }
+
+    /// Sets the buffers the data of the message is received into, in order.
+    pub fn with_buffers(mut self, bufs: &'a mut [IoSliceMut<'a>])
+                        -> MsgHdrMut<'a> {
+        self.bufs = bufs;
+        self
+    }
+
+    /// Sets the buffer control messages are received into.
+    ///
+    /// Its size can be computed with `cmsg_space`. Control messages which
+    /// don't fit are discarded, which is reported by
+    /// `RecvFlags::is_control_truncated`.
+    pub fn with_control(mut self, control: &'a mut [u8]) -> MsgHdrMut<'a> {
+        self.control = control;
+        self
+    }
+
+    /// Sets the `MSG_*` flags passed to `recvmsg`, such as `MSG_PEEK`.
+    pub fn with_flags(mut self, flags: i32) -> MsgHdrMut<'a> {
+        self.flags = flags;
+        self
+    }
+
+    /// Returns the address of the sender of the last message received.
+    ///
+    /// This is `None` before a message was received, and may be an unnamed
+    /// address for connected sockets.
+    pub fn addr(&self) -> Option<&SockAddr> {
+        self.addr.as_ref()
+    }
+
+    /// Returns the `msg_flags` the system reported for the last message
+    /// received.
+    pub fn recv_flags(&self) -> RecvFlags {
+        RecvFlags(self.recv_flags)
+    }
+
+    /// Returns the control messages of the last message received.
+    pub fn cmsgs(&self) -> CMsgIter<'_> {
+        CMsgIter { buf: &self.control[..self.control_len] }
+    }
+
+    /// Returns the timestamps and drop count reported for the last message
+    /// received, after enabling them with `Socket::enable_rx_timestamps`.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn timestamps(&self) -> Timestamps {
+        let mut timestamps = Timestamps::default();
+        for cmsg in self.cmsgs() {
+            if cmsg.level() != libc::SOL_SOCKET {
+                continue
+            }
+            let data = cmsg.data();
+            if cmsg.ty() == libc::SCM_TIMESTAMPING &&
+               data.len() >= mem::size_of::<[libc::timespec; 3]>() {
+                // The first timestamp is the software one and the third the
+                // raw hardware one, the second is deprecated and unused.
+                let ts = unsafe {
+                    ptr::read_unaligned(data.as_ptr() as *const [libc::timespec; 3])
+                };
+                timestamps.software = timespec_to_duration(&ts[0])
+                    .map(|dur| UNIX_EPOCH + dur);
+                timestamps.hardware = timespec_to_duration(&ts[2]);
+            } else if cmsg.ty() == libc::SO_RXQ_OVFL &&
+                      data.len() >= mem::size_of::<u32>() {
+                timestamps.dropped = Some(unsafe {
+                    ptr::read_unaligned(data.as_ptr() as *const u32)
+                });
+            }
+        }
+        timestamps
+    }
+
+    /// Returns the type-of-service byte of the last IPv4 message received,
+    /// or the traffic class of the last IPv6 message, after enabling them
+    /// with `Socket::set_recv_tos` or `Socket::set_recv_tclass_v6`.
+    ///
+    /// The lower two bits are the ECN field. A control buffer of
+    /// `cmsg_space(4)` bytes is large enough to receive the value.
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn tos(&self) -> Option<u8> {
+        // Linux reports the IPv4 value with the type of the `IP_TOS` option
+        // rather than the one of `IP_RECVTOS`.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let ip_tos = libc::IP_TOS;
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let ip_tos = libc::IP_RECVTOS;
+        for cmsg in self.cmsgs() {
+            let data = cmsg.data();
+            if cmsg.level() == libc::IPPROTO_IP && cmsg.ty() == ip_tos &&
+               !data.is_empty() {
+                return Some(data[0])
+            } else if cmsg.level() == libc::IPPROTO_IPV6 &&
+                      cmsg.ty() == libc::IPV6_TCLASS &&
+                      data.len() >= mem::size_of::<c_int>() {
+                let tclass = unsafe {
+                    ptr::read_unaligned(data.as_ptr() as *const c_int)
+                };
+                return Some(tclass as u8)
+            }
+        }
+        None
+    }
+
+    /// Returns the time-to-live of the last IPv4 message received, or the hop
+    /// limit of the last IPv6 message, after enabling them with
+    /// `Socket::set_recv_ttl` or `Socket::set_recv_hoplimit_v6`.
+    ///
+    /// A control buffer of `cmsg_space(4)` bytes is large enough to receive
+    /// the value.
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn ttl(&self) -> Option<u8> {
+        // Linux reports the IPv4 value as an `int` with the type of the
+        // `IP_TTL` option, the BSDs as a single byte with the type of
+        // `IP_RECVTTL`.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let ip_ttl = libc::IP_TTL;
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let ip_ttl = libc::IP_RECVTTL;
+        for cmsg in self.cmsgs() {
+            let data = cmsg.data();
+            if cmsg.level() == libc::IPPROTO_IP && cmsg.ty() == ip_ttl {
+                if data.len() >= mem::size_of::<c_int>() {
+                    let ttl = unsafe {
+                        ptr::read_unaligned(data.as_ptr() as *const c_int)
+                    };
+                    return Some(ttl as u8)
+                } else if !data.is_empty() {
+                    return Some(data[0])
+                }
+            } else if cmsg.level() == libc::IPPROTO_IPV6 &&
+                      cmsg.ty() == libc::IPV6_HOPLIMIT &&
+                      data.len() >= mem::size_of::<c_int>() {
+                let hoplimit = unsafe {
+                    ptr::read_unaligned(data.as_ptr() as *const c_int)
+                };
+                return Some(hoplimit as u8)
+            }
+        }
+        None
+    }
+
+    /// Returns the number of bytes which were still queued on the socket after
+    /// the last message was received, after enabling it with
+    /// `Socket::set_tcp_inq`.
+    ///
+    /// A control buffer of `cmsg_space(4)` bytes is large enough to receive
+    /// the value.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn inq(&self) -> Option<usize> {
+        for cmsg in self.cmsgs() {
+            let data = cmsg.data();
+            if cmsg.level() == libc::IPPROTO_TCP &&
+               cmsg.ty() == libc::TCP_CM_INQ &&
+               data.len() >= mem::size_of::<c_int>() {
+                let inq = unsafe {
+                    ptr::read_unaligned(data.as_ptr() as *const c_int)
+                };
+                return Some(inq as usize)
+            }
+        }
+        None
+    }
+
+    /// Returns the security context of the sender of the last message
+    /// received, after enabling it with `Socket::set_pass_sec`.
+    ///
+    /// The context is returned without its terminating nul byte. It's at most
+    /// a page long, but usually much shorter, so a control buffer of
+    /// `cmsg_space(256)` bytes is enough for most policies.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn security_label(&self) -> Option<&[u8]> {
+        for cmsg in self.cmsgs() {
+            if cmsg.level() == libc::SOL_SOCKET && cmsg.ty() == SCM_SECURITY {
+                let data = cmsg.data();
+                let len = data.iter().position(|&b| b == 0)
+                    .unwrap_or(data.len());
+                return Some(&data[..len])
+            }
+        }
+        None
+    }
+
+    /// Returns the extended error reported by the last message received from
+    /// the socket's error queue, after enabling `IP_RECVERR` or
+    /// `IPV6_RECVERR`, for example with `Socket::configure_for_quic`.
+    ///
+    /// Messages are received from the error queue by passing
+    /// `libc::MSG_ERRQUEUE` to `MsgHdrMut::with_flags`. The control buffer
+    /// needs `ExtendedError::control_len()` bytes for the error, in addition
+    /// to the space needed by the other control messages enabled on the
+    /// socket, such as `IP_PKTINFO`, which are received with it.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn extended_error(&self) -> Option<ExtendedError> {
+        for cmsg in self.cmsgs() {
+            if (cmsg.level() == libc::IPPROTO_IP &&
+                cmsg.ty() == libc::IP_RECVERR) ||
+               (cmsg.level() == libc::IPPROTO_IPV6 &&
+                cmsg.ty() == libc::IPV6_RECVERR) {
+                return ExtendedError::parse(cmsg.data())
+            }
+        }
+        None
+    }
+
+    /// Returns the destination address of the last message received, after
+    /// enabling it with `Socket::configure_for_quic`.
+    ///
+    /// This is the address the message was sent to, which for sockets bound
+    /// to an unspecified address tells which of the host's addresses to
+    /// reply from. A control buffer of `cmsg_space(20)` bytes is large enough
+    /// to receive the address.
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn dst_addr(&self) -> Option<IpAddr> {
+        for cmsg in self.cmsgs() {
+            let data = cmsg.data();
+            if cmsg.level() == libc::IPPROTO_IP {
+                if let Some(addr) = ipv4_dst_addr(cmsg.ty(), data) {
+                    return Some(IpAddr::V4(addr))
+                }
+            } else if cmsg.level() == libc::IPPROTO_IPV6 &&
+                      cmsg.ty() == libc::IPV6_PKTINFO &&
+                      data.len() >= mem::size_of::<libc::in6_pktinfo>() {
+                let info = unsafe {
+                    let ptr = data.as_ptr() as *const libc::in6_pktinfo;
+                    ptr::read_unaligned(ptr)
+                };
+                return Some(IpAddr::V6(Ipv6Addr::from(info.ipi6_addr.s6_addr)))
+            }
+        }
+        None
+    }
+
+    pub(crate) fn flags(&self) -> c_int {
+        self.flags
+    }
+
+    // Returns the raw header which `recvmsg` needs to be called with, which
+    // receives the address into `storage` and is then passed to `finish`.
+    pub(crate) fn raw(&mut self, storage: &mut MaybeUninit<sockaddr_storage>)
+                      -> libc::msghdr {
+        unsafe {
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_name = storage.as_mut_ptr() as *mut c_void;
+            msg.msg_namelen = mem::size_of::<sockaddr_storage>() as socklen_t;
+            // `IoSliceMut` is guaranteed to be ABI compatible with `iovec`.
+            msg.msg_iov = self.bufs.as_mut_ptr() as *mut libc::iovec;
+            msg.msg_iovlen = cmp::min(self.bufs.len(), c_int::MAX as usize) as _;
+            if !self.control.is_empty() {
+                msg.msg_control = self.control.as_mut_ptr() as *mut c_void;
+                msg.msg_controllen = self.control.len() as _;
+            }
+            msg
+        }
+    }
+
+    pub(crate) fn finish(&mut self,
+                         msg: &libc::msghdr,
+                         storage: &MaybeUninit<sockaddr_storage>) {
+        self.addr = Some(unsafe {
+            SockAddr::from_raw_parts(storage.as_ptr() as *const _,
+                                     msg.msg_namelen)
+        });
+        self.control_len = cmp::min(msg.msg_controllen as _,
+                                    self.control.len());
+        self.recv_flags = msg.msg_flags;
+    }
+}
+
+impl<'a> Default for MsgHdrMut<'a> {
+    fn default() -> MsgHdrMut<'a> {
+        MsgHdrMut::new()
+    }
+}
+
+impl<'a> fmt::Debug for MsgHdrMut<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MsgHdrMut")
+         .field("flags", &self.flags)
+         .field("addr", &self.addr)
+         .field("recv_flags", &self.recv_flags())
+         .field("control_len", &self.control_len)
+         .finish()
+    }
+}
+
+/// The receive timestamps of a message and the number of packets the socket
+/// dropped, as returned by `MsgHdrMut::timestamps`.
+///
+/// This type is only available on Linux.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Timestamps {
+    software: Option<SystemTime>,
+    hardware: Option<Duration>,
+    dropped: Option<u32>,
+}
+
+#[cfg(target_os = "linux")]
+impl Timestamps {
+    /// Returns the size of the control buffer needed to receive the
+    /// timestamps and drop count of a message, to pass to
+    /// `MsgHdrMut::with_control`.
+    pub fn control_len() -> usize {
+        cmsg_space(mem::size_of::<[libc::timespec; 3]>()) +
+            cmsg_space(mem::size_of::<u32>())
+    }
+
+    /// Returns the time at which the kernel received the message.
+    pub fn software(&self) -> Option<SystemTime> {
+        self.software
+    }
+
+    /// Returns the time at which the network card received the message.
+    ///
+    /// This is a reading of the card's own clock, which isn't necessarily
+    /// synchronized with the system clock, so it's returned as a duration
+    /// since that clock's epoch.
+    pub fn hardware(&self) -> Option<Duration> {
+        self.hardware
+    }
+
+    /// Returns the total number of packets the socket dropped before this
+    /// message was received because its receive buffer was full, or `None`
+    /// if it hasn't dropped any.
+    pub fn dropped(&self) -> Option<u32> {
+        self.dropped
+    }
+}
+
+// Timestamps which weren't taken are reported as zero.
+#[cfg(target_os = "linux")]
+fn timespec_to_duration(ts: &libc::timespec) -> Option<Duration> {
+    if ts.tv_sec == 0 && ts.tv_nsec == 0 {
+        None
+    } else {
+        Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+    }
+}
+
+// Returns the address of an `IP_PKTINFO` control message, which FreeBSD
+// doesn't support, reporting the bare address as `IP_RECVDSTADDR` instead.
+#[cfg(any(target_os = "linux", target_os = "android",
+          target_os = "macos", target_os = "ios"))]
+fn ipv4_dst_addr(ty: c_int, data: &[u8]) -> Option<Ipv4Addr> {
+    if ty != libc::IP_PKTINFO ||
+       data.len() < mem::size_of::<libc::in_pktinfo>() {
+        return None
+    }
+    let info = unsafe {
+        ptr::read_unaligned(data.as_ptr() as *const libc::in_pktinfo)
+    };
+    Some(::sys::from_in_addr(info.ipi_addr))
+}
+
+#[cfg(target_os = "freebsd")]
+fn ipv4_dst_addr(ty: c_int, data: &[u8]) -> Option<Ipv4Addr> {
+    if ty != libc::IP_RECVDSTADDR ||
+       data.len() < mem::size_of::<libc::in_addr>() {
+        return None
+    }
+    let addr = unsafe {
+        ptr::read_unaligned(data.as_ptr() as *const libc::in_addr)
+    };
+    Some(::sys::from_in_addr(addr))
+}
+
+// The size of the control buffer of `Socket::recv_datagram`, large enough
+// for the packet information, the type-of-service and the timestamps of a
+// datagram, with room to spare.
+#[cfg(any(target_os = "linux", target_os = "android",
+          target_os = "macos", target_os = "ios",
+          target_os = "freebsd"))]
+pub(crate) const RECV_MSG_CONTROL_LEN: usize = 256;
+
+/// A datagram received with `Socket::recv_datagram`, together with what the
+/// system reported about it.
+///
+/// This collects the results of receiving with `Socket::recv_msg` and the
+/// accessors of `MsgHdrMut`, for servers which need the destination address,
+/// the ECN bits or the timestamps of each datagram. The destination address
+/// and type-of-service are only reported after enabling them, for example
+/// with `Socket::configure_for_quic`, and the timestamps after
+/// `Socket::enable_rx_timestamps`.
+///
+/// This type is only available on Linux, Android, macOS, iOS and FreeBSD.
+#[cfg(any(target_os = "linux", target_os = "android",
+          target_os = "macos", target_os = "ios",
+          target_os = "freebsd"))]
+#[derive(Debug, Clone)]
+pub struct RecvMsg {
+    len: usize,
+    addr: SockAddr,
+    flags: RecvFlags,
+    dst_addr: Option<IpAddr>,
+    tos: Option<u8>,
+    #[cfg(target_os = "linux")]
+    timestamps: Timestamps,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android",
+          target_os = "macos", target_os = "ios",
+          target_os = "freebsd"))]
+impl RecvMsg {
+    pub(crate) fn new(len: usize, msg: &MsgHdrMut) -> RecvMsg {
+        RecvMsg {
+            len,
+            addr: msg.addr().cloned().expect("address of received message"),
+            flags: msg.recv_flags(),
+            dst_addr: msg.dst_addr(),
+            tos: msg.tos(),
+            #[cfg(target_os = "linux")]
+            timestamps: msg.timestamps(),
+        }
+    }
+
+    /// Returns the number of bytes received.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the datagram was empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the address of the sender.
+    pub fn addr(&self) -> &SockAddr {
+        &self.addr
+    }
+
+    /// Returns the `msg_flags` the system reported for the datagram, such as
+    /// whether it was truncated.
+    pub fn flags(&self) -> RecvFlags {
+        self.flags
+    }
+
+    /// Returns the address the datagram was sent to.
+    pub fn dst_addr(&self) -> Option<IpAddr> {
+        self.dst_addr
+    }
+
+    /// Returns the type-of-service byte or traffic class of the datagram.
+    pub fn tos(&self) -> Option<u8> {
+        self.tos
+    }
+
+    /// Returns the ECN field of the datagram, the lower two bits of its
+    /// type-of-service byte.
+    pub fn ecn(&self) -> Option<u8> {
+        self.tos.map(|tos| tos & 0b11)
+    }
+
+    /// Returns the receive timestamps of the datagram and the number of
+    /// packets the socket dropped.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn timestamps(&self) -> Timestamps {
+        self.timestamps
+    }
+}
+
+/// An error queued on a socket by the system, as returned by
+/// `MsgHdrMut::extended_error`.
+///
+/// This is the system's `sock_extended_err` structure, together with the
+/// address of the node which reported the error.
+///
+/// This type is only available on Linux and Android.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug, Clone)]
+pub struct ExtendedError {
+    errno: i32,
+    origin: u8,
+    ty: u8,
+    code: u8,
+    info: u32,
+    data: u32,
+    offender: Option<SockAddr>,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl ExtendedError {
+    /// Returns the size of the control buffer needed to receive an extended
+    /// error, to pass to `MsgHdrMut::with_control`.
+    pub fn control_len() -> usize {
+        cmsg_space(mem::size_of::<libc::sock_extended_err>() +
+                   mem::size_of::<libc::sockaddr_in6>())
+    }
+
+    fn parse(data: &[u8]) -> Option<ExtendedError> {
+        let len = mem::size_of::<libc::sock_extended_err>();
+        if data.len() < len {
+            return None
+        }
+        let err = unsafe {
+            ptr::read_unaligned(data.as_ptr() as *const libc::sock_extended_err)
+        };
+        // The offending address directly follows the error, and is left
+        // unspecified for errors which didn't come from the network.
+        let addr = &data[len..];
+        let family = if addr.len() >= mem::size_of::<libc::sa_family_t>() {
+            let family = unsafe {
+                ptr::read_unaligned(addr.as_ptr() as *const libc::sa_family_t)
+            };
+            family as c_int
+        } else {
+            libc::AF_UNSPEC
+        };
+        let addr_len = match family {
+            libc::AF_INET => mem::size_of::<libc::sockaddr_in>(),
+            libc::AF_INET6 => mem::size_of::<libc::sockaddr_in6>(),
+            _ => 0,
+        };
+        let offender = if addr_len != 0 && addr.len() >= addr_len {
+            SockAddr::from_bytes(&addr[..addr_len]).ok()
+        } else {
+            None
+        };
+        Some(ExtendedError {
+            errno: err.ee_errno as i32,
+            origin: err.ee_origin,
+            ty: err.ee_type,
+            code: err.ee_code,
+            info: err.ee_info,
+            data: err.ee_data,
+            offender,
+        })
+    }
+
+    /// Returns the error as an `io::Error`.
+    pub fn error(&self) -> io::Error {
+        io::Error::from_raw_os_error(self.errno)
+    }
+
+    /// Returns the raw error number, such as `EMSGSIZE` or `ECONNREFUSED`.
+    pub fn errno(&self) -> i32 {
+        self.errno
+    }
+
+    /// Returns where the error originated, one of the `SO_EE_ORIGIN_*`
+    /// constants.
+    pub fn origin(&self) -> u8 {
+        self.origin
+    }
+
+    /// Returns the type of the ICMP or ICMPv6 message which reported the
+    /// error, if it originated from one.
+    pub fn icmp_type(&self) -> u8 {
+        self.ty
+    }
+
+    /// Returns the code of the ICMP or ICMPv6 message which reported the
+    /// error, if it originated from one.
+    pub fn icmp_code(&self) -> u8 {
+        self.code
+    }
+
+    /// Returns the additional information of the error, such as the path
+    /// MTU for `EMSGSIZE` errors.
+    pub fn info(&self) -> u32 {
+        self.info
+    }
+
+    /// Returns the additional data of the error, such as the range of
+    /// timestamped packets for transmit timestamps.
+    pub fn data(&self) -> u32 {
+        self.data
+    }
+
+    /// Returns the path MTU reported by an `EMSGSIZE` error, which is
+    /// queued when a datagram is larger than the path allows, either locally
+    /// or after an ICMP "fragmentation needed" or "packet too big" message.
+    pub fn mtu(&self) -> Option<u32> {
+        if self.errno == libc::EMSGSIZE {
+            Some(self.info)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the address of the node which reported the error, such as the
+    /// router which sent the ICMP message, if known.
+    pub fn offender(&self) -> Option<&SockAddr> {
+        self.offender.as_ref()
+    }
+}
+
+/// The `msg_flags` reported by the system for a received message.
+///
+/// This type is only available on Unix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvFlags(i32);
+
+impl RecvFlags {
+    /// Returns whether the data of the message didn't fit in the buffers and
+    /// was truncated, corresponding to `MSG_TRUNC`.
+    pub fn is_truncated(&self) -> bool {
+        self.0 & libc::MSG_TRUNC != 0
+    }
+
+    /// Returns whether some control messages didn't fit in the control
+    /// buffer and were discarded, corresponding to `MSG_CTRUNC`.
+    pub fn is_control_truncated(&self) -> bool {
+        self.0 & libc::MSG_CTRUNC != 0
+    }
+
+    /// Returns whether the message ends a record, corresponding to
+    /// `MSG_EOR`. This is used by sequenced packet sockets.
+    pub fn is_end_of_record(&self) -> bool {
+        self.0 & libc::MSG_EOR != 0
+    }
+
+    /// Returns whether out-of-band data was received, corresponding to
+    /// `MSG_OOB`.
+    pub fn is_out_of_band(&self) -> bool {
+        self.0 & libc::MSG_OOB != 0
+    }
+}
+
+impl From<i32> for RecvFlags {
+    fn from(a: i32) -> RecvFlags {
+        RecvFlags(a)
+    }
+}
+
+impl From<RecvFlags> for i32 {
+    fn from(a: RecvFlags) -> i32 {
+        a.0
+    }
+}
+
+/// Returns the number of bytes a control message with `len` bytes of data
+/// takes up in a control buffer, corresponding to `CMSG_SPACE`.
+///
+/// This function is only available on Unix.
+pub fn cmsg_space(len: usize) -> usize {
+    unsafe { libc::CMSG_SPACE(len as c_uint) as usize }
+}
+
+// Rounds `len` up to the alignment of control messages.
+fn cmsg_align(len: usize) -> usize {
+    cmsg_space(len) - cmsg_space(0)
+}
+
+// Returns the offset of the data of a control message from its header.
+fn cmsg_data_offset() -> usize {
+    unsafe { libc::CMSG_LEN(0) as usize }
+}
+
+/// A buffer of control messages to send with `MsgHdr::with_control`.
+///
+/// This takes care of the layout of the control messages, including the
+/// padding `CMSG_SPACE` requires.
+///
+/// This type is only available on Unix.
+#[derive(Debug, Clone, Default)]
+pub struct CMsgBuf {
+    buf: Vec<u8>,
+}
+
+impl CMsgBuf {
+    /// Creates an empty buffer.
+    pub fn new() -> CMsgBuf {
+        CMsgBuf { buf: Vec::new() }
+    }
+
+    /// Appends a control message of the given `level` and type `ty`, such as
+    /// `IPPROTO_IP` and `IP_TOS`, carrying `data`.
+    pub fn push(&mut self, level: i32, ty: i32, data: &[u8]) -> &mut CMsgBuf {
+        let start = self.buf.len();
+        self.buf.resize(start + cmsg_space(data.len()), 0);
+        unsafe {
+            let mut hdr: libc::cmsghdr = mem::zeroed();
+            hdr.cmsg_level = level;
+            hdr.cmsg_type = ty;
+            hdr.cmsg_len = libc::CMSG_LEN(data.len() as c_uint) as _;
+            let ptr = self.buf.as_mut_ptr().add(start);
+            ptr::write_unaligned(ptr as *mut libc::cmsghdr, hdr);
+            ptr::copy_nonoverlapping(data.as_ptr(),
+                                     ptr.add(cmsg_data_offset()),
+                                     data.len());
+        }
+        self
+    }
+
+    /// Returns the encoded control messages.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+/// A control message received with `Socket::recv_msg`.
+///
+/// This type is only available on Unix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CMsg<'a> {
+    level: i32,
+    ty: i32,
+    data: &'a [u8],
+}
+
+impl<'a> CMsg<'a> {
+    /// Returns the level of this message, `cmsg_level`, such as
+    /// `SOL_SOCKET`.
+    pub fn level(&self) -> i32 {
+        self.level
+    }
+
+    /// Returns the type of this message, `cmsg_type`, such as `SCM_RIGHTS`.
+    pub fn ty(&self) -> i32 {
+        self.ty
+    }
+
+    /// Returns the data of this message.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+/// An iterator over the control messages received with `Socket::recv_msg`,
+/// created by `MsgHdrMut::cmsgs`.
+///
+/// This type is only available on Unix.
+#[derive(Debug, Clone)]
+pub struct CMsgIter<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Iterator for CMsgIter<'a> {
+    type Item = CMsg<'a>;
+
+    fn next(&mut self) -> Option<CMsg<'a>> {
+        if self.buf.len() < mem::size_of::<libc::cmsghdr>() {
+            return None
+        }
+        // The buffer supplied by the caller might not be aligned for
+        // `cmsghdr`, so the header has to be copied out.
+        let hdr = unsafe {
+            ptr::read_unaligned(self.buf.as_ptr() as *const libc::cmsghdr)
+        };
+        let len = hdr.cmsg_len as usize;
+        if len < cmsg_data_offset() || len > self.buf.len() {
+            self.buf = &[];
+            return None
+        }
+        let data = &self.buf[cmsg_data_offset()..len];
+        self.buf = &self.buf[cmp::min(cmsg_align(len), self.buf.len())..];
+        Some(CMsg {
+            level: hdr.cmsg_level,
+            ty: hdr.cmsg_type,
+            data,
+        })
+    }
+}
+
+/// A pool of buffers which `Socket::recv_multiple_with` receives datagrams
+/// into.
+///
+/// This allows buffers to be reused across calls, and to have whatever size
+/// and alignment the caller needs, for example to receive coalesced segments
+/// with UDP GRO.
+///
+/// This trait is only available on Unix.
+pub trait BufferProvider {
+    /// The type of the buffers in this pool.
+    type Buffer: AsMut<[u8]>;
+
+    /// Takes a buffer out of the pool, or returns `None` if the pool is
+    /// exhausted.
+    fn take(&mut self) -> Option<Self::Buffer>;
+
+    /// Returns a buffer which wasn't needed to the pool.
+    fn give_back(&mut self, buf: Self::Buffer);
+}
+
+/// Storage which `Socket::recv_multiple_with` reuses across calls.
+///
+/// Receiving a batch of datagrams with a single system call needs arrays
+/// describing each buffer and source address to the system. Keeping a
+/// `RecvScratch` around lets these arrays keep their allocation between
+/// calls, so once they've grown to the batch size receiving doesn't
+/// allocate.
+///
+/// This type is only available on Unix.
+pub struct RecvScratch {
+    #[cfg(target_os = "linux")]
+    pub(crate) msgs: Vec<libc::mmsghdr>,
+    #[cfg(target_os = "linux")]
+    pub(crate) iovs: Vec<libc::iovec>,
+    #[cfg(target_os = "linux")]
+    pub(crate) addrs: Vec<MaybeUninit<sockaddr_storage>>,
+}
+
+// The arrays only hold pointers into the caller's buffers during a call, and
+// are cleared before it returns.
+unsafe impl Send for RecvScratch {}
+unsafe impl Sync for RecvScratch {}
+
+impl RecvScratch {
+    /// Creates empty storage, which grows as needed.
+    pub fn new() -> RecvScratch {
+        RecvScratch {
+            #[cfg(target_os = "linux")]
+            msgs: Vec::new(),
+            #[cfg(target_os = "linux")]
+            iovs: Vec::new(),
+            #[cfg(target_os = "linux")]
+            addrs: Vec::new(),
+        }
+    }
+}
+
+impl Default for RecvScratch {
+    fn default() -> RecvScratch {
+        RecvScratch::new()
+    }
+}
+
+impl fmt::Debug for RecvScratch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RecvScratch").finish()
+    }
+}
+
+impl<T: AsMut<[u8]>> BufferProvider for Vec<T> {
+    type Buffer = T;
+
+    fn take(&mut self) -> Option<T> {
+        self.pop()
+    }
+
+    fn give_back(&mut self, buf: T) {
+        self.push(buf)
+    }
+}
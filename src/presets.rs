@@ -0,0 +1,97 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Sockets preconfigured for common use cases.
+//!
+//! The rest of this crate maps each option to a single call and leaves it to
+//! the caller to pick the options. The functions in this module instead
+//! create a socket with a documented set of options applied, as a starting
+//! point for the cases where the choice is the same every time. The returned
+//! sockets can be configured further as usual.
+//!
+//! This module is only available when the `presets` feature is enabled.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use socket2::presets;
+//!
+//! // listen for mDNS queries and responses
+//! let socket = presets::mdns_listener().unwrap();
+//! let mut buf = [0; 9000];
+//! let (n, addr) = socket.recv_from(&mut buf).unwrap();
+//! ```
+
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+use {Domain, Socket, Type};
+
+// The multicast group and port of mDNS, from RFC 6762.
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+
+/// Creates a UDP socket receiving the datagrams sent to the IPv4 multicast
+/// group `group` on `port`, for example for service discovery on the local
+/// network.
+///
+/// The socket:
+///
+/// * sets `SO_REUSEADDR`, and `SO_REUSEPORT` where supported, so that other
+///   processes on the host can listen to the same group and port,
+/// * is bound to `0.0.0.0:port` and joins `group` on the interface chosen by
+///   the system,
+/// * enables `IP_MULTICAST_LOOP`, so that it also receives the datagrams sent
+///   by other processes on the host,
+/// * sets `IP_MULTICAST_TTL` to 1, so that the datagrams it sends don't leave
+///   the local network.
+pub fn udp_multicast_listener(group: Ipv4Addr, port: u16)
+                              -> io::Result<Socket> {
+    multicast_listener(group, port, 1)
+}
+
+/// Creates a UDP socket for mDNS, receiving the datagrams sent to
+/// `224.0.0.251:5353`.
+///
+/// This is the same as `udp_multicast_listener`, except that
+/// `IP_MULTICAST_TTL` is set to 255, which RFC 6762 requires for mDNS so
+/// that receivers can tell the datagrams came from the local network.
+pub fn mdns_listener() -> io::Result<Socket> {
+    multicast_listener(MDNS_GROUP, MDNS_PORT, 255)
+}
+
+fn multicast_listener(group: Ipv4Addr, port: u16, ttl: u32)
+                      -> io::Result<Socket> {
+    let socket = Socket::new(Domain::ipv4(), Type::dgram(), None)?;
+    socket.set_reuse_address(true)?;
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly",
+              target_os = "openbsd", target_os = "netbsd"))]
+    socket.set_reuse_port(true)?;
+    let addr = SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), port);
+    socket.bind(&addr.into())?;
+    socket.join_multicast_v4(&group, &Ipv4Addr::new(0, 0, 0, 0))?;
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_multicast_ttl_v4(ttl)?;
+    Ok(socket)
+}
+
+/// Creates a TCP socket in the given domain for latency sensitive traffic,
+/// such as request and response protocols sending small messages.
+///
+/// The socket sets `TCP_NODELAY`, so that small writes are sent right away
+/// rather than being delayed to be coalesced with later ones. It isn't
+/// connected or bound yet.
+pub fn tcp_low_latency(domain: Domain) -> io::Result<Socket> {
+    let socket = Socket::new(domain, Type::stream(), None)?;
+    socket.set_nodelay(true)?;
+    Ok(socket)
+}
@@ -0,0 +1,474 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Client support for connecting through SOCKS5 and HTTP proxies.
+//!
+//! The functions in this module perform the handshake asking a proxy to
+//! open a connection to a target on the client's behalf, over a `Socket`
+//! which is already connected to the proxy. Once they return, data written
+//! to and read from the socket is exchanged with the target.
+//!
+//! This module is only available when the `proxy-client` feature is
+//! enabled.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::net::SocketAddr;
+//! use socket2::{Socket, Domain, Type};
+//! use socket2::proxy_client::{self, Target};
+//!
+//! let socket = Socket::new(Domain::ipv4(), Type::stream(), None).unwrap();
+//! socket.connect(&"127.0.0.1:1080".parse::<SocketAddr>().unwrap().into()).unwrap();
+//!
+//! let target = Target::Host("example.com", 80);
+//! proxy_client::socks5_connect(&socket, &target, None).unwrap();
+//! socket.send(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+//! ```
+
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::str;
+
+use Socket;
+
+// The maximum length of the response to an HTTP CONNECT request, including
+// its headers.
+const HTTP_MAX_LEN: usize = 8192;
+
+/// The target a proxy is asked to connect to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target<'a> {
+    /// A socket address.
+    Addr(SocketAddr),
+    /// A host name and port, which the proxy resolves.
+    Host(&'a str, u16),
+}
+
+/// Asks the SOCKS5 proxy `socket` is connected to to connect to `target`.
+///
+/// If `auth` is set, the proxy may ask for the given user name and password
+/// to be sent, otherwise only proxies which don't require authentication can
+/// be used. Note that the password is sent as plain text. An error of kind
+/// `InvalidInput` is returned without sending anything if the user name or
+/// password is longer than 255 bytes.
+///
+/// An error of kind `ConnectionRefused` or `PermissionDenied` is returned if
+/// the proxy reports that the target refused the connection or that its
+/// rules don't allow it, and an error of kind `InvalidData` if the proxy's
+/// response is invalid.
+pub fn socks5_connect(socket: &Socket,
+                      target: &Target,
+                      auth: Option<(&str, &str)>) -> io::Result<()> {
+    let mut s = socket;
+
+    if let Some((user, password)) = auth {
+        if user.len() > 255 || password.len() > 255 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "SOCKS5 user name and password must be \
+                                       at most 255 bytes"))
+        }
+    }
+
+    // Negotiate the authentication method, offering "no authentication" and
+    // if possible "user name and password".
+    if auth.is_some() {
+        s.write_all(&[5, 2, 0, 2])?;
+    } else {
+        s.write_all(&[5, 1, 0])?;
+    }
+    let mut reply = [0; 2];
+    recv_exact(socket, &mut reply)?;
+    if reply[0] != 5 {
+        return Err(invalid("invalid SOCKS5 version in proxy response"))
+    }
+    match (reply[1], auth) {
+        (0, _) => {}
+        (2, Some((user, password))) => {
+            let mut msg = vec![1, user.len() as u8];
+            msg.extend_from_slice(user.as_bytes());
+            msg.push(password.len() as u8);
+            msg.extend_from_slice(password.as_bytes());
+            s.write_all(&msg)?;
+            recv_exact(socket, &mut reply)?;
+            if reply[0] != 1 {
+                return Err(invalid("invalid SOCKS5 user name and password \
+                                    authentication version in proxy \
+                                    response"))
+            }
+            if reply[1] != 0 {
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                                          "SOCKS5 proxy rejected the user \
+                                           name and password"))
+            }
+        }
+        _ => {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                                      "SOCKS5 proxy requires an unsupported \
+                                       authentication method"))
+        }
+    }
+
+    let mut msg = vec![5, 1, 0];
+    let port = match *target {
+        Target::Addr(SocketAddr::V4(addr)) => {
+            msg.push(1);
+            msg.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        Target::Addr(SocketAddr::V6(addr)) => {
+            msg.push(4);
+            msg.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        Target::Host(host, port) => {
+            if host.len() > 255 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "SOCKS5 host names must be at most \
+                                           255 bytes"))
+            }
+            msg.push(3);
+            msg.push(host.len() as u8);
+            msg.extend_from_slice(host.as_bytes());
+            port
+        }
+    };
+    msg.push((port >> 8) as u8);
+    msg.push(port as u8);
+    s.write_all(&msg)?;
+
+    let mut reply = [0; 5];
+    recv_exact(socket, &mut reply)?;
+    if reply[0] != 5 {
+        return Err(invalid("invalid SOCKS5 version in proxy response"))
+    }
+    let (kind, msg) = match reply[1] {
+        0 => (None, ""),
+        2 => (Some(io::ErrorKind::PermissionDenied),
+              "connection not allowed by the SOCKS5 proxy's rules"),
+        3 => (Some(io::ErrorKind::Other), "network unreachable"),
+        4 => (Some(io::ErrorKind::Other), "host unreachable"),
+        5 => (Some(io::ErrorKind::ConnectionRefused), "connection refused"),
+        6 => (Some(io::ErrorKind::TimedOut), "TTL expired"),
+        7 => (Some(io::ErrorKind::Other), "command not supported by the \
+                                           SOCKS5 proxy"),
+        8 => (Some(io::ErrorKind::Other), "address type not supported by the \
+                                           SOCKS5 proxy"),
+        _ => (Some(io::ErrorKind::Other), "SOCKS5 proxy failed to connect"),
+    };
+    if let Some(kind) = kind {
+        return Err(io::Error::new(kind, msg))
+    }
+
+    // Skip the address the proxy bound for the connection, of which the
+    // first byte was already read.
+    let rest = match reply[3] {
+        1 => 4 - 1 + 2,
+        3 => reply[4] as usize + 2,
+        4 => 16 - 1 + 2,
+        _ => return Err(invalid("invalid address type in SOCKS5 response")),
+    };
+    let mut addr = vec![0; rest];
+    recv_exact(socket, &mut addr)
+}
+
+/// Asks the HTTP proxy `socket` is connected to to connect to `target`, by
+/// sending a `CONNECT` request.
+///
+/// `headers` are added to the request, for example a `Proxy-Authorization`
+/// header for proxies which require authentication.
+///
+/// Exactly the bytes of the proxy's response are consumed, so after this
+/// returns any data read from the socket is data sent by the target. An
+/// error of kind `PermissionDenied` is returned if the proxy responds with
+/// `407 Proxy Authentication Required`, an error of kind `Other` if it
+/// responds with another status which isn't successful, and an error of kind
+/// `InvalidData` if its response is invalid. To keep the request from being
+/// tampered with, an error of kind `InvalidInput` is returned without
+/// sending anything if the host name or a header contains a CR or LF, or if
+/// a header name contains a colon.
+#[allow(clippy::io_other_error)]
+pub fn http_connect(socket: &Socket,
+                    target: &Target,
+                    headers: &[(&str, &str)]) -> io::Result<()> {
+    let authority = match *target {
+        Target::Addr(addr) => addr.to_string(),
+        Target::Host(host, port) => {
+            if has_line_break(host) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "HTTP proxy target host must not \
+                                           contain CR or LF"))
+            }
+            format!("{}:{}", host, port)
+        }
+    };
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n",
+                              authority);
+    for &(name, value) in headers {
+        if name.contains(':') || has_line_break(name) ||
+           has_line_break(value) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "invalid HTTP proxy request header"))
+        }
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    let mut s = socket;
+    s.write_all(request.as_bytes())?;
+
+    let response = read_http_response(socket)?;
+    let response = str::from_utf8(&response)
+        .map_err(|_| invalid("invalid HTTP proxy response"))?;
+    let status_line = response.lines().next().unwrap_or("");
+    let mut parts = status_line.splitn(3, ' ');
+    let version = parts.next().unwrap_or("");
+    let status = parts.next().unwrap_or("");
+    if !version.starts_with("HTTP/1.") || status.len() != 3 {
+        return Err(invalid("invalid HTTP proxy response"))
+    }
+    match status.parse::<u16>() {
+        Ok(200..=299) => Ok(()),
+        Ok(407) => {
+            Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                               "HTTP proxy requires authentication"))
+        }
+        Ok(_) => {
+            Err(io::Error::new(io::ErrorKind::Other,
+                               format!("HTTP proxy refused to connect: {}",
+                                       status_line)))
+        }
+        Err(_) => Err(invalid("invalid HTTP proxy response")),
+    }
+}
+
+fn read_http_response(socket: &Socket) -> io::Result<Vec<u8>> {
+    // The response is terminated by an empty line, so peek to find it and
+    // only consume the bytes up to and including it, like `read_header` in
+    // the `proxy_protocol` module.
+    let mut response = Vec::new();
+    let mut buf = [0; HTTP_MAX_LEN];
+    loop {
+        let room = HTTP_MAX_LEN - response.len();
+        if room == 0 {
+            return Err(invalid("HTTP proxy response too long"))
+        }
+        let n = match socket.peek(&mut buf[..room]) {
+            Ok(0) => return Err(eof()),
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        // The terminator may straddle what was already consumed and the
+        // newly peeked data.
+        let prefix = &response[response.len().saturating_sub(3)..];
+        let mut joined = prefix.to_vec();
+        joined.extend_from_slice(&buf[..n]);
+        let end = joined.windows(4)
+                        .position(|w| w == b"\r\n\r\n")
+                        .map(|i| i + 4 - prefix.len());
+        let n = match socket.recv(&mut buf[..end.unwrap_or(n)]) {
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        response.extend_from_slice(&buf[..n]);
+        if response.ends_with(b"\r\n\r\n") {
+            return Ok(response)
+        }
+    }
+}
+
+fn recv_exact(socket: &Socket, mut buf: &mut [u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        match socket.recv(buf) {
+            Ok(0) => return Err(eof()),
+            Ok(n) => {
+                let tmp = buf;
+                buf = &mut tmp[n..];
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+fn has_line_break(s: &str) -> bool {
+    s.contains('\r') || s.contains('\n')
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof,
+                   "connection closed before the proxy's response was \
+                    complete")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Write};
+    use std::net::{Shutdown, TcpListener};
+    use std::thread;
+
+    use {Domain, Socket, Type};
+    use super::*;
+
+    // Connects to a fake proxy which sends `response` and then returns the
+    // request it received once the returned socket is closed.
+    fn proxy(response: Vec<u8>) -> (Socket, thread::JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(&response).unwrap();
+            stream.shutdown(Shutdown::Write).unwrap();
+            let mut request = Vec::new();
+            let _ = stream.read_to_end(&mut request);
+            request
+        });
+        let socket = Socket::new(Domain::ipv4(), Type::stream(), None)
+            .unwrap();
+        socket.connect(&addr.into()).unwrap();
+        (socket, handle)
+    }
+
+    fn http_status(response: &str) -> io::Result<()> {
+        let (socket, _) = proxy(response.as_bytes().to_vec());
+        http_connect(&socket, &Target::Host("example.com", 443), &[])
+    }
+
+    fn socks5_status(code: u8) -> io::Result<()> {
+        let (socket, _) = proxy(vec![5, 0, 5, code, 0, 1, 0, 0, 0, 0, 0, 0]);
+        socks5_connect(&socket, &Target::Host("example.com", 80), None)
+    }
+
+    #[test]
+    fn http_connect_consumes_only_the_response() {
+        let (socket, handle) = proxy(b"HTTP/1.1 200 Connection established\r\n\
+                                       Via: proxy\r\n\r\nhello".to_vec());
+        let target = Target::Host("example.com", 443);
+        http_connect(&socket, &target, &[("Proxy-Authorization", "Basic eA==")])
+            .unwrap();
+        let mut buf = [0; 16];
+        assert_eq!(socket.recv(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"hello");
+        drop(socket);
+        assert_eq!(handle.join().unwrap(),
+                   &b"CONNECT example.com:443 HTTP/1.1\r\n\
+                      Host: example.com:443\r\n\
+                      Proxy-Authorization: Basic eA==\r\n\r\n"[..]);
+    }
+
+    #[test]
+    fn http_connect_errors() {
+        assert!(http_status("HTTP/1.0 204 No Content\r\n\r\n").is_ok());
+        assert_eq!(http_status("HTTP/1.1 407 Proxy Authentication Required\
+                                \r\n\r\n").unwrap_err().kind(),
+                   io::ErrorKind::PermissionDenied);
+        assert_eq!(http_status("HTTP/1.1 403 Forbidden\r\n\r\n")
+                       .unwrap_err().kind(),
+                   io::ErrorKind::Other);
+        assert_eq!(http_status("SSH-2.0-OpenSSH\r\n\r\n").unwrap_err().kind(),
+                   io::ErrorKind::InvalidData);
+        assert_eq!(http_status("HTTP/1.1 20 OK\r\n\r\n").unwrap_err().kind(),
+                   io::ErrorKind::InvalidData);
+        assert_eq!(http_status("HTTP/1.1 200 OK\r\n").unwrap_err().kind(),
+                   io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn http_connect_rejects_line_breaks() {
+        let socket = Socket::new(Domain::ipv4(), Type::stream(), None)
+            .unwrap();
+        let invalid = [
+            (Target::Host("example.com\r\nX: y", 80), ("A", "b")),
+            (Target::Host("example.com", 80), ("A\nB", "c")),
+            (Target::Host("example.com", 80), ("A", "b\r\nX: y")),
+            (Target::Host("example.com", 80), ("X: y", "b")),
+        ];
+        for &(ref target, header) in invalid.iter() {
+            let err = http_connect(&socket, target, &[header]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn socks5_connect_with_auth() {
+        let (socket, handle) = proxy(vec![5, 2, 1, 0,
+                                          5, 0, 0, 3, 4, b'h', b'o', b's',
+                                          b't', 0, 80,
+                                          b'h', b'i']);
+        let target = Target::Addr("127.0.0.1:80".parse().unwrap());
+        socks5_connect(&socket, &target, Some(("user", "pass"))).unwrap();
+        let mut buf = [0; 16];
+        assert_eq!(socket.recv(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"hi");
+        drop(socket);
+        assert_eq!(handle.join().unwrap(),
+                   &[5, 2, 0, 2,
+                     1, 4, b'u', b's', b'e', b'r', 4, b'p', b'a', b's', b's',
+                     5, 1, 0, 1, 127, 0, 0, 1, 0, 80][..]);
+    }
+
+    #[test]
+    fn socks5_connect_auth_errors() {
+        // The socket isn't connected, so this only passes if the lengths are
+        // checked before anything is sent.
+        let socket = Socket::new(Domain::ipv4(), Type::stream(), None)
+            .unwrap();
+        let long = "x".repeat(256);
+        let target = Target::Host("example.com", 80);
+        for &auth in [(&long[..], "pass"), ("user", &long[..])].iter() {
+            let err = socks5_connect(&socket, &target, Some(auth))
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+
+        let (socket, _) = proxy(vec![5, 2, 5, 0]);
+        let err = socks5_connect(&socket, &target, Some(("user", "pass")))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn socks5_connect_reply_codes() {
+        assert!(socks5_status(0).is_ok());
+        let codes = [
+            (1, io::ErrorKind::Other),
+            (2, io::ErrorKind::PermissionDenied),
+            (3, io::ErrorKind::Other),
+            (4, io::ErrorKind::Other),
+            (5, io::ErrorKind::ConnectionRefused),
+            (6, io::ErrorKind::TimedOut),
+            (7, io::ErrorKind::Other),
+            (8, io::ErrorKind::Other),
+        ];
+        for &(code, kind) in codes.iter() {
+            assert_eq!(socks5_status(code).unwrap_err().kind(), kind);
+        }
+    }
+
+    #[test]
+    fn socks5_connect_invalid_response() {
+        let (socket, _) = proxy(vec![4, 0]);
+        let target = Target::Host("example.com", 80);
+        assert_eq!(socks5_connect(&socket, &target, None).unwrap_err().kind(),
+                   io::ErrorKind::InvalidData);
+        let (socket, _) = proxy(vec![5, 2]);
+        assert_eq!(socks5_connect(&socket, &target, None).unwrap_err().kind(),
+                   io::ErrorKind::PermissionDenied);
+        let (socket, _) = proxy(vec![5, 0, 5, 0, 0, 9, 0]);
+        assert_eq!(socks5_connect(&socket, &target, None).unwrap_err().kind(),
+                   io::ErrorKind::InvalidData);
+    }
+}
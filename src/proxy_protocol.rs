@@ -0,0 +1,401 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Support for the PROXY protocol used by load balancers.
+//!
+//! Proxies such as HAProxy can prefix each connection they forward with a
+//! header describing the original client connection. This module reads that
+//! header, in either the text (version 1) or binary (version 2) format, from
+//! an accepted `Socket`, leaving the socket positioned at the first byte of
+//! the proxied data.
+//!
+//! This module is only available when the `proxy-protocol` feature is
+//! enabled.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::net::SocketAddr;
+//! use socket2::{Socket, Domain, Type};
+//! use socket2::proxy_protocol::{self, ProxyHeader};
+//!
+//! let listener = Socket::new(Domain::ipv4(), Type::stream(), None).unwrap();
+//! listener.bind(&"127.0.0.1:12345".parse::<SocketAddr>().unwrap().into()).unwrap();
+//! listener.listen(128).unwrap();
+//!
+//! let (socket, addr) = listener.accept().unwrap();
+//! let client = match proxy_protocol::read_header(&socket).unwrap() {
+//!     ProxyHeader::Proxied { source, .. } => source,
+//!     ProxyHeader::Local => addr.as_std().unwrap(),
+//! };
+//! ```
+
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::str;
+
+use Socket;
+
+// The maximum length of a version 1 header, including the CRLF.
+const V1_MAX_LEN: usize = 107;
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The information carried by a PROXY protocol header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyHeader {
+    /// The connection was forwarded on behalf of a client.
+    Proxied {
+        /// The address of the original client.
+        source: SocketAddr,
+        /// The address the original client connected to.
+        destination: SocketAddr,
+    },
+    /// The connection wasn't forwarded on behalf of a client, for example
+    /// because it's a health check made by the proxy itself, or the proxy
+    /// didn't know the client's address. The socket's own addresses should be
+    /// used for the connection.
+    Local,
+}
+
+/// Reads a PROXY protocol header from the start of `socket`.
+///
+/// Exactly the bytes of the header are consumed, so after this returns any
+/// data read from the socket is data sent by the client. Both version 1 and
+/// version 2 headers are supported. Version 2 type-length-value extensions
+/// are skipped, and Unix domain addresses are reported as
+/// `ProxyHeader::Local`.
+///
+/// An error of kind `InvalidData` is returned if the connection doesn't start
+/// with a valid header. As required by the PROXY protocol specification there
+/// is no way to accept connections both with and without a header.
+pub fn read_header(socket: &Socket) -> io::Result<ProxyHeader> {
+    let mut start = [0; 16];
+    recv_exact(socket, &mut start[..V1_PREFIX.len()])?;
+    if &start[..V1_PREFIX.len()] == V1_PREFIX {
+        return read_v1(socket)
+    }
+    if start[..V1_PREFIX.len()] != V2_SIGNATURE[..V1_PREFIX.len()] {
+        return Err(invalid("connection did not start with a PROXY header"))
+    }
+
+    recv_exact(socket, &mut start[V1_PREFIX.len()..])?;
+    if &start[..V2_SIGNATURE.len()] != V2_SIGNATURE {
+        return Err(invalid("invalid PROXY protocol version 2 signature"))
+    }
+    let len = (start[14] as usize) << 8 | start[15] as usize;
+    let mut rest = vec![0; len];
+    recv_exact(socket, &mut rest)?;
+    parse_v2(start[12], start[13], &rest)
+}
+
+fn read_v1(socket: &Socket) -> io::Result<ProxyHeader> {
+    // The header is terminated by a CRLF, so peek to find it and only consume
+    // the bytes up to and including it. Everything before it is part of the
+    // header, so it's fine to consume the peeked data when there's no CRLF in
+    // it yet.
+    let mut line = V1_PREFIX.to_vec();
+    let mut buf = [0; V1_MAX_LEN];
+    loop {
+        let room = V1_MAX_LEN - line.len();
+        if room == 0 {
+            return Err(invalid("PROXY protocol version 1 header too long"))
+        }
+        let n = match socket.peek(&mut buf[..room]) {
+            Ok(0) => return Err(eof()),
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        // The CR may already have been consumed with the previous data.
+        let end = if line.ends_with(b"\r") && buf[0] == b'\n' {
+            Some(1)
+        } else {
+            buf[..n].windows(2).position(|w| w == b"\r\n").map(|i| i + 2)
+        };
+        let n = match socket.recv(&mut buf[..end.unwrap_or(n)]) {
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        line.extend_from_slice(&buf[..n]);
+        if line.ends_with(b"\r\n") {
+            return parse_v1(&line[V1_PREFIX.len()..line.len() - 2])
+        }
+    }
+}
+
+fn parse_v1(line: &[u8]) -> io::Result<ProxyHeader> {
+    let line = str::from_utf8(line)
+        .map_err(|_| invalid("invalid PROXY protocol version 1 header"))?;
+    let mut parts = line.split(' ');
+    let v6 = match parts.next() {
+        Some("TCP4") => false,
+        Some("TCP6") => true,
+        Some("UNKNOWN") => return Ok(ProxyHeader::Local),
+        _ => return Err(invalid("unknown PROXY protocol version 1 protocol")),
+    };
+    let parts = parts.collect::<Vec<_>>();
+    if parts.len() != 4 {
+        return Err(invalid("invalid PROXY protocol version 1 header"))
+    }
+    let port = |s: &str| {
+        s.parse::<u16>()
+         .map_err(|_| invalid("invalid port in PROXY protocol header"))
+    };
+    let (src, dst) = if v6 {
+        let ip = |s: &str| {
+            s.parse::<Ipv6Addr>()
+             .map_err(|_| invalid("invalid address in PROXY protocol header"))
+        };
+        (SocketAddr::V6(SocketAddrV6::new(ip(parts[0])?, port(parts[2])?, 0, 0)),
+         SocketAddr::V6(SocketAddrV6::new(ip(parts[1])?, port(parts[3])?, 0, 0)))
+    } else {
+        let ip = |s: &str| {
+            s.parse::<Ipv4Addr>()
+             .map_err(|_| invalid("invalid address in PROXY protocol header"))
+        };
+        (SocketAddr::V4(SocketAddrV4::new(ip(parts[0])?, port(parts[2])?)),
+         SocketAddr::V4(SocketAddrV4::new(ip(parts[1])?, port(parts[3])?)))
+    };
+    Ok(ProxyHeader::Proxied { source: src, destination: dst })
+}
+
+fn parse_v2(version_command: u8, family: u8, addrs: &[u8])
+            -> io::Result<ProxyHeader> {
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"))
+    }
+    match version_command & 0xf {
+        0 => return Ok(ProxyHeader::Local),
+        1 => {}
+        _ => return Err(invalid("unknown PROXY protocol version 2 command")),
+    }
+
+    let port = |b: &[u8]| (b[0] as u16) << 8 | b[1] as u16;
+    match family >> 4 {
+        // AF_INET
+        1 => {
+            if addrs.len() < 12 {
+                return Err(invalid("truncated PROXY protocol version 2 header"))
+            }
+            let ip = |b: &[u8]| Ipv4Addr::new(b[0], b[1], b[2], b[3]);
+            Ok(ProxyHeader::Proxied {
+                source: SocketAddr::V4(SocketAddrV4::new(ip(&addrs[0..4]),
+                                                         port(&addrs[8..10]))),
+                destination: SocketAddr::V4(SocketAddrV4::new(ip(&addrs[4..8]),
+                                                              port(&addrs[10..12]))),
+            })
+        }
+        // AF_INET6
+        2 => {
+            if addrs.len() < 36 {
+                return Err(invalid("truncated PROXY protocol version 2 header"))
+            }
+            let ip = |b: &[u8]| {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(b);
+                Ipv6Addr::from(octets)
+            };
+            Ok(ProxyHeader::Proxied {
+                source: SocketAddr::V6(SocketAddrV6::new(ip(&addrs[0..16]),
+                                                         port(&addrs[32..34]),
+                                                         0, 0)),
+                destination: SocketAddr::V6(SocketAddrV6::new(ip(&addrs[16..32]),
+                                                              port(&addrs[34..36]),
+                                                              0, 0)),
+            })
+        }
+        // AF_UNSPEC and AF_UNIX
+        0 | 3 => Ok(ProxyHeader::Local),
+        _ => Err(invalid("unknown PROXY protocol version 2 address family")),
+    }
+}
+
+fn recv_exact(socket: &Socket, mut buf: &mut [u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        match socket.recv(buf) {
+            Ok(0) => return Err(eof()),
+            Ok(n) => {
+                let tmp = buf;
+                buf = &mut tmp[n..];
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof,
+                   "connection closed before the PROXY header was complete")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::thread;
+    use std::time::Duration;
+
+    use Socket;
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    fn proxied(source: &str, destination: &str) -> ProxyHeader {
+        ProxyHeader::Proxied {
+            source: addr(source),
+            destination: addr(destination),
+        }
+    }
+
+    #[test]
+    fn v1() {
+        assert_eq!(parse_v1(b"TCP4 192.0.2.1 198.51.100.2 56324 443").unwrap(),
+                   proxied("192.0.2.1:56324", "198.51.100.2:443"));
+        assert_eq!(parse_v1(b"TCP6 2001:db8::1 2001:db8::2 56324 443").unwrap(),
+                   proxied("[2001:db8::1]:56324", "[2001:db8::2]:443"));
+        assert_eq!(parse_v1(b"UNKNOWN").unwrap(), ProxyHeader::Local);
+        assert_eq!(parse_v1(b"UNKNOWN ::1 ::2 1 2").unwrap(),
+                   ProxyHeader::Local);
+    }
+
+    #[test]
+    fn v1_invalid() {
+        let invalid: &[&[u8]] = &[
+            b"",
+            b"UDP4 192.0.2.1 198.51.100.2 56324 443",
+            b"TCP4 192.0.2.1 198.51.100.2 56324",
+            b"TCP4 192.0.2.1 198.51.100.2 56324 443 1",
+            b"TCP4 2001:db8::1 2001:db8::2 56324 443",
+            b"TCP6 192.0.2.1 198.51.100.2 56324 443",
+            b"TCP4 192.0.2.1 198.51.100.2 65536 443",
+            b"TCP4 192.0.2.1  198.51.100.2 56324 443",
+            b"TCP4 192.0.2.1 198.51.100.2 56324 \xff",
+        ];
+        for line in invalid {
+            assert_eq!(parse_v1(line).unwrap_err().kind(),
+                       io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn v2() {
+        let v4 = [192, 0, 2, 1, 198, 51, 100, 2, 0xdc, 0x04, 0x01, 0xbb];
+        assert_eq!(parse_v2(0x21, 0x11, &v4).unwrap(),
+                   proxied("192.0.2.1:56324", "198.51.100.2:443"));
+        let mut v6 = [0; 36];
+        v6[0] = 0xfe;
+        v6[1] = 0x80;
+        v6[15] = 1;
+        v6[31] = 2;
+        v6[32..].copy_from_slice(&[0xdc, 0x04, 0x01, 0xbb]);
+        assert_eq!(parse_v2(0x21, 0x21, &v6).unwrap(),
+                   proxied("[fe80::1]:56324", "[::2]:443"));
+        // Type-length-value extensions following the addresses are skipped.
+        let mut tlvs = v4.to_vec();
+        tlvs.extend_from_slice(&[0x04, 0x00, 0x01, 0x00]);
+        assert_eq!(parse_v2(0x21, 0x11, &tlvs).unwrap(),
+                   proxied("192.0.2.1:56324", "198.51.100.2:443"));
+        assert_eq!(parse_v2(0x20, 0x00, &[]).unwrap(), ProxyHeader::Local);
+        assert_eq!(parse_v2(0x20, 0x11, &v4).unwrap(), ProxyHeader::Local);
+        assert_eq!(parse_v2(0x21, 0x00, &[]).unwrap(), ProxyHeader::Local);
+        assert_eq!(parse_v2(0x21, 0x31, &[0; 216]).unwrap(),
+                   ProxyHeader::Local);
+    }
+
+    #[test]
+    fn v2_invalid() {
+        let v4 = [192, 0, 2, 1, 198, 51, 100, 2, 0xdc, 0x04, 0x01, 0xbb];
+        let invalid: &[(u8, u8, &[u8])] = &[
+            (0x11, 0x11, &v4),
+            (0x22, 0x11, &v4),
+            (0x21, 0x41, &v4),
+            (0x21, 0x11, &v4[..11]),
+            (0x21, 0x21, &[0; 35]),
+        ];
+        for &(version_command, family, addrs) in invalid {
+            assert_eq!(parse_v2(version_command, family, addrs).unwrap_err()
+                           .kind(),
+                       io::ErrorKind::InvalidData);
+        }
+    }
+
+    // Sends each of `chunks` to an accepted socket, pausing in between so
+    // that they're likely to arrive separately.
+    fn accept_sending(chunks: &[&[u8]]) -> Socket {
+        let chunks = chunks.iter().map(|c| c.to_vec()).collect::<Vec<_>>();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            for chunk in chunks {
+                stream.write_all(&chunk).unwrap();
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+        listener.accept().unwrap().0.into()
+    }
+
+    #[test]
+    fn read_header_consumes_only_the_header() {
+        let socket = accept_sending(&[b"PROXY TCP4 192.0.2.1 198.51.100.2 \
+                                        56324 443\r\nhello"]);
+        assert_eq!(read_header(&socket).unwrap(),
+                   proxied("192.0.2.1:56324", "198.51.100.2:443"));
+        let mut buf = [0; 16];
+        assert_eq!(socket.recv(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"hello");
+
+        let socket = accept_sending(&[b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\
+                                        \x00hello"]);
+        assert_eq!(read_header(&socket).unwrap(), ProxyHeader::Local);
+        assert_eq!(socket.recv(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"hello");
+    }
+
+    #[test]
+    fn read_header_split_crlf() {
+        let socket = accept_sending(&[b"PROXY UNKNOWN\r", b"\nhello"]);
+        assert_eq!(read_header(&socket).unwrap(), ProxyHeader::Local);
+        let mut buf = [0; 16];
+        assert_eq!(socket.recv(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"hello");
+    }
+
+    #[test]
+    fn read_header_truncated() {
+        let truncated: &[&[&[u8]]] = &[
+            &[b"PROXY TCP4 192.0.2.1"],
+            &[b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0c\xc0\x00"],
+        ];
+        for chunks in truncated {
+            let socket = accept_sending(chunks);
+            assert_eq!(read_header(&socket).unwrap_err().kind(),
+                       io::ErrorKind::UnexpectedEof);
+        }
+        let socket = accept_sending(&[b"GET / HTTP/1.1\r\n\r\n"]);
+        assert_eq!(read_header(&socket).unwrap_err().kind(),
+                   io::ErrorKind::InvalidData);
+        let mut long = b"PROXY ".to_vec();
+        long.extend_from_slice(&[b'A'; 200]);
+        let socket = accept_sending(&[&long]);
+        assert_eq!(read_header(&socket).unwrap_err().kind(),
+                   io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,605 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::io;
+use std::mem::{self, MaybeUninit};
+use std::net::{Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::ptr;
+#[cfg(unix)]
+use std::ffi::OsStr;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(unix)]
+use std::path::Path;
+#[cfg(unix)]
+use std::slice;
+
+#[cfg(unix)]
+use libc::{c_int, sa_family_t, sockaddr, sockaddr_in, sockaddr_in6,
+           sockaddr_storage, sockaddr_un, socklen_t, AF_INET, AF_INET6,
+           AF_UNIX};
+#[cfg(windows)]
+use winapi::{c_int, ADDRESS_FAMILY as sa_family_t, SOCKADDR as sockaddr,
+             SOCKADDR_IN as sockaddr_in, sockaddr_in6,
+             SOCKADDR_STORAGE as sockaddr_storage, c_int as socklen_t,
+             AF_INET, AF_INET6};
+#[cfg(target_os = "linux")]
+use libc::{AF_RXRPC, SOCK_DGRAM};
+
+use sys;
+
+/// The address of a socket.
+///
+/// This is a wrapper around the system's `sockaddr_storage`, and so can hold
+/// an address of any family supported by the system. `SockAddr`s can be
+/// created from the standard library's `SocketAddr`, `SocketAddrV4` and
+/// `SocketAddrV6` types, and converted back with methods such as `as_std`.
+#[derive(Clone)]
+pub struct SockAddr {
+    storage: MaybeUninit<sockaddr_storage>,
+    len: socklen_t,
+}
+
+impl SockAddr {
+    /// Constructs a `SockAddr` from its raw components.
+    ///
+    /// The first `len` bytes pointed to by `addr` are copied into the new
+    /// address.
+    ///
+    /// # Safety
+    ///
+    /// `addr` must point to at least `len` bytes of initialized memory which
+    /// hold a socket address of the family given by its first field, as
+    /// written by the system for calls such as `getsockname`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `len` is larger than the size of
+    /// `sockaddr_storage`.
+    pub unsafe fn from_raw_parts(addr: *const sockaddr, len: socklen_t)
+                                 -> SockAddr {
+        assert!(len as usize <= mem::size_of::<sockaddr_storage>(),
+                "socket address length too large");
+        let mut storage = MaybeUninit::<sockaddr_storage>::uninit();
+        // The family is read by `family`, so make sure it's initialized even
+        // if the system didn't write one, for example for unnamed Unix
+        // sockets on some platforms.
+        (*storage.as_mut_ptr()).ss_family = 0;
+        ptr::copy_nonoverlapping(addr as *const u8,
+                                 storage.as_mut_ptr() as *mut u8,
+                                 len as usize);
+        SockAddr {
+            storage,
+            len,
+        }
+    }
+
+    /// Constructs a `SockAddr` from the raw bytes of a socket address.
+    ///
+    /// This allows using address families which this crate has no dedicated
+    /// support for, together with `Domain::other`. `bytes` is the system's
+    /// structure for the family, such as `sockaddr_un`, starting with the
+    /// family field.
+    ///
+    /// An error of kind `InvalidInput` is returned if `bytes` is larger than
+    /// a `sockaddr_storage`.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<SockAddr> {
+        if bytes.len() > mem::size_of::<sockaddr_storage>() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "socket address too large"))
+        }
+        unsafe {
+            Ok(SockAddr::from_raw_parts(bytes.as_ptr() as *const sockaddr,
+                                        bytes.len() as socklen_t))
+        }
+    }
+
+    /// Returns the raw bytes of this address, as returned by the system.
+    ///
+    /// This is the counterpart of [`from_bytes`] for reading addresses of
+    /// families which this crate has no dedicated support for.
+    ///
+    /// [`from_bytes`]: #method.from_bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            ::std::slice::from_raw_parts(self.storage.as_ptr() as *const u8,
+                                         self.len as usize)
+        }
+    }
+
+    /// Returns this address's family.
+    pub fn family(&self) -> sa_family_t {
+        unsafe { (*self.storage.as_ptr()).ss_family }
+    }
+
+    /// Returns the size of this address in bytes.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> socklen_t {
+        self.len
+    }
+
+    /// Returns a raw pointer to the address.
+    ///
+    /// Only the first [`len`] bytes of the address are valid.
+    ///
+    /// [`len`]: #method.len
+    pub fn as_ptr(&self) -> *const sockaddr {
+        self.storage.as_ptr() as *const sockaddr
+    }
+
+    /// Returns this address as a `SocketAddrV4` if it is in the `AF_INET`
+    /// family.
+    pub fn as_inet(&self) -> Option<SocketAddrV4> {
+        if self.family() as c_int != AF_INET ||
+           (self.len as usize) < mem::size_of::<sockaddr_in>() {
+            return None
+        }
+        let sa = unsafe { &*(self.storage.as_ptr() as *const sockaddr_in) };
+        Some(SocketAddrV4::new(sys::from_in_addr(sa.sin_addr),
+                               ::ntoh(sa.sin_port)))
+    }
+
+    /// Returns this address as a `SocketAddrV6` if it is in the `AF_INET6`
+    /// family.
+    pub fn as_inet6(&self) -> Option<SocketAddrV6> {
+        if self.family() as c_int != AF_INET6 ||
+           (self.len as usize) < mem::size_of::<sockaddr_in6>() {
+            return None
+        }
+        let sa = unsafe { &*(self.storage.as_ptr() as *const sockaddr_in6) };
+        Some(SocketAddrV6::new(Ipv6Addr::from(sa.sin6_addr.s6_addr),
+                               ::ntoh(sa.sin6_port),
+                               sa.sin6_flowinfo,
+                               sa.sin6_scope_id))
+    }
+
+    /// Returns this address as a `SocketAddr` if it is in the `AF_INET` or
+    /// `AF_INET6` family.
+    pub fn as_std(&self) -> Option<SocketAddr> {
+        self.as_inet().map(SocketAddr::V4)
+            .or_else(|| self.as_inet6().map(SocketAddr::V6))
+    }
+
+    /// Returns the scope identifier of this address if it is in the
+    /// `AF_INET6` family.
+    ///
+    /// For link-local addresses this is the index of the network interface
+    /// the address belongs to. It is zero for addresses without a scope.
+    pub fn scope_id(&self) -> Option<u32> {
+        self.as_inet6().map(|addr| addr.scope_id())
+    }
+
+    /// Returns the name of the network interface identified by the scope
+    /// identifier of this address, such as `eth0`.
+    ///
+    /// This is meant for displaying link-local addresses, as in
+    /// `fe80::1%eth0`, which is more meaningful than the interface index.
+    /// `None` is returned if this address isn't in the `AF_INET6` family or
+    /// has no scope, and an error if no interface has the index.
+    ///
+    /// On Unix this function is not available when the `no-ioctl` feature is
+    /// enabled, as the C library looks the name up with an `ioctl`.
+    #[cfg(any(windows, not(feature = "no-ioctl")))]
+    pub fn scope_name(&self) -> io::Result<Option<String>> {
+        match self.scope_id() {
+            Some(0) | None => Ok(None),
+            Some(index) => sys::interface_name(index).map(Some),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl SockAddr {
+    /// Constructs a `SockAddr` with the family `AF_UNIX` and the provided
+    /// path.
+    ///
+    /// An error of kind `InvalidInput` is returned if the path contains a NUL
+    /// byte or is too long to fit in a `sockaddr_un`.
+    ///
+    /// This function is only available on Unix.
+    pub fn unix<P: AsRef<Path>>(path: P) -> io::Result<SockAddr> {
+        let bytes = path.as_ref().as_os_str().as_bytes();
+        unsafe {
+            let mut addr: sockaddr_un = mem::zeroed();
+            addr.sun_family = AF_UNIX as sa_family_t;
+            if bytes.contains(&0) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "paths may not contain interior \
+                                           null bytes"))
+            }
+            // Leave room for the terminating NUL byte.
+            if bytes.len() >= addr.sun_path.len() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "path must be shorter than \
+                                           SUN_LEN"))
+            }
+            for (dst, src) in addr.sun_path.iter_mut().zip(bytes) {
+                *dst = *src as _;
+            }
+            // An empty path is an unnamed address, which has no terminator.
+            let mut len = sun_path_offset() + bytes.len();
+            if !bytes.is_empty() {
+                len += 1;
+            }
+            Ok(SockAddr::from_raw_parts(&addr as *const _ as *const sockaddr,
+                                        len as socklen_t))
+        }
+    }
+
+    /// Constructs a `SockAddr` with the family `AF_UNIX` and the provided
+    /// name in the abstract namespace.
+    ///
+    /// Abstract addresses don't exist in the filesystem and are removed
+    /// automatically once all sockets bound to them are closed. `name` may
+    /// contain any bytes, including NUL bytes, and doesn't include the
+    /// leading NUL byte which marks an address as abstract.
+    ///
+    /// An error of kind `InvalidInput` is returned if the name is too long to
+    /// fit in a `sockaddr_un`.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn unix_abstract(name: &[u8]) -> io::Result<SockAddr> {
+        unsafe {
+            let mut addr: sockaddr_un = mem::zeroed();
+            addr.sun_family = AF_UNIX as sa_family_t;
+            if name.len() >= addr.sun_path.len() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "abstract name must be shorter \
+                                           than SUN_LEN"))
+            }
+            for (dst, src) in addr.sun_path[1..].iter_mut().zip(name) {
+                *dst = *src as _;
+            }
+            let len = sun_path_offset() + 1 + name.len();
+            Ok(SockAddr::from_raw_parts(&addr as *const _ as *const sockaddr,
+                                        len as socklen_t))
+        }
+    }
+
+    /// Constructs a `SockAddr` with the family `AF_RXRPC` for the RxRPC
+    /// service `service` at `transport`, the UDP address of the peer or, for
+    /// servers, the local address to bind to.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn rxrpc(service: u16, transport: &SocketAddr) -> SockAddr {
+        unsafe {
+            let mut addr: sockaddr_rxrpc = mem::zeroed();
+            addr.srx_family = AF_RXRPC as sa_family_t;
+            addr.srx_service = service;
+            addr.transport_type = SOCK_DGRAM as u16;
+            let transport = SockAddr::from(*transport);
+            addr.transport_len = transport.len() as u16;
+            ptr::copy_nonoverlapping(transport.as_ptr() as *const u8,
+                                     &mut addr.transport as *mut _ as *mut u8,
+                                     transport.len() as usize);
+            SockAddr::from_raw_parts(&addr as *const _ as *const sockaddr,
+                                     mem::size_of_val(&addr) as socklen_t)
+        }
+    }
+
+    /// Returns the service and transport address of this address if it is
+    /// in the `AF_RXRPC` family.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn as_rxrpc(&self) -> Option<(u16, SocketAddr)> {
+        if self.family() as c_int != AF_RXRPC ||
+           (self.len as usize) < mem::size_of::<sockaddr_rxrpc>() {
+            return None
+        }
+        let addr = unsafe {
+            &*(self.storage.as_ptr() as *const sockaddr_rxrpc)
+        };
+        // `transport_len` comes from the address itself, which may have been
+        // built from arbitrary bytes, so it must stay within both the
+        // `transport` field and the bytes which were actually written.
+        let offset = &addr.transport as *const _ as usize -
+                     addr as *const _ as usize;
+        let len = addr.transport_len as usize;
+        if len > mem::size_of::<sockaddr_in6>() ||
+           offset + len > self.len as usize {
+            return None
+        }
+        let transport = unsafe {
+            SockAddr::from_raw_parts(&addr.transport as *const _ as *const _,
+                                     addr.transport_len as socklen_t)
+        };
+        transport.as_std().map(|transport| (addr.srx_service, transport))
+    }
+
+    /// Returns whether this is an unnamed address in the `AF_UNIX` family,
+    /// such as the address of a socket which isn't bound or one created with
+    /// `socketpair`.
+    ///
+    /// This function is only available on Unix.
+    pub fn is_unnamed(&self) -> bool {
+        match self.sun_path() {
+            Some(path) => path.is_empty(),
+            None => false,
+        }
+    }
+
+    /// Returns the name of this address, without the leading NUL byte, if it
+    /// is an abstract address in the `AF_UNIX` family.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn as_abstract_name(&self) -> Option<&[u8]> {
+        match self.sun_path() {
+            Some(path) if path.first() == Some(&0) => Some(&path[1..]),
+            _ => None,
+        }
+    }
+
+    /// Returns the path of this address if it is a pathname address in the
+    /// `AF_UNIX` family.
+    ///
+    /// This function is only available on Unix.
+    pub fn as_pathname(&self) -> Option<&Path> {
+        let path = self.sun_path()?;
+        // Unnamed addresses are empty and abstract ones start with a NUL.
+        match path.first() {
+            None | Some(&0) => return None,
+            Some(_) => {}
+        }
+        let end = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+        Some(Path::new(OsStr::from_bytes(&path[..end])))
+    }
+
+    // Returns the part of `sun_path` covered by this address if it's in the
+    // `AF_UNIX` family.
+    fn sun_path(&self) -> Option<&[u8]> {
+        if self.family() as c_int != AF_UNIX {
+            return None
+        }
+        let offset = sun_path_offset();
+        let len = (self.len as usize).saturating_sub(offset);
+        unsafe {
+            let start = (self.storage.as_ptr() as *const u8).add(offset);
+            Some(slice::from_raw_parts(start, len))
+        }
+    }
+}
+
+// `sockaddr_rxrpc` from linux/rxrpc.h, which isn't bound by libc. The
+// transport address is a union of `sockaddr_in` and `sockaddr_in6`.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct sockaddr_rxrpc {
+    srx_family: sa_family_t,
+    srx_service: u16,
+    transport_type: u16,
+    transport_len: u16,
+    transport: sockaddr_in6,
+}
+
+#[cfg(unix)]
+fn sun_path_offset() -> usize {
+    let addr: sockaddr_un = unsafe { mem::zeroed() };
+    addr.sun_path.as_ptr() as usize - &addr as *const _ as usize
+}
+
+impl From<SocketAddrV4> for SockAddr {
+    fn from(addr: SocketAddrV4) -> SockAddr {
+        unsafe {
+            let mut sin: sockaddr_in = mem::zeroed();
+            sin.sin_family = AF_INET as sa_family_t;
+            sin.sin_port = ::hton(addr.port());
+            sin.sin_addr = sys::to_in_addr(addr.ip());
+            SockAddr::from_raw_parts(&sin as *const _ as *const sockaddr,
+                                     mem::size_of::<sockaddr_in>() as socklen_t)
+        }
+    }
+}
+
+impl From<SocketAddrV6> for SockAddr {
+    fn from(addr: SocketAddrV6) -> SockAddr {
+        unsafe {
+            let mut sin6: sockaddr_in6 = mem::zeroed();
+            sin6.sin6_family = AF_INET6 as _;
+            sin6.sin6_port = ::hton(addr.port());
+            sin6.sin6_flowinfo = addr.flowinfo();
+            sin6.sin6_addr = sys::to_in6_addr(addr.ip());
+            sin6.sin6_scope_id = addr.scope_id();
+            SockAddr::from_raw_parts(&sin6 as *const _ as *const sockaddr,
+                                     mem::size_of::<sockaddr_in6>() as socklen_t)
+        }
+    }
+}
+
+impl From<SocketAddr> for SockAddr {
+    fn from(addr: SocketAddr) -> SockAddr {
+        match addr {
+            SocketAddr::V4(addr) => addr.into(),
+            SocketAddr::V6(addr) => addr.into(),
+        }
+    }
+}
+
+impl fmt::Debug for SockAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut f = f.debug_struct("SockAddr");
+        f.field("family", &self.family());
+        if let Some(addr) = self.as_std() {
+            f.field("addr", &addr);
+        }
+        #[cfg(unix)] {
+            if let Some(path) = self.as_pathname() {
+                f.field("path", &path);
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))] {
+                if let Some(name) = self.as_abstract_name() {
+                    f.field("abstract_name", &String::from_utf8_lossy(name));
+                }
+            }
+        }
+        f.field("len", &self.len).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::mem;
+    use std::net::SocketAddr;
+
+    use SockAddr;
+    use super::*;
+
+    // Checks that `addr` survives a round trip through its raw bytes.
+    fn round_trip(addr: &SockAddr) -> SockAddr {
+        let copy = SockAddr::from_bytes(addr.as_bytes()).unwrap();
+        assert_eq!(copy.as_bytes(), addr.as_bytes());
+        assert_eq!(copy.len(), addr.len());
+        assert_eq!(copy.family(), addr.family());
+        copy
+    }
+
+    #[test]
+    fn bytes_inet() {
+        let std = "192.0.2.1:8080".parse::<SocketAddr>().unwrap();
+        let addr = round_trip(&SockAddr::from(std));
+        assert_eq!(addr.family() as c_int, AF_INET);
+        assert_eq!(addr.len() as usize, mem::size_of::<sockaddr_in>());
+        assert_eq!(addr.as_std(), Some(std));
+    }
+
+    #[test]
+    fn bytes_inet6() {
+        let std = "[2001:db8::1%3]:8080".parse::<SocketAddr>().unwrap();
+        let addr = round_trip(&SockAddr::from(std));
+        assert_eq!(addr.family() as c_int, AF_INET6);
+        assert_eq!(addr.len() as usize, mem::size_of::<sockaddr_in6>());
+        assert_eq!(addr.as_std(), Some(std));
+        assert_eq!(addr.scope_id(), Some(3));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn bytes_unix() {
+        let addr = round_trip(&SockAddr::unix("/tmp/socket2.sock").unwrap());
+        assert_eq!(addr.family() as c_int, AF_UNIX);
+        assert_eq!(addr.as_pathname(), Some(Path::new("/tmp/socket2.sock")));
+        assert_eq!(addr.as_std(), None);
+    }
+
+    #[test]
+    fn bytes_short() {
+        let addr = SockAddr::from_bytes(&[]).unwrap();
+        assert_eq!(addr.len(), 0);
+        assert_eq!(addr.family(), 0);
+        assert!(addr.as_bytes().is_empty());
+        assert_eq!(addr.as_std(), None);
+
+        // A truncated address keeps its family but isn't read past its end.
+        let std = "192.0.2.1:8080".parse::<SocketAddr>().unwrap();
+        let full = SockAddr::from(std);
+        let addr = SockAddr::from_bytes(&full.as_bytes()[..4]).unwrap();
+        assert_eq!(addr.family() as c_int, AF_INET);
+        assert_eq!(addr.as_bytes(), &full.as_bytes()[..4]);
+        assert_eq!(addr.as_inet(), None);
+    }
+
+    #[test]
+    fn bytes_oversized() {
+        let max = mem::size_of::<sockaddr_storage>();
+        let addr = SockAddr::from_bytes(&vec![0; max]).unwrap();
+        assert_eq!(addr.len() as usize, max);
+        let err = SockAddr::from_bytes(&vec![0; max + 1]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn bytes_rxrpc_oversized_transport() {
+        let std = "192.0.2.1:8080".parse::<SocketAddr>().unwrap();
+        let addr = round_trip(&SockAddr::rxrpc(7, &std));
+        assert_eq!(addr.as_rxrpc(), Some((7, std)));
+
+        // `transport_len` follows the family, service and transport type.
+        let mut bytes = addr.as_bytes().to_vec();
+        for &len in &[29u16, 128, 129, u16::MAX] {
+            bytes[6..8].copy_from_slice(&len.to_ne_bytes());
+            let addr = SockAddr::from_bytes(&bytes).unwrap();
+            assert_eq!(addr.as_rxrpc(), None);
+        }
+    }
+
+    // Builds the raw bytes of a pathname `AF_UNIX` address by hand, the way
+    // a user of a family without dedicated support would.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn raw_unix(path: &Path) -> Vec<u8> {
+        let family = AF_UNIX as sa_family_t;
+        let mut bytes = unsafe {
+            slice::from_raw_parts(&family as *const _ as *const u8,
+                                  mem::size_of::<sa_family_t>()).to_vec()
+        };
+        bytes.extend_from_slice(path.as_os_str().as_bytes());
+        bytes.push(0);
+        bytes
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn domain_other() {
+        use std::env;
+        use std::fs;
+        use std::io::IoSlice;
+        use std::process;
+
+        use {Domain, MsgHdr, Socket, Type};
+
+        assert_eq!(i32::from(Domain::other(AF_UNIX)), AF_UNIX);
+        let dir = env::temp_dir();
+        let server_path = dir.join(format!("socket2-{}-other-server",
+                                           process::id()));
+        let client_path = dir.join(format!("socket2-{}-other-client",
+                                           process::id()));
+        let _ = fs::remove_file(&server_path);
+        let _ = fs::remove_file(&client_path);
+        let server_bytes = raw_unix(&server_path);
+        let client_bytes = raw_unix(&client_path);
+        let server_addr = SockAddr::from_bytes(&server_bytes).unwrap();
+        let client_addr = SockAddr::from_bytes(&client_bytes).unwrap();
+
+        let server = Socket::new(Domain::other(AF_UNIX), Type::dgram(), None)
+            .unwrap();
+        server.bind(&server_addr).unwrap();
+        let client = Socket::new(Domain::other(AF_UNIX), Type::dgram(), None)
+            .unwrap();
+        client.bind(&client_addr).unwrap();
+        client.connect(&server_addr).unwrap();
+        assert_eq!(client.local_addr().unwrap().as_bytes(), &client_bytes[..]);
+        assert_eq!(client.peer_addr().unwrap().as_bytes(), &server_bytes[..]);
+
+        let bufs = [IoSlice::new(b"ping")];
+        assert_eq!(client.send_msg(&MsgHdr::new().with_buffers(&bufs))
+                       .unwrap(), 4);
+        let mut buf = [0; 16];
+        let (n, from) = server.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"ping");
+        assert_eq!(from.as_bytes(), &client_bytes[..]);
+
+        // Reply to the address exactly as it was received.
+        let from = SockAddr::from_bytes(from.as_bytes()).unwrap();
+        let bufs = [IoSlice::new(b"pong")];
+        let msg = MsgHdr::new().with_addr(&from).with_buffers(&bufs);
+        assert_eq!(server.send_msg(&msg).unwrap(), 4);
+        let (n, from) = client.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"pong");
+        assert_eq!(from.as_bytes(), &server_bytes[..]);
+
+        fs::remove_file(&server_path).unwrap();
+        fs::remove_file(&client_path).unwrap();
+    }
+}
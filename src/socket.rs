@@ -8,10 +8,22 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::cmp;
 use std::fmt;
-use std::io::{self, Read, Write};
-use std::net::{self, SocketAddr, Ipv4Addr, Ipv6Addr, Shutdown};
-use std::time::Duration;
+#[cfg(unix)]
+use std::fs;
+use std::io::{self, Read, Write, IoSlice, IoSliceMut};
+use std::mem::MaybeUninit;
+use std::net::{self, IpAddr, Ipv4Addr, Ipv6Addr, Shutdown};
+#[cfg(windows)]
+use std::ops::BitOr;
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+use std::time::{Duration, Instant};
+#[cfg(all(target_os = "linux", not(feature = "no-ioctl")))]
+use std::time::SystemTime;
 
 #[cfg(unix)]
 use libc as c;
@@ -19,7 +31,33 @@ use libc as c;
 use winapi as c;
 
 use sys;
-use {Socket, Protocol, Domain, Type};
+use utils::{advance_slices, advance_slices_mut, as_uninit_mut};
+use {Socket, SockAddr, SocketOptions, Metadata, Protocol, Domain, Type};
+use {Ipv4Header, TcpKeepalive};
+#[cfg(all(feature = "quic",
+          any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd")))]
+use QuicFeatures;
+#[cfg(any(target_os = "linux", target_os = "freebsd", windows))]
+use {TcpInfo, TcpState};
+#[cfg(unix)]
+use {BufferProvider, MsgHdr, MsgHdrMut, RecvScratch};
+#[cfg(any(target_os = "linux", target_os = "android",
+          target_os = "macos", target_os = "ios",
+          target_os = "freebsd"))]
+use {msg::RECV_MSG_CONTROL_LEN, RecvMsg};
+#[cfg(target_os = "linux")]
+use {CanFilter, MemInfo, MulticastMode, RxrpcSecurityLevel};
+#[cfg(target_os = "freebsd")]
+use TimestampClock;
+#[cfg(windows)]
+use {Event, NetworkEvents};
+#[cfg(any(target_os = "linux", target_os = "android",
+      target_os = "macos", target_os = "ios",
+      target_os = "freebsd", target_os = "dragonfly",
+      target_os = "openbsd", target_os = "netbsd"))]
+use UCred;
 
 impl Socket {
     /// Creates a new socket ready to be configured.
@@ -36,6 +74,46 @@ impl Socket {
         })
     }
 
+    /// Creates a new Multipath TCP stream socket in `domain`, falling back
+    /// to a plain TCP socket if the system doesn't support Multipath TCP.
+    ///
+    /// On Linux and Android this tries `Protocol::mptcp` first, and falls
+    /// back if the kernel is built without Multipath TCP or it's disabled
+    /// with the `net.mptcp.enabled` sysctl. Elsewhere a plain TCP socket is
+    /// always created. Even a Multipath TCP socket falls back to plain TCP
+    /// for connections with peers which don't support it, which
+    /// [`is_multipath`] tells apart.
+    ///
+    /// [`is_multipath`]: #method.is_multipath
+    pub fn new_mptcp(domain: Domain) -> io::Result<Socket> {
+        #[cfg(any(target_os = "linux", target_os = "android"))] {
+            let mptcp = Some(Protocol::mptcp());
+            match Socket::new(Domain(domain.0), Type::stream(), mptcp) {
+                Ok(socket) => return Ok(socket),
+                Err(ref e) if e.raw_os_error() == Some(c::EPROTONOSUPPORT) ||
+                              e.raw_os_error() == Some(c::ENOPROTOOPT) ||
+                              e.raw_os_error() == Some(c::EINVAL) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Socket::new(domain, Type::stream(), None)
+    }
+
+    /// Creates a pair of sockets which are connected to each other.
+    ///
+    /// This function corresponds to `socketpair(2)`. Both sockets have the
+    /// close-on-exec flag set.
+    ///
+    /// This function is only available on Unix.
+    #[cfg(unix)]
+    pub fn pair(domain: Domain,
+                type_: Type,
+                protocol: Option<Protocol>) -> io::Result<(Socket, Socket)> {
+        let protocol = protocol.map(|p| p.0).unwrap_or(0);
+        let (a, b) = sys::Socket::pair(domain.0, type_.0, protocol)?;
+        Ok((Socket { inner: a }, Socket { inner: b }))
+    }
+
     /// Consumes this `Socket`, converting it to a `TcpStream`.
     pub fn into_tcp_stream(self) -> net::TcpStream {
         self.into()
@@ -58,18 +136,220 @@ impl Socket {
     ///
     /// An error will be returned if `listen` or `connect` has already been
     /// called on this builder.
-    pub fn connect(&self, addr: &SocketAddr) -> io::Result<()> {
+    pub fn connect(&self, addr: &SockAddr) -> io::Result<()> {
         self.inner.connect(addr)
     }
 
+    /// Initiate a connection on this socket to the specified address, only
+    /// waiting for `timeout` for the connection to be established.
+    ///
+    /// Unlike calling [`connect`] with a write timeout, this performs a
+    /// nonblocking connect, waits for it to complete with `poll` on Unix and
+    /// `select` on Windows, and then checks `SO_ERROR` for its result. An
+    /// error of kind `TimedOut` is returned if the connection isn't
+    /// established in time, and one of kind `InvalidInput` if `timeout` is
+    /// zero.
+    ///
+    /// The socket is put back into its previous blocking mode afterwards. On
+    /// Windows, where the mode can't be queried, this is the mode last set
+    /// through [`set_nonblocking`], and sockets created from a raw socket
+    /// are assumed to be blocking.
+    ///
+    /// [`connect`]: #method.connect
+    /// [`set_nonblocking`]: #method.set_nonblocking
+    pub fn connect_timeout(&self, addr: &SockAddr, timeout: Duration)
+                           -> io::Result<()> {
+        if timeout == Duration::from_secs(0) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "cannot set a 0 duration timeout"))
+        }
+        self.inner.connect_timeout(addr, timeout)
+    }
+
+    /// Binds this socket to `local` and connects it to `remote`, waiting at
+    /// most `timeout` for the connection to be established.
+    ///
+    /// This is the combination used for TCP hole punching through NATs: both
+    /// peers connect to each other from the fixed local port that they
+    /// already announced, for example through a rendezvous server, which may
+    /// be in use by another socket at the same time. To allow this,
+    /// `SO_REUSEADDR` and, on the platforms supporting it, `SO_REUSEPORT` are
+    /// set before the socket is bound. The connection is then made as with
+    /// [`connect_timeout`], and succeeds either normally or through a TCP
+    /// simultaneous open when both peers' connection attempts cross.
+    ///
+    /// A failed or timed out attempt leaves the socket in an unspecified
+    /// state, so a new socket should be used to try again.
+    ///
+    /// [`connect_timeout`]: #method.connect_timeout
+    pub fn connect_from(&self, local: &SockAddr, remote: &SockAddr,
+                        timeout: Duration) -> io::Result<()> {
+        self.set_reuse_address(true)?;
+        #[cfg(any(target_os = "linux", target_os = "android",
+                  target_os = "macos", target_os = "ios",
+                  target_os = "freebsd", target_os = "dragonfly",
+                  target_os = "openbsd", target_os = "netbsd"))]
+        self.set_reuse_port(true)?;
+        self.bind(local)?;
+        self.connect_timeout(remote, timeout)
+    }
+
+    /// Binds this socket to port 0 of `ip`, letting the system pick an
+    /// ephemeral port, and returns the address it was bound to.
+    ///
+    /// This is a convenience for calling [`bind`] followed by [`local_addr`]
+    /// when the chosen port is needed right away, for example to exchange
+    /// candidate addresses with a peer when traversing NATs.
+    ///
+    /// [`bind`]: #method.bind
+    /// [`local_addr`]: #method.local_addr
+    #[allow(clippy::io_other_error)]
+    pub fn bind_ephemeral(&self, ip: IpAddr) -> io::Result<net::SocketAddr> {
+        let addr = net::SocketAddr::new(ip, 0).into();
+        self.bind_returning_addr(&addr)?.as_std().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Other,
+                           "socket is not bound to an IP address")
+        })
+    }
+
+    /// Creates a new socket of the same domain, type and protocol as this
+    /// one, bound to the same local address.
+    ///
+    /// For UDP hole punching this allows talking to several peers, or
+    /// retrying, from the local port whose NAT mapping was already learned,
+    /// without disturbing this socket. The new socket has `SO_REUSEADDR`
+    /// and, on the platforms supporting it, `SO_REUSEPORT` set before it is
+    /// bound. Most systems only allow the address to be shared if this
+    /// socket had the same options set before it was bound, as
+    /// [`connect_from`] does.
+    ///
+    /// [`connect_from`]: #method.connect_from
+    pub fn rebind_same_port(&self) -> io::Result<Socket> {
+        let metadata = self.metadata()?;
+        let addr = self.local_addr()?;
+        let socket = Socket::new(metadata.domain(), metadata.ty(),
+                                 metadata.protocol())?;
+        socket.set_reuse_address(true)?;
+        #[cfg(any(target_os = "linux", target_os = "android",
+                  target_os = "macos", target_os = "ios",
+                  target_os = "freebsd", target_os = "dragonfly",
+                  target_os = "openbsd", target_os = "netbsd"))]
+        socket.set_reuse_port(true)?;
+        socket.bind(&addr)?;
+        Ok(socket)
+    }
+
     /// Binds this socket to the specified address.
     ///
     /// This function directly corresponds to the bind(2) function on Windows
     /// and Unix.
-    pub fn bind(&self, addr: &SocketAddr) -> io::Result<()> {
+    pub fn bind(&self, addr: &SockAddr) -> io::Result<()> {
         self.inner.bind(addr)
     }
 
+    /// Binds this socket to the specified address and returns the address
+    /// it was actually bound to.
+    ///
+    /// This is a combination of [`bind`] and [`local_addr`], which is useful
+    /// when the system picks part of the address, such as the port when
+    /// binding to port 0.
+    ///
+    /// [`bind`]: #method.bind
+    /// [`local_addr`]: #method.local_addr
+    pub fn bind_returning_addr(&self, addr: &SockAddr)
+                               -> io::Result<SockAddr> {
+        self.bind(addr)?;
+        self.local_addr()
+    }
+
+    /// Binds this Unix domain socket to the path of `addr`, removing the
+    /// socket file left behind by a server which is no longer running.
+    ///
+    /// Binding fails if the file exists, even if no socket is bound to it
+    /// anymore. In that case this connects to the path with a new socket of
+    /// the same type, and only if the connection is refused, meaning that
+    /// nothing is bound to the file, it's removed and the bind is retried.
+    /// The original error of kind `AddrInUse` is returned if the path is
+    /// still in use or if the file isn't a socket, which is never removed.
+    /// Addresses which aren't pathnames, such as abstract ones, are simply
+    /// bound as with [`bind`].
+    ///
+    /// Note that two servers doing this at the same time can both remove the
+    /// file, and end up with only one of them reachable.
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// [`bind`]: #method.bind
+    #[cfg(unix)]
+    pub fn bind_reclaiming_stale(&self, addr: &SockAddr) -> io::Result<()> {
+        let path = match addr.as_pathname() {
+            Some(path) => path,
+            None => return self.bind(addr),
+        };
+        let err = match self.bind(addr) {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        if err.kind() != io::ErrorKind::AddrInUse {
+            return Err(err)
+        }
+        match fs::symlink_metadata(path) {
+            Ok(ref metadata) if metadata.file_type().is_socket() => {}
+            _ => return Err(err),
+        }
+        // The probe doesn't block, so that a listener with a full backlog is
+        // considered alive rather than waited for.
+        let probe = Socket::new(Domain::unix(), self.metadata()?.ty(), None)?;
+        probe.set_nonblocking(true)?;
+        match probe.connect(addr) {
+            Err(ref e) if e.kind() == io::ErrorKind::ConnectionRefused => {}
+            _ => return Err(err),
+        }
+        match fs::remove_file(path) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            res => res?,
+        }
+        self.bind(addr)
+    }
+
+    /// Returns whether the socket file this Unix domain socket is bound to is
+    /// removed when it's dropped.
+    ///
+    /// For more information about this option, see
+    /// [`set_unlink_on_drop`][link].
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// [link]: #method.set_unlink_on_drop
+    #[cfg(unix)]
+    pub fn unlink_on_drop(&self) -> bool {
+        self.inner.unlink_on_drop()
+    }
+
+    /// Sets whether the socket file this Unix domain socket is bound to is
+    /// removed when it's dropped.
+    ///
+    /// The file otherwise stays behind after the socket is closed, and makes
+    /// binding to the path fail until it's removed. This has to be enabled
+    /// after binding, and an error of kind `InvalidInput` is returned if the
+    /// socket isn't bound to a pathname. The file is only removed if it's
+    /// still the one which existed when this was enabled, and not one created
+    /// by another socket since.
+    ///
+    /// The file isn't removed when the socket is converted into a raw
+    /// descriptor or a standard library type, when a clone made with
+    /// [`try_clone`] is dropped, or if the process is killed. Servers can
+    /// use [`bind_reclaiming_stale`] to recover from the latter.
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// [`try_clone`]: #method.try_clone
+    /// [`bind_reclaiming_stale`]: #method.bind_reclaiming_stale
+    #[cfg(unix)]
+    pub fn set_unlink_on_drop(&self, unlink: bool) -> io::Result<()> {
+        self.inner.set_unlink_on_drop(unlink)
+    }
+
     /// Mark a socket as ready to accept incoming connection requests using
     /// accept()
     ///
@@ -82,27 +362,143 @@ impl Socket {
         self.inner.listen(backlog)
     }
 
+    /// Applies backpressure to new connections on this listening socket
+    /// without closing it.
+    ///
+    /// This calls listen(2) again with a backlog of zero. Once the kernel's
+    /// accept queue is full further connection attempts are left pending, and
+    /// are retried by the client, rather than being refused, so no connection
+    /// is lost while the server is overloaded. Connections which are already
+    /// queued can still be accepted. Note that some systems still queue a
+    /// few connections with a zero backlog.
+    ///
+    /// Use [`resume_accepting`] to restore the backlog.
+    ///
+    /// This function is only available on Unix, as Windows doesn't allow
+    /// changing the backlog of a listening socket.
+    ///
+    /// [`resume_accepting`]: #method.resume_accepting
+    #[cfg(unix)]
+    pub fn pause_accepting(&self) -> io::Result<()> {
+        self.inner.listen(0)
+    }
+
+    /// Restores the backlog of a listening socket paused with
+    /// [`pause_accepting`].
+    ///
+    /// `backlog` should normally be the value originally passed to
+    /// [`listen`].
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// [`pause_accepting`]: #method.pause_accepting
+    /// [`listen`]: #method.listen
+    #[cfg(unix)]
+    pub fn resume_accepting(&self, backlog: i32) -> io::Result<()> {
+        self.inner.listen(backlog)
+    }
+
     /// Accept a new incoming connection from this listener.
     ///
     /// This function will block the calling thread until a new connection is
     /// established. When established, the corresponding `Socket` and the
     /// remote peer's address will be returned.
-    pub fn accept(&self) -> io::Result<(Socket, SocketAddr)> {
+    pub fn accept(&self) -> io::Result<(Socket, SockAddr)> {
         self.inner.accept().map(|(socket, addr)| {
             (Socket { inner: socket }, addr)
         })
     }
 
+    /// Accept a new incoming connection from this listener if `condition`
+    /// returns `true` for the remote peer's address.
+    ///
+    /// This function corresponds to `WSAAccept` with a condition function.
+    /// If the `SO_CONDITIONAL_ACCEPT` option is set on this listener, see
+    /// [`set_conditional_accept`], connections are only completed once
+    /// they're accepted, so rejected peers never see the connection being
+    /// established. A rejected connection is reset and this function returns
+    /// an error with `WSAECONNREFUSED`, so the caller can go on to accept the
+    /// next one.
+    ///
+    /// This function is only available on Windows.
+    ///
+    /// [`set_conditional_accept`]: #method.set_conditional_accept
+    #[cfg(windows)]
+    pub fn accept_conditional<F>(&self, condition: F)
+                                 -> io::Result<(Socket, SockAddr)>
+        where F: FnMut(&SockAddr) -> bool
+    {
+        self.inner.accept_conditional(condition).map(|(socket, addr)| {
+            (Socket { inner: socket }, addr)
+        })
+    }
+
+    /// Gets the value of the `SO_CONDITIONAL_ACCEPT` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_conditional_accept`][link].
+    ///
+    /// This function is only available on Windows.
+    ///
+    /// [link]: #method.set_conditional_accept
+    #[cfg(windows)]
+    pub fn conditional_accept(&self) -> io::Result<bool> {
+        self.inner.conditional_accept()
+    }
+
+    /// Sets the value of the `SO_CONDITIONAL_ACCEPT` option on this socket.
+    ///
+    /// If set, incoming connections aren't completed by the system before
+    /// they're accepted with [`accept_conditional`], which allows rejecting
+    /// them based on the peer's address before the handshake finishes. This
+    /// has to be set before the socket starts listening.
+    ///
+    /// This function is only available on Windows.
+    ///
+    /// [`accept_conditional`]: #method.accept_conditional
+    #[cfg(windows)]
+    pub fn set_conditional_accept(&self, conditional: bool) -> io::Result<()> {
+        self.inner.set_conditional_accept(conditional)
+    }
+
+    /// Accept a new incoming connection from this listener with `accept4`,
+    /// passing `flags` such as `SOCK_NONBLOCK`.
+    ///
+    /// `SOCK_CLOEXEC` is always added to `flags`, so the accepted socket is
+    /// atomically made non-inheritable, and with `SOCK_NONBLOCK` it's also
+    /// nonblocking from the start, without any extra system calls.
+    ///
+    /// This function is only available on Linux and the BSDs other than
+    /// macOS and iOS.
+    #[cfg(any(target_os = "linux", target_os = "freebsd",
+              target_os = "dragonfly", target_os = "netbsd",
+              target_os = "openbsd"))]
+    pub fn accept_with_flags(&self, flags: i32)
+                             -> io::Result<(Socket, SockAddr)> {
+        self.inner.accept_with_flags(flags).map(|(socket, addr)| {
+            (Socket { inner: socket }, addr)
+        })
+    }
+
     /// Returns the socket address of the local half of this TCP connection.
-    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+    pub fn local_addr(&self) -> io::Result<SockAddr> {
         self.inner.local_addr()
     }
 
     /// Returns the socket address of the remote peer of this TCP connection.
-    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+    pub fn peer_addr(&self) -> io::Result<SockAddr> {
         self.inner.peer_addr()
     }
 
+    /// Returns the communication domain, type and protocol of this socket.
+    ///
+    /// These are looked up the first time they're needed and cached
+    /// afterwards, so repeated calls don't cost a system call each. The
+    /// protocol isn't reported by all platforms.
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        self.inner.metadata()
+    }
+
     /// Creates a new independently owned handle to the underlying socket.
     ///
     /// The returned `TcpStream` is a reference to the same stream that this
@@ -130,6 +526,72 @@ impl Socket {
         self.inner.set_nonblocking(nonblocking)
     }
 
+    /// Returns whether the close-on-exec flag (`FD_CLOEXEC`) is set on this
+    /// socket.
+    ///
+    /// For more information about this flag, see [`set_cloexec`][link].
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// [link]: #method.set_cloexec
+    #[cfg(unix)]
+    pub fn cloexec(&self) -> io::Result<bool> {
+        self.inner.cloexec()
+    }
+
+    /// Sets or clears the close-on-exec flag (`FD_CLOEXEC`) on this socket.
+    ///
+    /// All sockets created by this crate have the flag set, so they aren't
+    /// leaked into child processes. Clearing it allows the socket to be
+    /// passed to a child process on purpose, for example to hand a listening
+    /// socket over to a new version of a server.
+    ///
+    /// This function is only available on Unix.
+    #[cfg(unix)]
+    pub fn set_cloexec(&self, close_on_exec: bool) -> io::Result<()> {
+        self.inner.set_cloexec(close_on_exec)
+    }
+
+    /// Performs the `ioctl` operation `request` on this socket, passing `arg`
+    /// as its argument.
+    ///
+    /// This allows operations which aren't covered by other methods to be
+    /// performed without extracting the socket's file descriptor. On success
+    /// returns the value returned by `ioctl`.
+    ///
+    /// This function is only available on Unix, and not when the `no-ioctl`
+    /// feature is enabled.
+    ///
+    /// # Safety
+    ///
+    /// `arg` must be valid for the operation `request`, which usually means
+    /// it has to point to a value of the type the operation reads or writes.
+    /// The operation must not invalidate the socket, for example by closing
+    /// its file descriptor.
+    #[cfg(all(unix, not(feature = "no-ioctl")))]
+    pub unsafe fn ioctl(&self, request: u64, arg: *mut c::c_void)
+                        -> io::Result<i32> {
+        self.inner.ioctl(request, arg)
+    }
+
+    /// Performs the `fcntl` command `cmd` on this socket, passing the integer
+    /// `arg` as its argument.
+    ///
+    /// This allows commands which aren't covered by other methods, such as
+    /// `F_SETOWN`, to be performed without extracting the socket's file
+    /// descriptor. On success returns the value returned by `fcntl`.
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// # Safety
+    ///
+    /// `cmd` must take an integer argument, and must not invalidate the
+    /// socket or change its state in a way other methods don't expect.
+    #[cfg(unix)]
+    pub unsafe fn fcntl(&self, cmd: i32, arg: i32) -> io::Result<i32> {
+        self.inner.fcntl(cmd, arg)
+    }
+
     /// Shuts down the read, write, or both halves of this connection.
     ///
     /// This function will cause all pending and future I/O on the specified
@@ -149,6 +611,52 @@ impl Socket {
         self.inner.recv(buf)
     }
 
+    /// Identical to [`recv`] but receives into a possibly uninitialized
+    /// buffer, so it doesn't need to be zeroed first.
+    ///
+    /// On success, returns the number of bytes read, and the first that many
+    /// bytes of `buf` are guaranteed to be initialized.
+    ///
+    /// [`recv`]: #method.recv
+    pub fn recv_uninit(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+        self.inner.recv_with_flags(buf, 0)
+    }
+
+    /// Identical to [`recv`] but allows for specification of arbitrary flags
+    /// to the underlying `recv` call, such as `MSG_DONTWAIT` or `MSG_WAITALL`.
+    ///
+    /// [`recv`]: #method.recv
+    pub fn recv_with_flags(&self, buf: &mut [u8], flags: i32)
+                           -> io::Result<usize> {
+        self.inner.recv_with_flags(as_uninit_mut(buf), flags)
+    }
+
+    /// Receives out-of-band (urgent) data on this socket, using the
+    /// `MSG_OOB` flag.
+    ///
+    /// TCP only supports a single byte of urgent data at a time. This fails
+    /// if no out-of-band data is pending or if [`set_out_of_band_inline`] is
+    /// enabled.
+    ///
+    /// [`set_out_of_band_inline`]: #method.set_out_of_band_inline
+    pub fn recv_out_of_band(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.recv_out_of_band(buf)
+    }
+
+    /// Returns whether the read position of this socket is at the
+    /// out-of-band mark, using the `SIOCATMARK` ioctl.
+    ///
+    /// On Unix this returns `true` if the next byte read is the first byte
+    /// after the urgent data was sent. On Windows it returns `true` if there
+    /// is no out-of-band data waiting to be read.
+    ///
+    /// On Unix this function is not available when the `no-ioctl` feature is
+    /// enabled.
+    #[cfg(any(windows, not(feature = "no-ioctl")))]
+    pub fn at_mark(&self) -> io::Result<bool> {
+        self.inner.at_mark()
+    }
+
     /// Receives data on the socket from the remote adress to which it is
     /// connected, without removing that data from the queue. On success,
     /// returns the number of bytes peeked.
@@ -156,15 +664,312 @@ impl Socket {
     /// Successive calls return the same data. This is accomplished by passing
     /// `MSG_PEEK` as a flag to the underlying `recv` system call.
     pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.peek(as_uninit_mut(buf))
+    }
+
+    /// Waits until `buf.len()` bytes can be peeked from the socket and peeks
+    /// them into `buf`, failing with a `TimedOut` error if that doesn't
+    /// happen within `timeout`.
+    ///
+    /// This is intended for stream sockets, to look at the start of a
+    /// connection before deciding how to handle it, for example to tell TLS
+    /// from plain HTTP on an accepted socket. The data stays queued for the
+    /// handler to read. An error of kind `InvalidInput` is returned if
+    /// `timeout` is too large to compute a deadline from.
+    ///
+    /// On Unix this sets `SO_RCVLOWAT` to `buf.len()` while waiting, so the
+    /// socket only becomes readable once enough data has arrived, and
+    /// restores the previous value afterwards. An error of kind
+    /// `UnexpectedEof` is returned if the connection is closed before that.
+    /// This relies on the socket honoring `SO_RCVLOWAT` when polled, as TCP
+    /// sockets do; on Linux, Unix domain sockets don't, and fail with
+    /// `UnexpectedEof` as soon as only part of the data has arrived.
+    ///
+    /// Windows has no equivalent option, so there this sleeps between
+    /// attempts while only part of the data has arrived, with a backoff of
+    /// up to 50 milliseconds. If the connection is closed after only part of
+    /// the data arrived this can't be told apart from the peer being slow,
+    /// and the call times out.
+    pub fn peek_exact(&self, buf: &mut [u8], timeout: Duration)
+                      -> io::Result<()> {
+        let deadline = match Instant::now().checked_add(timeout) {
+            Some(deadline) => deadline,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                              "timeout is too large")),
+        };
+        self.inner.peek_exact(buf, deadline)
+    }
+
+    /// Identical to [`peek`] but peeks into a possibly uninitialized buffer,
+    /// so it doesn't need to be zeroed first.
+    ///
+    /// On success, the first as many bytes of `buf` as were peeked are
+    /// guaranteed to be initialized.
+    ///
+    /// [`peek`]: #method.peek
+    pub fn peek_uninit(&self, buf: &mut [MaybeUninit<u8>])
+                       -> io::Result<usize> {
         self.inner.peek(buf)
     }
 
+    /// Returns the size of the next datagram queued on this socket, without
+    /// removing it from the queue.
+    ///
+    /// This allows a buffer of exactly the right size to be allocated before
+    /// calling [`recv`]. On Linux and Android this passes `MSG_PEEK` and
+    /// `MSG_TRUNC` to `recv`, and so waits for a datagram like [`peek`]. On
+    /// other platforms this uses the `FIONREAD` ioctl, which doesn't wait and
+    /// returns 0 if nothing is queued. On Unix platforms other than Linux and
+    /// Android it returns the total size of all queued datagrams, which is
+    /// still large enough to hold the next one.
+    ///
+    /// On Unix platforms other than Linux and Android this function is not
+    /// available when the `no-ioctl` feature is enabled.
+    ///
+    /// [`recv`]: #method.recv
+    /// [`peek`]: #method.peek
+    #[cfg(any(windows, target_os = "linux", target_os = "android",
+              not(feature = "no-ioctl")))]
+    pub fn peek_len(&self) -> io::Result<usize> {
+        self.inner.peek_len()
+    }
+
+    /// Receives data on the socket from the remote address to which it is
+    /// connected, filling the buffers in `bufs` in order.
+    ///
+    /// This function corresponds to `recvmsg` on Unix and `WSARecv` on
+    /// Windows, so all the buffers are filled with a single system call.
+    /// On success, returns the total number of bytes read.
+    pub fn recv_vectored(&self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        self.inner.recv_vectored(bufs)
+    }
+
+    /// Receives data on the socket until all the buffers in `bufs` are
+    /// filled, calling `recv_vectored` as many times as needed.
+    ///
+    /// After each partial read the buffers are advanced past the data
+    /// received, as `IoSliceMut::advance_slices` does. If an error occurs
+    /// `bufs` is left pointing at the part which wasn't filled yet, so the call
+    /// can be resumed, for example after a `WouldBlock` error on a
+    /// nonblocking socket. An error of kind `UnexpectedEof` is returned if
+    /// the connection is closed before the buffers are filled, and
+    /// `Interrupted` errors are retried.
+    pub fn recv_vectored_exact(&self, bufs: &mut &mut [IoSliceMut<'_>])
+                               -> io::Result<()> {
+        advance_slices_mut(bufs, 0);
+        while !bufs.is_empty() {
+            match self.recv_vectored(bufs) {
+                Ok(0) => {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                              "failed to fill whole buffers"))
+                }
+                Ok(n) => advance_slices_mut(bufs, n),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Receives a message on the socket, along with its control messages and
+    /// the address it came from, as described by `msg`.
+    ///
+    /// This function corresponds to `recvmsg`. On success, returns the number
+    /// of bytes read into the buffers of `msg`. The sender's address, the
+    /// `msg_flags` reported by the system and the control messages received
+    /// can then be read back from `msg`.
+    ///
+    /// This function is only available on Unix.
+    #[cfg(unix)]
+    pub fn recv_msg(&self, msg: &mut MsgHdrMut) -> io::Result<usize> {
+        self.inner.recv_msg(msg)
+    }
+
+    /// Receives a datagram on the socket, along with the address it came from
+    /// and what the system reported about it.
+    ///
+    /// This is a convenience over `recv_msg` for datagram servers, which
+    /// receives into a single buffer with a control buffer large enough for
+    /// the destination address, the type-of-service and the timestamps of the
+    /// datagram. Use `recv_msg` directly to receive other control messages.
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn recv_datagram(&self, buf: &mut [u8]) -> io::Result<RecvMsg> {
+        let mut control = [0; RECV_MSG_CONTROL_LEN];
+        let mut bufs = [IoSliceMut::new(buf)];
+        let mut msg = MsgHdrMut::new()
+            .with_buffers(&mut bufs)
+            .with_control(&mut control);
+        let n = self.inner.recv_msg(&mut msg)?;
+        Ok(RecvMsg::new(n, &msg))
+    }
+
+    /// Receives multiple datagrams on the socket with a single system call,
+    /// one into each buffer of `bufs`.
+    ///
+    /// This blocks until at least one datagram is available, and then
+    /// receives as many of the queued datagrams as fit in `bufs` without
+    /// blocking again. On success, returns the number of bytes read and the
+    /// source address of each datagram received, in the order of `bufs`.
+    ///
+    /// This function uses `recvmmsg` on Linux. On other Unix platforms it
+    /// falls back to calling `recvfrom` for each buffer, and errors after the
+    /// first datagram end the batch rather than being returned.
+    ///
+    /// This function is only available on Unix.
+    #[cfg(unix)]
+    pub fn recv_multiple(&self, bufs: &mut [&mut [u8]])
+                         -> io::Result<Vec<(usize, SockAddr)>> {
+        self.inner.recv_multiple(bufs)
+    }
+
+    /// Receives multiple datagrams on the socket like [`recv_multiple`], but
+    /// waits until every buffer of `bufs` received a datagram or `timeout`
+    /// elapsed rather than returning as soon as one arrived.
+    ///
+    /// This passes `timeout` to `recvmmsg`, so collectors without an event
+    /// loop can wait for up to `bufs.len()` datagrams or the given time with
+    /// a single call. With `None` it waits until all buffers are filled. If
+    /// the call is interrupted by a signal it's restarted with the time which
+    /// is left.
+    ///
+    /// The kernel only checks the timeout after each datagram it receives,
+    /// so the wait for a datagram which never arrives isn't bounded by it.
+    /// Setting a read timeout with [`set_read_timeout`] bounds each of these
+    /// waits too.
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// [`recv_multiple`]: #method.recv_multiple
+    /// [`set_read_timeout`]: #method.set_read_timeout
+    #[cfg(target_os = "linux")]
+    pub fn recv_multiple_timeout(&self,
+                                 bufs: &mut [&mut [u8]],
+                                 timeout: Option<Duration>)
+                                 -> io::Result<Vec<(usize, SockAddr)>> {
+        self.inner.recv_multiple_timeout(bufs, timeout)
+    }
+
+    /// Receives multiple datagrams on the socket like [`recv_multiple`], into
+    /// buffers taken from `provider`.
+    ///
+    /// Up to `count` buffers are taken from `provider`. Each buffer which
+    /// received a datagram is appended to `msgs`, along with the number of
+    /// bytes read into it and the datagram's source address, and the number
+    /// of datagrams received is returned. The buffers which weren't needed
+    /// are given back to `provider`, as are all of them if an error is
+    /// returned.
+    ///
+    /// `scratch` holds the arrays passed to the system, so that with the
+    /// same `scratch` and `msgs` reused across calls, for example by
+    /// draining `msgs` and giving the buffers back to `provider` once the
+    /// datagrams are handled, receiving a batch doesn't allocate.
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// [`recv_multiple`]: #method.recv_multiple
+    #[cfg(unix)]
+    pub fn recv_multiple_with<P>(&self,
+                                 provider: &mut P,
+                                 count: usize,
+                                 scratch: &mut RecvScratch,
+                                 msgs: &mut Vec<(P::Buffer, usize, SockAddr)>)
+                                 -> io::Result<usize>
+        where P: BufferProvider
+    {
+        let start = msgs.len();
+        while msgs.len() - start < count {
+            match provider.take() {
+                // The length and address are filled in once received.
+                Some(buf) => msgs.push((buf, 0, SockAddr::from_bytes(&[])?)),
+                None => break,
+            }
+        }
+        let res = if msgs.len() > start {
+            self.inner.recv_multiple_into(&mut msgs[start..], scratch)
+        } else {
+            Ok(0)
+        };
+        let received = *res.as_ref().unwrap_or(&0);
+        while msgs.len() > start + received {
+            if let Some((buf, _, _)) = msgs.pop() {
+                provider.give_back(buf);
+            }
+        }
+        res
+    }
+
+    /// Receives data on the socket from the remote address to which it is
+    /// connected, failing with a `TimedOut` error if no data arrives before
+    /// `deadline`.
+    ///
+    /// Unlike calling [`set_read_timeout`] before [`recv`] the deadline only
+    /// applies to this call, so it doesn't race with other threads using or
+    /// reconfiguring a clone of this socket. Windows has no per-call
+    /// nonblocking flag, so there a blocking socket is switched to
+    /// nonblocking mode for the duration of the call instead.
+    ///
+    /// [`set_read_timeout`]: #method.set_read_timeout
+    /// [`recv`]: #method.recv
+    pub fn recv_deadline(&self, buf: &mut [u8], deadline: Instant)
+                         -> io::Result<usize> {
+        self.inner.recv_deadline(buf, deadline)
+    }
+
     /// Receives data from the socket. On success, returns the number of bytes
     /// read and the address from whence the data came.
-    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SockAddr)> {
         self.inner.recv_from(buf)
     }
 
+    /// Identical to [`recv_from`] but receives into a possibly uninitialized
+    /// buffer, so it doesn't need to be zeroed first.
+    ///
+    /// On success, the first as many bytes of `buf` as were read are
+    /// guaranteed to be initialized.
+    ///
+    /// [`recv_from`]: #method.recv_from
+    pub fn recv_from_uninit(&self, buf: &mut [MaybeUninit<u8>])
+                            -> io::Result<(usize, SockAddr)> {
+        self.inner.recv_from_with_flags(buf, 0)
+    }
+
+    /// Identical to [`recv_from`] but allows for specification of arbitrary
+    /// flags to the underlying `recvfrom` call.
+    ///
+    /// [`recv_from`]: #method.recv_from
+    pub fn recv_from_with_flags(&self, buf: &mut [u8], flags: i32)
+                                -> io::Result<(usize, SockAddr)> {
+        self.inner.recv_from_with_flags(as_uninit_mut(buf), flags)
+    }
+
+    /// Receives an IPv4 packet on a raw socket, returning its parsed header,
+    /// its payload and the address from whence it came.
+    ///
+    /// Raw IPv4 sockets return packets including their IP header, but some
+    /// systems modify the header before returning it: macOS and iOS convert
+    /// the total length and fragment offset fields to host byte order and
+    /// subtract the header length from the total length, which the returned
+    /// header undoes. Linux, FreeBSD 11 and later, OpenBSD, NetBSD and
+    /// Windows leave the fields untouched. Other systems, including
+    /// DragonFly and older versions of FreeBSD, are assumed to do the same,
+    /// so there the fields are returned as the system reports them. The
+    /// payload is the part of `buf` which follows the header, excluding any
+    /// trailing bytes beyond the packet's total length.
+    ///
+    /// An error with the `InvalidData` kind is returned if the received data
+    /// doesn't start with a valid IPv4 header.
+    pub fn recv_from_ipv4<'a>(&self, buf: &'a mut [u8])
+                              -> io::Result<(Ipv4Header, &'a [u8], SockAddr)> {
+        let (n, addr) = self.recv_from(buf)?;
+        let (header, payload) = Ipv4Header::split(&buf[..n])?;
+        Ok((header, payload, addr))
+    }
+
     /// Receives data from the socket, without removing it from the queue.
     ///
     /// Successive calls return the same data. This is accomplished by passing
@@ -172,7 +977,7 @@ impl Socket {
     ///
     /// On success, returns the number of bytes peeked and the address from
     /// whence the data came.
-    pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+    pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SockAddr)> {
         self.inner.peek_from(buf)
     }
 
@@ -186,81 +991,547 @@ impl Socket {
         self.inner.send(buf)
     }
 
-    /// Sends data on the socket to the given address. On success, returns the
-    /// number of bytes written.
+    /// Identical to [`send`] but allows for specification of arbitrary flags
+    /// to the underlying `send` call, such as `MSG_DONTWAIT` or `MSG_MORE`.
     ///
-    /// This is typically used on UDP or datagram-oriented sockets. On success
-    /// returns the number of bytes that were sent.
-    pub fn send_to(&self, buf: &[u8], addr: &SocketAddr) -> io::Result<usize> {
-        self.inner.send_to(buf, addr)
+    /// On sequenced packet sockets, `MSG_EOR` ends the record with this data,
+    /// which the receiver sees in `RecvFlags::is_end_of_record` on systems
+    /// that report it. Linux accepts the flag but ends a record with every
+    /// call regardless.
+    ///
+    /// On Unix `MSG_NOSIGNAL` is always added to `flags` where it is
+    /// supported, like for [`send`].
+    ///
+    /// [`send`]: #method.send
+    pub fn send_with_flags(&self, buf: &[u8], flags: i32) -> io::Result<usize> {
+        self.inner.send_with_flags(buf, flags)
     }
 
-    // ================================================
+    /// Sends out-of-band (urgent) data on the socket to a connected peer,
+    /// using the `MSG_OOB` flag.
+    ///
+    /// On success returns the number of bytes that were sent.
+    pub fn send_out_of_band(&self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.send_out_of_band(buf)
+    }
 
-    /// Gets the value of the `IP_TTL` option for this socket.
+    /// Sends the data in `bufs`, in order, on the socket to a connected peer.
     ///
-    /// For more information about this option, see [`set_ttl`][link].
+    /// This function corresponds to `sendmsg` on Unix and `WSASend` on
+    /// Windows, so all the buffers are sent with a single system call.
+    /// On success returns the total number of bytes that were sent.
+    pub fn send_vectored(&self, bufs: &[IoSlice]) -> io::Result<usize> {
+        self.inner.send_vectored(bufs)
+    }
+
+    /// Identical to [`send_vectored`] but allows for specification of
+    /// arbitrary flags, such as `MSG_EOR` to end a record with the data in
+    /// `bufs`.
     ///
-    /// [link]: #method.set_ttl
-    pub fn ttl(&self) -> io::Result<u32> {
-        self.inner.ttl()
+    /// On Unix `MSG_NOSIGNAL` is always added to `flags` where it is
+    /// supported.
+    ///
+    /// [`send_vectored`]: #method.send_vectored
+    pub fn send_vectored_with_flags(&self, bufs: &[IoSlice], flags: i32)
+                                    -> io::Result<usize> {
+        self.inner.send_vectored_with_flags(bufs, flags)
     }
 
-    /// Sets the value for the `IP_TTL` option on this socket.
+    /// Sends all the data in `bufs` on the socket to a connected peer,
+    /// calling `send_vectored` as many times as needed.
     ///
-    /// This value sets the time-to-live field that is used in every packet sent
-    /// from this socket.
-    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
-        self.inner.set_ttl(ttl)
+    /// After each partial write the buffers are advanced past the data sent,
+    /// as `IoSlice::advance_slices` does. If an error occurs `bufs` is left
+    /// pointing at the data which wasn't sent yet, so the call can be resumed,
+    /// for example after a `WouldBlock` error on a nonblocking socket. An
+    /// error of kind `WriteZero` is returned if no data could be sent, and
+    /// `Interrupted` errors are retried.
+    pub fn send_vectored_all(&self, bufs: &mut &mut [IoSlice<'_>])
+                             -> io::Result<()> {
+        advance_slices(bufs, 0);
+        while !bufs.is_empty() {
+            match self.send_vectored(bufs) {
+                Ok(0) => {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero,
+                                              "failed to send whole buffers"))
+                }
+                Ok(n) => advance_slices(bufs, n),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
     }
 
-    /// Gets the value of the `IPV6_V6ONLY` option for this socket.
+    /// Sends a message, along with its control messages, as described by
+    /// `msg`.
     ///
-    /// For more information about this option, see [`set_only_v6`][link].
+    /// This function corresponds to `sendmsg`. On success returns the number
+    /// of bytes that were sent.
     ///
-    /// [link]: #method.set_only_v6
-    pub fn only_v6(&self) -> io::Result<bool> {
-        self.inner.only_v6()
+    /// This function is only available on Unix.
+    #[cfg(unix)]
+    pub fn send_msg(&self, msg: &MsgHdr) -> io::Result<usize> {
+        self.inner.send_msg(msg)
     }
 
-    /// Sets the value for the `IPV6_V6ONLY` option on this socket.
+    /// Sends multiple messages on the socket with a single system call.
     ///
-    /// If this is set to `true` then the socket is restricted to sending and
-    /// receiving IPv6 packets only. In this case two IPv4 and IPv6 applications
-    /// can bind the same port at the same time.
+    /// The messages are sent in order until one can't be sent, and on
+    /// success the number of bytes sent for each message sent is returned. An
+    /// error is only returned if the first message couldn't be sent. Each
+    /// message is sent with the flags set with `MsgHdr::with_flags`, so
+    /// `MSG_EOR` can end a record after any of them.
     ///
-    /// If this is set to `false` then the socket can be used to send and
-    /// receive packets from an IPv4-mapped IPv6 address.
-    pub fn set_only_v6(&self, only_v6: bool) -> io::Result<()> {
-        self.inner.set_only_v6(only_v6)
+    /// This function uses `sendmmsg` on Linux, with one call for each run of
+    /// messages with the same flags. On other Unix platforms it falls back to
+    /// calling `sendmsg` for each message.
+    ///
+    /// This function is only available on Unix.
+    #[cfg(unix)]
+    pub fn send_multiple(&self, msgs: &[MsgHdr]) -> io::Result<Vec<usize>> {
+        self.inner.send_multiple(msgs)
     }
 
-    /// Returns the read timeout of this socket.
+    /// Sends data on the socket to a connected peer, failing with a `TimedOut`
+    /// error if the data can't be sent before `deadline`.
     ///
-    /// If the timeout is `None`, then `read` calls will block indefinitely.
-    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
-        self.inner.read_timeout()
+    /// This is the sending counterpart of [`recv_deadline`]: the deadline only
+    /// applies to this call, so it doesn't race with other threads changing
+    /// the socket's write timeout. On Windows a blocking socket is likewise
+    /// switched to nonblocking mode for the duration of the call.
+    ///
+    /// [`recv_deadline`]: #method.recv_deadline
+    pub fn send_deadline(&self, buf: &[u8], deadline: Instant)
+                         -> io::Result<usize> {
+        self.inner.send_deadline(buf, deadline)
     }
 
-    /// Sets the read timeout to the timeout specified.
+    /// Sends data on the socket to the given address. On success, returns the
+    /// number of bytes written.
     ///
-    /// If the value specified is `None`, then `read` calls will block
-    /// indefinitely. It is an error to pass the zero `Duration` to this
-    /// method.
-    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
-        self.inner.set_read_timeout(dur)
+    /// This is typically used on UDP or datagram-oriented sockets. On success
+    /// returns the number of bytes that were sent.
+    pub fn send_to(&self, buf: &[u8], addr: &SockAddr) -> io::Result<usize> {
+        self.inner.send_to(buf, addr)
     }
 
-    /// Returns the write timeout of this socket.
+    /// Identical to [`send_to`] but allows for specification of arbitrary
+    /// flags to the underlying `sendto` call, such as `MSG_CONFIRM`.
     ///
-    /// If the timeout is `None`, then `write` calls will block indefinitely.
-    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
-        self.inner.write_timeout()
+    /// [`send_to`]: #method.send_to
+    pub fn send_to_with_flags(&self, buf: &[u8], addr: &SockAddr, flags: i32)
+                              -> io::Result<usize> {
+        self.inner.send_to_with_flags(buf, addr, flags)
     }
 
-    /// Sets the write timeout to the timeout specified.
+    /// Sends data on this Unix domain socket along with the file descriptors
+    /// `fds`, which are passed as `SCM_RIGHTS` ancillary data.
     ///
-    /// If the value specified is `None`, then `write` calls will block
+    /// The peer receives its own copies of the descriptors with
+    /// [`recv_with_fds`], and they can be closed in this process once this
+    /// returns. At least one byte of data should be sent along with the
+    /// descriptors. On success returns the number of bytes that were sent.
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// [`recv_with_fds`]: #method.recv_with_fds
+    #[cfg(unix)]
+    pub fn send_with_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        self.inner.send_with_fds(buf, fds)
+    }
+
+    /// Receives data on this Unix domain socket along with any file
+    /// descriptors passed as `SCM_RIGHTS` ancillary data.
+    ///
+    /// On success returns the number of bytes read into `buf` and the number
+    /// of descriptors stored at the start of `fds`. The caller owns the
+    /// received descriptors, which have the close-on-exec flag set.
+    ///
+    /// If the message carried more descriptors than fit in `fds` all of them
+    /// are closed, the message is consumed and an error of kind `InvalidData`
+    /// is returned.
+    ///
+    /// This function is only available on Unix.
+    #[cfg(unix)]
+    pub fn recv_with_fds(&self, buf: &mut [u8], fds: &mut [RawFd])
+                         -> io::Result<(usize, usize)> {
+        self.inner.recv_with_fds(buf, fds)
+    }
+
+    /// Sends `socket` to the peer of this Unix domain socket.
+    ///
+    /// The socket's descriptor is passed as `SCM_RIGHTS` ancillary data,
+    /// along with its type so the receiving side can verify what it got. The
+    /// peer receives its own handle to the socket with [`recv_socket`], and
+    /// `socket` can be closed once this returns. This is useful for handing
+    /// listening sockets over to a new process without dropping connections.
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// [`recv_socket`]: #method.recv_socket
+    #[cfg(unix)]
+    pub fn send_socket(&self, socket: &Socket) -> io::Result<()> {
+        self.inner.send_socket(&socket.inner)
+    }
+
+    /// Receives a socket sent by the peer of this Unix domain socket with
+    /// [`send_socket`].
+    ///
+    /// The type of the received socket is looked up with `SO_TYPE` and
+    /// checked against the type sent by the peer. An error of kind
+    /// `InvalidData` is returned if they differ, or if the message received
+    /// didn't carry exactly one socket. The received socket has the
+    /// close-on-exec flag set.
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// [`send_socket`]: #method.send_socket
+    #[cfg(unix)]
+    pub fn recv_socket(&self) -> io::Result<Socket> {
+        self.inner.recv_socket().map(|s| Socket { inner: s })
+    }
+
+    /// Returns the credentials of the process on the other end of this Unix
+    /// domain socket.
+    ///
+    /// The credentials are those of the peer at the time it called `connect`
+    /// or `listen`, or created the socket pair. This uses `SO_PEERCRED` on
+    /// Linux and Android, `LOCAL_PEERCRED` on macOS, iOS, FreeBSD and
+    /// DragonFly, and `getpeereid` on the other BSDs. The peer's process ID is
+    /// only available on Linux, Android, macOS and iOS.
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and the
+    /// BSDs.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn peer_cred(&self) -> io::Result<UCred> {
+        let (uid, gid, pid) = self.inner.peer_cred()?;
+        Ok(UCred { uid, gid, pid })
+    }
+
+    /// Gets the value of the `SO_PASSSEC` option on this socket.
+    ///
+    /// For more information about this option, see [`set_pass_sec`][link].
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [link]: #method.set_pass_sec
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn pass_sec(&self) -> io::Result<bool> {
+        self.inner.pass_sec()
+    }
+
+    /// Sets the value of the `SO_PASSSEC` option on this socket.
+    ///
+    /// If enabled, every message received with `recvmsg` on a Unix socket
+    /// carries an `SCM_SECURITY` control message containing the security
+    /// context of the sending process, as assigned by the active Linux
+    /// security module such as SELinux or AppArmor. The context is returned
+    /// by `MsgHdrMut::security_label`. No control message is sent if no
+    /// security module is active.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_pass_sec(&self, pass: bool) -> io::Result<()> {
+        self.inner.set_pass_sec(pass)
+    }
+
+    /// Associates this socket with `event`, which is then signaled whenever
+    /// one of `events` occurs on the socket.
+    ///
+    /// This function corresponds to `WSAEventSelect`. It replaces any earlier
+    /// association of this socket, and passing `NetworkEvents::empty()`
+    /// removes it. Note that this puts the socket into nonblocking mode, and
+    /// it can't be made blocking again while it's associated with an event.
+    ///
+    /// This function is only available on Windows.
+    #[cfg(windows)]
+    pub fn event_select(&self, event: &Event, events: NetworkEvents)
+                        -> io::Result<()> {
+        self.inner.event_select(&event.inner, events.0)
+    }
+
+    /// Returns the network events which occurred on this socket since the
+    /// last call, and resets `event`.
+    ///
+    /// This function corresponds to `WSAEnumNetworkEvents`. The error of a
+    /// failed connection attempt can be retrieved with [`take_error`] once
+    /// `NetworkEvents::connect` is reported.
+    ///
+    /// This function is only available on Windows.
+    ///
+    /// [`take_error`]: #method.take_error
+    #[cfg(windows)]
+    pub fn network_events(&self, event: &Event) -> io::Result<NetworkEvents> {
+        self.inner.network_events(&event.inner).map(NetworkEvents)
+    }
+
+    // ================================================
+
+    /// Gets the value of the `IP_TTL` option for this socket.
+    ///
+    /// For more information about this option, see [`set_ttl`][link].
+    ///
+    /// [link]: #method.set_ttl
+    pub fn ttl(&self) -> io::Result<u32> {
+        self.inner.ttl()
+    }
+
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This value sets the time-to-live field that is used in every packet sent
+    /// from this socket.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    /// Gets the value of the `IPV6_UNICAST_HOPS` option for this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_unicast_hops_v6`][link].
+    ///
+    /// [link]: #method.set_unicast_hops_v6
+    pub fn unicast_hops_v6(&self) -> io::Result<u32> {
+        self.inner.unicast_hops_v6()
+    }
+
+    /// Sets the value for the `IPV6_UNICAST_HOPS` option on this socket.
+    ///
+    /// This value sets the hop limit field that is used in every unicast
+    /// IPv6 packet sent from this socket, the IPv6 counterpart of
+    /// [`set_ttl`].
+    ///
+    /// [`set_ttl`]: #method.set_ttl
+    pub fn set_unicast_hops_v6(&self, hops: u32) -> io::Result<()> {
+        self.inner.set_unicast_hops_v6(hops)
+    }
+
+    /// Gets the value of the `IP_TOS` option for this socket.
+    ///
+    /// For more information about this option, see [`set_tos`][link].
+    ///
+    /// [link]: #method.set_tos
+    pub fn tos(&self) -> io::Result<u32> {
+        self.inner.tos()
+    }
+
+    /// Sets the value for the `IP_TOS` option on this socket.
+    ///
+    /// This value sets the type-of-service field that is used in every
+    /// IPv4 packet sent from this socket. Its upper six bits are the DSCP
+    /// code point used for quality of service, for example `46 << 2` for
+    /// expedited forwarding, and its lower two bits the ECN field, which
+    /// some systems don't allow setting through this option.
+    ///
+    /// On Windows the system ignores this value unless configured otherwise
+    /// by the administrator, and the DSCP code point has to be set with the
+    /// qWAVE API or a group policy instead.
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        self.inner.set_tos(tos)
+    }
+
+    /// Gets the value of the `IPV6_TCLASS` option for this socket.
+    ///
+    /// For more information about this option, see [`set_tclass_v6`][link].
+    ///
+    /// [link]: #method.set_tclass_v6
+    pub fn tclass_v6(&self) -> io::Result<u32> {
+        self.inner.tclass_v6()
+    }
+
+    /// Sets the value for the `IPV6_TCLASS` option on this socket.
+    ///
+    /// This value sets the traffic class field that is used in every IPv6
+    /// packet sent from this socket, which has the same layout as the IPv4
+    /// type-of-service field set by [`set_tos`].
+    ///
+    /// The same caveats as for [`set_tos`] apply on Windows.
+    ///
+    /// [`set_tos`]: #method.set_tos
+    pub fn set_tclass_v6(&self, tclass: u32) -> io::Result<()> {
+        self.inner.set_tclass_v6(tclass)
+    }
+
+    /// Gets the value of the `IP_RECVTOS` option for this socket.
+    ///
+    /// For more information about this option, see [`set_recv_tos`][link].
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD.
+    ///
+    /// [link]: #method.set_recv_tos
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn recv_tos(&self) -> io::Result<bool> {
+        self.inner.recv_tos()
+    }
+
+    /// Sets the value of the `IP_RECVTOS` option for this socket.
+    ///
+    /// If enabled, the type-of-service byte of each IPv4 datagram received
+    /// is reported as a control message, which is returned by
+    /// `MsgHdrMut::tos` for messages received with `recv_msg`. Its lower two
+    /// bits are the ECN field, which QUIC implementations need to validate
+    /// ECN.
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn set_recv_tos(&self, recv: bool) -> io::Result<()> {
+        self.inner.set_recv_tos(recv)
+    }
+
+    /// Gets the value of the `IPV6_RECVTCLASS` option for this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_recv_tclass_v6`][link].
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD.
+    ///
+    /// [link]: #method.set_recv_tclass_v6
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn recv_tclass_v6(&self) -> io::Result<bool> {
+        self.inner.recv_tclass_v6()
+    }
+
+    /// Sets the value of the `IPV6_RECVTCLASS` option for this socket.
+    ///
+    /// This is the IPv6 counterpart of [`set_recv_tos`], reporting the
+    /// traffic class of each IPv6 datagram received, which is also returned
+    /// by `MsgHdrMut::tos`.
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD.
+    ///
+    /// [`set_recv_tos`]: #method.set_recv_tos
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn set_recv_tclass_v6(&self, recv: bool) -> io::Result<()> {
+        self.inner.set_recv_tclass_v6(recv)
+    }
+
+    /// Gets the value of the `IP_RECVTTL` option for this socket.
+    ///
+    /// For more information about this option, see [`set_recv_ttl`][link].
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD.
+    ///
+    /// [link]: #method.set_recv_ttl
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn recv_ttl(&self) -> io::Result<bool> {
+        self.inner.recv_ttl()
+    }
+
+    /// Sets the value of the `IP_RECVTTL` option for this socket.
+    ///
+    /// If enabled, the time-to-live of each IPv4 datagram received is
+    /// reported as a control message, which is returned by `MsgHdrMut::ttl`
+    /// for messages received with `recv_msg`. This allows checking how many
+    /// hops a datagram has travelled, as done by the Generalized TTL Security
+    /// Mechanism (RFC 5082).
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn set_recv_ttl(&self, recv: bool) -> io::Result<()> {
+        self.inner.set_recv_ttl(recv)
+    }
+
+    /// Gets the value of the `IPV6_RECVHOPLIMIT` option for this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_recv_hoplimit_v6`][link].
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD.
+    ///
+    /// [link]: #method.set_recv_hoplimit_v6
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn recv_hoplimit_v6(&self) -> io::Result<bool> {
+        self.inner.recv_hoplimit_v6()
+    }
+
+    /// Sets the value of the `IPV6_RECVHOPLIMIT` option for this socket.
+    ///
+    /// This is the IPv6 counterpart of [`set_recv_ttl`], reporting the hop
+    /// limit of each IPv6 datagram received, which is also returned by
+    /// `MsgHdrMut::ttl`.
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD.
+    ///
+    /// [`set_recv_ttl`]: #method.set_recv_ttl
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn set_recv_hoplimit_v6(&self, recv: bool) -> io::Result<()> {
+        self.inner.set_recv_hoplimit_v6(recv)
+    }
+
+    /// Gets the value of the `IPV6_V6ONLY` option for this socket.
+    ///
+    /// For more information about this option, see [`set_only_v6`][link].
+    ///
+    /// [link]: #method.set_only_v6
+    pub fn only_v6(&self) -> io::Result<bool> {
+        self.inner.only_v6()
+    }
+
+    /// Sets the value for the `IPV6_V6ONLY` option on this socket.
+    ///
+    /// If this is set to `true` then the socket is restricted to sending and
+    /// receiving IPv6 packets only. In this case two IPv4 and IPv6 applications
+    /// can bind the same port at the same time.
+    ///
+    /// If this is set to `false` then the socket can be used to send and
+    /// receive packets from an IPv4-mapped IPv6 address.
+    pub fn set_only_v6(&self, only_v6: bool) -> io::Result<()> {
+        self.inner.set_only_v6(only_v6)
+    }
+
+    /// Returns the read timeout of this socket.
+    ///
+    /// If the timeout is `None`, then `read` calls will block indefinitely.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.read_timeout()
+    }
+
+    /// Sets the read timeout to the timeout specified.
+    ///
+    /// If the value specified is `None`, then `read` calls will block
+    /// indefinitely. It is an error to pass the zero `Duration` to this
+    /// method.
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.inner.set_read_timeout(dur)
+    }
+
+    /// Returns the write timeout of this socket.
+    ///
+    /// If the timeout is `None`, then `write` calls will block indefinitely.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.write_timeout()
+    }
+
+    /// Sets the write timeout to the timeout specified.
+    ///
+    /// If the value specified is `None`, then `write` calls will block
     /// indefinitely. It is an error to pass the zero `Duration` to this
     /// method.
     pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
@@ -287,6 +1558,33 @@ impl Socket {
         self.inner.set_nodelay(nodelay)
     }
 
+    /// Gets the value of the `TCP_MAXSEG` option on this socket.
+    ///
+    /// For a connected socket this is the maximum segment size currently
+    /// used for the connection. For more information about this option, see
+    /// [`set_mss`][link].
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// [link]: #method.set_mss
+    #[cfg(unix)]
+    pub fn mss(&self) -> io::Result<u32> {
+        self.inner.mss()
+    }
+
+    /// Sets the value of the `TCP_MAXSEG` option on this socket.
+    ///
+    /// This limits the size of the segments sent on the connection, which
+    /// can be used to make room for the headers added by a tunnel. It has to
+    /// be set before the socket is connected, and the system may still lower
+    /// it further to fit the path MTU.
+    ///
+    /// This function is only available on Unix.
+    #[cfg(unix)]
+    pub fn set_mss(&self, mss: u32) -> io::Result<()> {
+        self.inner.set_mss(mss)
+    }
+
     /// Sets the value of the `SO_BROADCAST` option for this socket.
     ///
     /// When enabled, this socket is allowed to send packets to a broadcast
@@ -305,6 +1603,88 @@ impl Socket {
         self.inner.set_broadcast(broadcast)
     }
 
+    /// Sends data on this socket to the limited broadcast address,
+    /// `255.255.255.255`, and the given port.
+    ///
+    /// The `SO_BROADCAST` option is enabled first if it isn't already, so
+    /// this is suited to discovery protocols such as DHCP. Datagrams sent to
+    /// the limited broadcast address don't leave the local network; to reach
+    /// the broadcast address of a specific network, use
+    /// [`send_broadcast_on`] instead.
+    ///
+    /// An error of kind `InvalidInput` is returned if this isn't a
+    /// `SOCK_DGRAM` socket.
+    ///
+    /// [`send_broadcast_on`]: #method.send_broadcast_on
+    pub fn send_broadcast(&self, buf: &[u8], port: u16) -> io::Result<usize> {
+        self.send_broadcast_to(buf, Ipv4Addr::new(255, 255, 255, 255), port)
+    }
+
+    /// Sends data on this socket to the broadcast address of the network
+    /// with the given interface address and netmask, and the given port.
+    ///
+    /// This is the directed broadcast counterpart of [`send_broadcast`]: the
+    /// destination is `addr` with all the host bits covered by `netmask`
+    /// set, such as `192.168.1.255` for `192.168.1.10` and `255.255.255.0`.
+    /// Unlike the limited broadcast address it can be routed to a remote
+    /// network, and it selects the interface to send on when the host is
+    /// attached to several networks.
+    ///
+    /// An error of kind `InvalidInput` is returned if this isn't a
+    /// `SOCK_DGRAM` socket, or if `netmask` isn't a valid netmask or leaves
+    /// fewer than two host bits, in which case the network has no broadcast
+    /// address.
+    ///
+    /// [`send_broadcast`]: #method.send_broadcast
+    pub fn send_broadcast_on(&self, buf: &[u8], port: u16, addr: Ipv4Addr,
+                             netmask: Ipv4Addr) -> io::Result<usize> {
+        let host = !u32::from(netmask);
+        if host & host.wrapping_add(1) != 0 || host < 3 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "netmask doesn't allow a broadcast \
+                                       address"))
+        }
+        let broadcast = Ipv4Addr::from(u32::from(addr) | host);
+        self.send_broadcast_to(buf, broadcast, port)
+    }
+
+    fn send_broadcast_to(&self, buf: &[u8], ip: Ipv4Addr, port: u16)
+                         -> io::Result<usize> {
+        if self.metadata()?.ty != c::SOCK_DGRAM {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "broadcasts can only be sent on \
+                                       datagram sockets"))
+        }
+        if !self.broadcast()? {
+            self.set_broadcast(true)?;
+        }
+        let addr = net::SocketAddrV4::new(ip, port);
+        self.send_to(buf, &addr.into())
+    }
+
+    /// Gets the value of the `SO_OOBINLINE` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_out_of_band_inline`][link].
+    ///
+    /// [link]: #method.set_out_of_band_inline
+    pub fn out_of_band_inline(&self) -> io::Result<bool> {
+        self.inner.out_of_band_inline()
+    }
+
+    /// Sets the value of the `SO_OOBINLINE` option on this socket.
+    ///
+    /// If enabled, out-of-band data is placed in the normal data stream and
+    /// is read with [`recv`] rather than [`recv_out_of_band`], with
+    /// [`at_mark`] telling where it starts.
+    ///
+    /// [`recv`]: #method.recv
+    /// [`recv_out_of_band`]: #method.recv_out_of_band
+    /// [`at_mark`]: #method.at_mark
+    pub fn set_out_of_band_inline(&self, oob_inline: bool) -> io::Result<()> {
+        self.inner.set_out_of_band_inline(oob_inline)
+    }
+
     /// Gets the value of the `IP_MULTICAST_TTL` option for this socket.
     ///
     /// For more information about this option, see
@@ -411,6 +1791,38 @@ impl Socket {
         self.inner.leave_multicast_v6(multiaddr, interface)
     }
 
+    /// Executes an operation of the `IPV6_JOIN_ANYCAST` type.
+    ///
+    /// This function makes the host join the IPv6 anycast address `anyaddr`
+    /// on the interface with index `interface` (or 0 to let the system choose
+    /// an interface). The membership is held until it's dropped with
+    /// [`leave_anycast_v6`] or the socket is closed.
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// [`leave_anycast_v6`]: #method.leave_anycast_v6
+    #[cfg(target_os = "linux")]
+    pub fn join_anycast_v6(&self,
+                           anyaddr: &Ipv6Addr,
+                           interface: u32) -> io::Result<()> {
+        self.inner.join_anycast_v6(anyaddr, interface)
+    }
+
+    /// Executes an operation of the `IPV6_LEAVE_ANYCAST` type.
+    ///
+    /// For more information about this option, see
+    /// [`join_anycast_v6`][link].
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// [link]: #method.join_anycast_v6
+    #[cfg(target_os = "linux")]
+    pub fn leave_anycast_v6(&self,
+                            anyaddr: &Ipv6Addr,
+                            interface: u32) -> io::Result<()> {
+        self.inner.leave_anycast_v6(anyaddr, interface)
+    }
+
     /// Reads the linger duration for this socket by getting the SO_LINGER
     /// option
     pub fn linger(&self) -> io::Result<Option<Duration>> {
@@ -422,6 +1834,34 @@ impl Socket {
         self.inner.set_linger(dur)
     }
 
+    /// Gets the value of the `TCP_LINGER2` option on this socket.
+    ///
+    /// For more information about this option, see [`set_tcp_linger2`][link].
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [link]: #method.set_tcp_linger2
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn tcp_linger2(&self) -> io::Result<Option<Duration>> {
+        self.inner.tcp_linger2()
+    }
+
+    /// Sets the value of the `TCP_LINGER2` option on this socket.
+    ///
+    /// This sets how long the socket stays in the `FIN_WAIT2` state after it
+    /// was closed, waiting for the peer to close its side of the connection.
+    /// Busy proxies can shorten it to free connection table entries sooner.
+    /// The duration is rounded up to whole seconds and capped by the kernel.
+    /// A zero duration restores the system default,
+    /// `net.ipv4.tcp_fin_timeout`, which is what `tcp_linger2` then returns,
+    /// and `None` makes the socket skip the state and close immediately.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_linger2(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.inner.set_tcp_linger2(dur)
+    }
+
     /// Check the `SO_REUSEADDR` option on this socket.
     pub fn reuse_address(&self) -> io::Result<bool> {
         self.inner.reuse_address()
@@ -436,6 +1876,34 @@ impl Socket {
         self.inner.set_reuse_address(reuse)
     }
 
+    /// Gets the value of the `SO_EXCLUSIVEADDRUSE` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_exclusive_address_use`][link].
+    ///
+    /// This function is only available on Windows.
+    ///
+    /// [link]: #method.set_exclusive_address_use
+    #[cfg(windows)]
+    pub fn exclusive_address_use(&self) -> io::Result<bool> {
+        self.inner.exclusive_address_use()
+    }
+
+    /// Sets the value of the `SO_EXCLUSIVEADDRUSE` option on this socket.
+    ///
+    /// On Windows `SO_REUSEADDR` allows a socket to bind to an address which
+    /// is in active use by another socket, which lets other processes take
+    /// over a server's port. When this option is set no other socket can
+    /// bind to this socket's address, whether or not it sets `SO_REUSEADDR`.
+    /// It has to be set before the socket is bound, and servers should
+    /// generally set it instead of `SO_REUSEADDR`.
+    ///
+    /// This function is only available on Windows.
+    #[cfg(windows)]
+    pub fn set_exclusive_address_use(&self, exclusive: bool) -> io::Result<()> {
+        self.inner.set_exclusive_address_use(exclusive)
+    }
+
     /// Gets the value of the `SO_RCVBUF` option on this socket.
     ///
     /// For more information about this option, see
@@ -471,199 +1939,2748 @@ impl Socket {
         self.inner.set_send_buffer_size(size)
     }
 
-    /// Returns whether keepalive messages are enabled on this socket, and if so
-    /// the duration of time between them.
+    /// Returns an estimate of how many bytes can be written to this socket
+    /// without blocking.
     ///
-    /// For more information about this option, see [`set_keepalive`][link].
+    /// This is the size of the send buffer, as returned by
+    /// [`send_buffer_size`], minus the number of bytes queued in it, which is
+    /// read with the `SIOCOUTQ` ioctl on Linux and Android and the
+    /// `SO_NWRITE` option on macOS and iOS. Applications doing their own flow
+    /// control can use it in place of `SO_SNDLOWAT`, which Linux doesn't
+    /// allow to be changed (see [`set_send_low_watermark`]).
     ///
-    /// [link]: #method.set_keepalive
-    pub fn keepalive(&self) -> io::Result<Option<Duration>> {
-        self.inner.keepalive()
+    /// The result is only an estimate, and errs on the low side: Linux
+    /// doubles the requested buffer size to leave room for bookkeeping
+    /// overhead, so only half of the reported size is counted there. For TCP
+    /// sockets the queued bytes include data that was sent but not yet
+    /// acknowledged.
+    ///
+    /// This function is only available on Linux, Android, macOS and iOS, and
+    /// not on Linux and Android when the `no-ioctl` feature is enabled.
+    ///
+    /// [`send_buffer_size`]: #method.send_buffer_size
+    /// [`set_send_low_watermark`]: #method.set_send_low_watermark
+    #[cfg(any(all(any(target_os = "linux", target_os = "android"),
+                  not(feature = "no-ioctl")),
+              target_os = "macos", target_os = "ios"))]
+    pub fn writable_bytes_estimate(&self) -> io::Result<usize> {
+        let size = self.inner.send_buffer_size()?;
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let size = size / 2;
+        let queued = self.inner.send_queue_len()?;
+        Ok(size.saturating_sub(queued))
     }
 
-    /// Sets whether keepalive messages are enabled to be sent on this socket.
+    /// Gets the value of the `SO_SNDLOWAT` option on this socket.
     ///
-    /// On Unix, this option will set the `SO_KEEPALIVE` as well as the
-    /// `TCP_KEEPALIVE` or `TCP_KEEPIDLE` option (depending on your platform).
-    /// On Windows, this will set the `SIO_KEEPALIVE_VALS` option.
+    /// For more information about this option, see
+    /// [`set_send_low_watermark`][link].
     ///
-    /// If `None` is specified then keepalive messages are disabled, otherwise
-    /// the duration specified will be the time to remain idle before sending a
-    /// TCP keepalive probe.
+    /// This function is only available on Unix.
     ///
-    /// Some platforms specify this value in seconds, so sub-second
-    /// specifications may be omitted.
-    pub fn set_keepalive(&self, keepalive: Option<Duration>) -> io::Result<()> {
-        self.inner.set_keepalive(keepalive)
+    /// [link]: #method.set_send_low_watermark
+    #[cfg(unix)]
+    pub fn send_low_watermark(&self) -> io::Result<usize> {
+        self.inner.send_low_watermark()
     }
 
-    /// Check the value of the `SO_REUSEPORT` option on this socket.
+    /// Sets the value of the `SO_SNDLOWAT` option on this socket.
     ///
-    /// This function is only available on Unix when the `reuseport` feature is
-    /// enabled.
-    #[cfg(all(unix, feature = "reuseport"))]
-    pub fn reuse_port(&self) -> io::Result<bool> {
+    /// The socket is only reported as writable, for example by `poll`, once
+    /// at least `lowat` bytes of its send buffer are free. Linux and Android
+    /// don't implement the option: it always reads as 1, and setting it
+    /// fails with `ENOPROTOOPT`. There [`writable_bytes_estimate`] can be
+    /// used to emulate it.
+    ///
+    /// This function is only available on Unix.
+    ///
+    /// [`writable_bytes_estimate`]: #method.writable_bytes_estimate
+    #[cfg(unix)]
+    pub fn set_send_low_watermark(&self, lowat: usize) -> io::Result<()> {
+        self.inner.set_send_low_watermark(lowat)
+    }
+
+    /// Sizes the send and receive buffers of this socket for a path with the
+    /// given `bandwidth`, in bytes per second, and round-trip time.
+    ///
+    /// To keep such a path busy the buffers have to hold all the data in
+    /// flight, which is the bandwidth-delay product `bandwidth * rtt`. Both
+    /// `SO_SNDBUF` and `SO_RCVBUF` are set to that many bytes.
+    ///
+    /// The system may silently cap the sizes at its configured maximum, for
+    /// example `net.core.wmem_max` and `net.core.rmem_max` on Linux, see
+    /// [`force_buffer_sizes_for`] to exceed it. Note that Linux doubles the
+    /// sizes to leave room for bookkeeping, so the values reported by
+    /// [`send_buffer_size`] and [`recv_buffer_size`] differ from the ones set.
+    ///
+    /// [`force_buffer_sizes_for`]: #method.force_buffer_sizes_for
+    /// [`send_buffer_size`]: #method.send_buffer_size
+    /// [`recv_buffer_size`]: #method.recv_buffer_size
+    pub fn set_buffer_sizes_for(&self, bandwidth: u64, rtt: Duration)
+                                -> io::Result<()> {
+        let size = bdp(bandwidth, rtt);
+        self.inner.set_send_buffer_size(size)?;
+        self.inner.set_recv_buffer_size(size)
+    }
+
+    /// Sizes the send and receive buffers of this socket for a path like
+    /// [`set_buffer_sizes_for`], ignoring the system's maximum buffer sizes.
+    ///
+    /// This uses the `SO_SNDBUFFORCE` and `SO_RCVBUFFORCE` options, and so
+    /// requires the `CAP_NET_ADMIN` capability.
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// [`set_buffer_sizes_for`]: #method.set_buffer_sizes_for
+    #[cfg(target_os = "linux")]
+    pub fn force_buffer_sizes_for(&self, bandwidth: u64, rtt: Duration)
+                                  -> io::Result<()> {
+        let size = bdp(bandwidth, rtt);
+        self.inner.force_send_buffer_size(size)?;
+        self.inner.force_recv_buffer_size(size)
+    }
+
+    /// Returns whether keepalive messages are enabled on this socket, and if so
+    /// the duration of time between them.
+    ///
+    /// For more information about this option, see [`set_keepalive`][link].
+    ///
+    /// [link]: #method.set_keepalive
+    pub fn keepalive(&self) -> io::Result<Option<Duration>> {
+        self.inner.keepalive()
+    }
+
+    /// Sets whether keepalive messages are enabled to be sent on this socket.
+    ///
+    /// On Unix, this option will set the `SO_KEEPALIVE` as well as the
+    /// `TCP_KEEPALIVE` or `TCP_KEEPIDLE` option (depending on your platform).
+    /// On Windows, this will set the `SIO_KEEPALIVE_VALS` option.
+    ///
+    /// If `None` is specified then keepalive messages are disabled, otherwise
+    /// the duration specified will be the time to remain idle before sending a
+    /// TCP keepalive probe.
+    ///
+    /// Some platforms specify this value in seconds, so sub-second
+    /// specifications may be omitted. To also configure the interval between
+    /// probes and their number, see [`set_tcp_keepalive`].
+    ///
+    /// [`set_tcp_keepalive`]: #method.set_tcp_keepalive
+    pub fn set_keepalive(&self, keepalive: Option<Duration>) -> io::Result<()> {
+        self.inner.set_keepalive(keepalive)
+    }
+
+    /// Enables keepalive messages on this socket and configures them with
+    /// the given parameters.
+    ///
+    /// On Unix, this sets the `SO_KEEPALIVE` option as well as the
+    /// `TCP_KEEPIDLE` (or `TCP_KEEPALIVE`), `TCP_KEEPINTVL` and `TCP_KEEPCNT`
+    /// options for the parameters which are set. On Windows, this sets the
+    /// `SIO_KEEPALIVE_VALS` option, using the system's defaults for the idle
+    /// time and interval if they aren't set, and the `TCP_KEEPCNT` option,
+    /// which is only supported since Windows 10, version 1703.
+    ///
+    /// Some platforms specify the times in seconds, so sub-second
+    /// specifications may be omitted.
+    pub fn set_tcp_keepalive(&self, params: &TcpKeepalive) -> io::Result<()> {
+        self.inner.set_tcp_keepalive(params)
+    }
+
+    /// Reads the commonly inspected options of this socket in one call.
+    ///
+    /// This covers `SO_REUSEADDR`, `TCP_NODELAY`, the buffer sizes, the read
+    /// and write timeouts, `SO_LINGER`, keepalive and `IP_TTL`. Options which
+    /// can't be read are left out of the result rather than failing the
+    /// whole call, so this is suited to diagnostic output such as logs.
+    pub fn dump_options(&self) -> SocketOptions {
+        SocketOptions {
+            reuse_address: self.reuse_address().ok(),
+            nodelay: self.nodelay().ok(),
+            recv_buffer_size: self.recv_buffer_size().ok(),
+            send_buffer_size: self.send_buffer_size().ok(),
+            read_timeout: self.read_timeout().ok(),
+            write_timeout: self.write_timeout().ok(),
+            linger: self.linger().ok(),
+            keepalive: self.keepalive().ok(),
+            ttl: self.ttl().ok(),
+        }
+    }
+
+    /// Gets the value of the `SO_REUSEPORT` option on this socket.
+    ///
+    /// For more information about this option, see [`set_reuse_port`].
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and the
+    /// BSDs.
+    ///
+    /// [`set_reuse_port`]: #method.set_reuse_port
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn reuse_port(&self) -> io::Result<bool> {
         self.inner.reuse_port()
     }
 
     /// Set value for the `SO_REUSEPORT` option on this socket.
     ///
-    /// This indicates that futher calls to `bind` may allow reuse of local
-    /// addresses. For IPv4 sockets this means that a socket may bind even when
-    /// there's a socket already listening on this port.
+    /// This indicates that further calls to `bind` may bind to the same
+    /// address as this socket, as long as all of the sockets involved set
+    /// this option before being bound. On Linux the sockets must also belong
+    /// to the same user, and incoming connections and datagrams are then
+    /// distributed over the sockets, which allows spreading the load of a
+    /// service over several processes or threads.
     ///
-    /// This function is only available on Unix when the `reuseport` feature is
-    /// enabled.
-    #[cfg(all(unix, feature = "reuseport"))]
+    /// This function is only available on Linux, Android, macOS, iOS and the
+    /// BSDs.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly",
+              target_os = "openbsd", target_os = "netbsd"))]
     pub fn set_reuse_port(&self, reuse: bool) -> io::Result<()> {
         self.inner.set_reuse_port(reuse)
     }
+
+    /// Gets the value of the `SO_REUSEPORT_LB` option on this socket.
+    ///
+    /// For more information about this option, see [`set_reuse_port_lb`].
+    ///
+    /// This function is only available on FreeBSD.
+    ///
+    /// [`set_reuse_port_lb`]: #method.set_reuse_port_lb
+    #[cfg(target_os = "freebsd")]
+    pub fn reuse_port_lb(&self) -> io::Result<bool> {
+        self.inner.reuse_port_lb()
+    }
+
+    /// Set value for the `SO_REUSEPORT_LB` option on this socket.
+    ///
+    /// Like `SO_REUSEPORT` this allows several sockets to bind to the same
+    /// address, but the sockets form a load-balancing group in which the
+    /// kernel distributes incoming connections and datagrams over the
+    /// sockets, rather than delivering them to the most recently bound one.
+    ///
+    /// This function is only available on FreeBSD.
+    #[cfg(target_os = "freebsd")]
+    pub fn set_reuse_port_lb(&self, reuse: bool) -> io::Result<()> {
+        self.inner.set_reuse_port_lb(reuse)
+    }
+
+    /// Gets the value of the `SO_ACCEPTFILTER` option on this socket.
+    ///
+    /// This returns the name of the accept filter installed on this socket,
+    /// or `None` if there is none. For more information about this option,
+    /// see [`set_accept_filter`][link].
+    ///
+    /// This function is only available on FreeBSD.
+    ///
+    /// [link]: #method.set_accept_filter
+    #[cfg(target_os = "freebsd")]
+    pub fn accept_filter(&self) -> io::Result<Option<String>> {
+        self.inner.accept_filter()
+    }
+
+    /// Sets the value of the `SO_ACCEPTFILTER` option on this socket.
+    ///
+    /// An accept filter makes the kernel hold back incoming connections from
+    /// `accept` until they are ready to be handled. `filter` is the name of
+    /// the filter, such as `"dataready"` to wait until data arrives, or
+    /// `"httpready"` to wait until a full HTTP request header arrived, and
+    /// `None` removes the filter. The filter's kernel module, such as
+    /// `accf_http`, has to be loaded.
+    ///
+    /// This has to be called on a listening socket. An error of kind
+    /// `InvalidInput` is returned if `filter` is longer than 15 bytes. On
+    /// Linux, [`set_deferred_accept`] provides similar behavior.
+    ///
+    /// This function is only available on FreeBSD.
+    ///
+    /// [`set_deferred_accept`]: #method.set_deferred_accept
+    #[cfg(target_os = "freebsd")]
+    pub fn set_accept_filter(&self, filter: Option<&str>) -> io::Result<()> {
+        self.inner.set_accept_filter(filter)
+    }
+
+    /// Gets the value of the `TCP_DEFER_ACCEPT` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_deferred_accept`][link].
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [link]: #method.set_deferred_accept
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn deferred_accept(&self) -> io::Result<Duration> {
+        self.inner.deferred_accept()
+    }
+
+    /// Sets the value of the `TCP_DEFER_ACCEPT` option on this socket.
+    ///
+    /// This makes the kernel hold back incoming connections from `accept`
+    /// until data arrived on them, waiting at most about `timeout` for it,
+    /// so servers for protocols in which the client talks first aren't woken
+    /// up for idle connections. A zero `timeout` disables the option. The
+    /// timeout is rounded up to whole seconds, and then by the kernel to its
+    /// `SYN-ACK` retransmission schedule, which `deferred_accept` reflects.
+    ///
+    /// This has to be called on a listening socket. On FreeBSD,
+    /// [`set_accept_filter`] with the `"dataready"` filter provides similar
+    /// behavior.
+    ///
+    /// With this option set, the first [`recv`] on a socket returned by
+    /// [`accept`] normally finds the client's data already queued. Once the
+    /// timeout expired the connection is accepted anyway, so a `recv` may
+    /// still block unless the socket is nonblocking or has a read timeout.
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [`set_accept_filter`]: #method.set_accept_filter
+    /// [`recv`]: #method.recv
+    /// [`accept`]: #method.accept
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_deferred_accept(&self, timeout: Duration) -> io::Result<()> {
+        self.inner.set_deferred_accept(timeout)
+    }
+
+    /// Gets the value of the `TCP_SYNCNT` option on this socket.
+    ///
+    /// For more information about this option, see [`set_tcp_syncnt`][link].
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [link]: #method.set_tcp_syncnt
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn tcp_syncnt(&self) -> io::Result<u32> {
+        self.inner.tcp_syncnt()
+    }
+
+    /// Sets the value of the `TCP_SYNCNT` option on this socket.
+    ///
+    /// This is the number of times a `SYN` is retransmitted before a
+    /// connection attempt is given up, overriding the system-wide
+    /// `net.ipv4.tcp_syn_retries`. As the retransmission timeout doubles
+    /// each time, starting at one second, a low count makes `connect` fail
+    /// quickly when the peer doesn't respond. The kernel accepts values
+    /// from 1 to 127.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_syncnt(&self, count: u32) -> io::Result<()> {
+        self.inner.set_tcp_syncnt(count)
+    }
+
+    /// Gets the value of the `TCP_WINDOW_CLAMP` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_window_clamp`][link].
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [link]: #method.set_window_clamp
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn window_clamp(&self) -> io::Result<u32> {
+        self.inner.window_clamp()
+    }
+
+    /// Sets the value of the `TCP_WINDOW_CLAMP` option on this socket.
+    ///
+    /// This caps the receive window advertised to the peer at `clamp` bytes,
+    /// which limits the rate at which the peer can send on this connection
+    /// independently of the receive buffer size. The kernel raises values
+    /// below half of its minimum receive buffer size to that, and only
+    /// accepts zero, which removes the cap, before the socket is connected.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_window_clamp(&self, clamp: u32) -> io::Result<()> {
+        self.inner.set_window_clamp(clamp)
+    }
+
+    /// Gets the value of the `TCP_NOTSENT_LOWAT` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_notsent_lowat`][link].
+    ///
+    /// This function is only available on Linux, Android, macOS and iOS.
+    ///
+    /// [link]: #method.set_notsent_lowat
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios"))]
+    pub fn notsent_lowat(&self) -> io::Result<u32> {
+        self.inner.notsent_lowat()
+    }
+
+    /// Sets the value of the `TCP_NOTSENT_LOWAT` option on this socket.
+    ///
+    /// This limits the amount of data queued on this socket which hasn't
+    /// been sent yet: the socket is only reported as writable, for example
+    /// by `poll`, while less than `lowat` bytes are unsent. Keeping the
+    /// queue shallow lets applications which prioritize their own data, such
+    /// as HTTP/2 servers, decide what to send at the last moment and react to
+    /// backpressure sooner. On Linux the system-wide default is
+    /// `net.ipv4.tcp_notsent_lowat`, which is unlimited unless configured.
+    ///
+    /// This function is only available on Linux, Android, macOS and iOS.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios"))]
+    pub fn set_notsent_lowat(&self, lowat: u32) -> io::Result<()> {
+        self.inner.set_notsent_lowat(lowat)
+    }
+
+    /// Sets the value of the `TCP_MD5SIG` option on this socket.
+    ///
+    /// This installs `key` as the key with which the TCP MD5 signature
+    /// option of RFC 2385 is added to and verified on the segments exchanged
+    /// with the peer at the IP address of `addr`, as required by many BGP
+    /// peers. An empty `key` removes the key for the address. On a listening
+    /// socket keys can be installed for several peers, and are inherited by
+    /// the accepted sockets. Keys have to be installed before connecting or
+    /// accepting, as unsigned connections aren't upgraded.
+    ///
+    /// An error of kind `InvalidInput` is returned if `key` is longer than
+    /// `TCP_MD5SIG_MAXKEYLEN` (80) bytes.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_md5sig(&self, addr: &SockAddr, key: &[u8])
+                          -> io::Result<()> {
+        self.inner.set_tcp_md5sig(addr, None, key)
+    }
+
+    /// Sets the value of the `TCP_MD5SIG_EXT` option on this socket.
+    ///
+    /// This is like [`set_tcp_md5sig`], but installs `key` for all peers in
+    /// the network of `addr` with the given prefix length, for example for
+    /// all of `10.0.0.0/24` on a listening socket. It needs Linux 4.13 or
+    /// later.
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [`set_tcp_md5sig`]: #method.set_tcp_md5sig
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_md5sig_prefix(&self, addr: &SockAddr, prefix_len: u8,
+                                 key: &[u8]) -> io::Result<()> {
+        self.inner.set_tcp_md5sig(addr, Some(prefix_len), key)
+    }
+
+    /// Gets the value of the `TCP_SAVE_SYN` option on this socket.
+    ///
+    /// For more information about this option, see [`set_save_syn`][link].
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [link]: #method.set_save_syn
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn save_syn(&self) -> io::Result<bool> {
+        self.inner.save_syn()
+    }
+
+    /// Sets the value of the `TCP_SAVE_SYN` option on this socket.
+    ///
+    /// If enabled on a listening socket, the kernel keeps the headers of the
+    /// `SYN` which opened each connection, which can then be read from the
+    /// accepted socket with [`saved_syn`], for example to fingerprint the
+    /// peer's operating system.
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [`saved_syn`]: #method.saved_syn
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_save_syn(&self, save: bool) -> io::Result<()> {
+        self.inner.set_save_syn(save)
+    }
+
+    /// Gets the value of the `TCP_SAVED_SYN` option on this socket.
+    ///
+    /// This returns the IP and TCP headers of the `SYN` which opened this
+    /// accepted connection, if [`set_save_syn`] was enabled on the listening
+    /// socket. The kernel releases the headers once they're read, so later
+    /// calls return an empty buffer.
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [`set_save_syn`]: #method.set_save_syn
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn saved_syn(&self) -> io::Result<Vec<u8>> {
+        self.inner.saved_syn()
+    }
+
+    /// Gets the value of the `TCP_FASTOPEN_KEY` option on this socket.
+    ///
+    /// This returns the primary key used to generate the TCP Fast Open
+    /// cookies of this listening socket. For more information about this
+    /// option, see [`set_tcp_fastopen_key`][link].
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [link]: #method.set_tcp_fastopen_key
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn tcp_fastopen_key(&self) -> io::Result<[u8; 16]> {
+        self.inner.tcp_fastopen_key()
+    }
+
+    /// Sets the value of the `TCP_FASTOPEN_KEY` option on this socket.
+    ///
+    /// This sets the key used to generate and validate the TCP Fast Open
+    /// cookies of this listening socket, overriding the system-wide key of
+    /// the `net.ipv4.tcp_fastopen_key` sysctl. Cookies generated with a
+    /// previous key are no longer accepted, see [`set_tcp_fastopen_keys`]
+    /// for rotating keys without that. It needs Linux 4.10 or later.
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [`set_tcp_fastopen_keys`]: #method.set_tcp_fastopen_keys
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_fastopen_key(&self, key: &[u8; 16]) -> io::Result<()> {
+        self.inner.set_tcp_fastopen_key(&key[..])
+    }
+
+    /// Sets the value of the `TCP_FASTOPEN_KEY` option on this socket to a
+    /// primary and a backup key.
+    ///
+    /// New cookies are generated with `primary`, while cookies generated
+    /// with either key are accepted. When rotating keys, setting the new key
+    /// as the primary and the previous one as the backup keeps the cookies
+    /// already handed out to clients valid. It needs Linux 5.3 or later.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_fastopen_keys(&self, primary: &[u8; 16], backup: &[u8; 16])
+                                 -> io::Result<()> {
+        let mut keys = [0; 32];
+        keys[..16].copy_from_slice(primary);
+        keys[16..].copy_from_slice(backup);
+        self.inner.set_tcp_fastopen_key(&keys[..])
+    }
+
+    /// Returns whether this socket uses Multipath TCP, using the
+    /// `TCP_IS_MPTCP` option.
+    ///
+    /// This is `false` for plain TCP sockets, and for Multipath TCP sockets
+    /// whose connection fell back to plain TCP because the peer doesn't
+    /// support Multipath TCP. The option needs Linux 5.16 or later.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn is_multipath(&self) -> io::Result<bool> {
+        self.inner.is_multipath()
+    }
+
+    /// Gets the value of the `SO_MAX_PACING_RATE` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_max_pacing_rate`][link].
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [link]: #method.set_max_pacing_rate
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn max_pacing_rate(&self) -> io::Result<Option<u64>> {
+        self.inner.max_pacing_rate()
+    }
+
+    /// Sets the value of the `SO_MAX_PACING_RATE` option on this socket.
+    ///
+    /// This limits the rate at which data is sent on this socket to `rate`
+    /// bytes per second, spacing out the packets, and `None` removes the
+    /// limit. This is enforced by the `fq` queueing discipline, and for TCP
+    /// sockets also by the TCP stack itself, so single connections can be
+    /// rate-limited without configuring traffic control. Rates which don't
+    /// fit in 32 bits need Linux 4.20 or later.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_max_pacing_rate(&self, rate: Option<u64>) -> io::Result<()> {
+        self.inner.set_max_pacing_rate(rate)
+    }
+
+    /// Gets the value of the `SO_TXREHASH` option on this socket.
+    ///
+    /// For more information about this option, see [`set_tx_rehash`][link].
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// [link]: #method.set_tx_rehash
+    #[cfg(target_os = "linux")]
+    pub fn tx_rehash(&self) -> io::Result<Option<bool>> {
+        self.inner.tx_rehash()
+    }
+
+    /// Sets the value of the `SO_TXREHASH` option on this socket.
+    ///
+    /// This controls whether the kernel picks a new transmit hash for the
+    /// connection when it detects a problem with the path, such as a
+    /// retransmission timeout, which moves the connection to another
+    /// transmit queue or, with multipath routing or load-balanced links,
+    /// another path. `None` uses the system-wide default of the
+    /// `net.core.txrehash` sysctl. It needs Linux 5.19 or later.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn set_tx_rehash(&self, rehash: Option<bool>) -> io::Result<()> {
+        self.inner.set_tx_rehash(rehash)
+    }
+
+    /// Attaches a classic BPF program to this socket's `SO_REUSEPORT` group
+    /// which distributes incoming packets and connections over `sockets`
+    /// sockets based on a hash of the connection's 4-tuple.
+    ///
+    /// This sets the `SO_ATTACH_REUSEPORT_CBPF` option. The program selects
+    /// the socket at index `hash % sockets` in the group, where sockets are
+    /// numbered in the order in which they were added to it, so `sockets`
+    /// should be the number of sockets bound to the address. Packets for which
+    /// the program selects a nonexistent socket are distributed by the kernel's
+    /// default algorithm. The program is shared by the whole group, so it only
+    /// needs to be attached to one of its sockets.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn attach_reuse_port_hash_filter(&self, sockets: u32) -> io::Result<()> {
+        self.inner.attach_reuse_port_hash_filter(sockets)
+    }
+
+    /// Gets the value of the `TCP_INQ` option on this socket.
+    ///
+    /// For more information about this option, see [`set_tcp_inq`][link].
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// [link]: #method.set_tcp_inq
+    #[cfg(target_os = "linux")]
+    pub fn tcp_inq(&self) -> io::Result<bool> {
+        self.inner.tcp_inq()
+    }
+
+    /// Sets the value of the `TCP_INQ` option on this socket.
+    ///
+    /// If enabled, every message received with `recvmsg` carries a
+    /// `TCP_CM_INQ` control message containing the number of bytes still
+    /// queued on the socket after the read, which allows readers to size their
+    /// next read accordingly. The value is returned by `MsgHdrMut::inq`.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn set_tcp_inq(&self, inq: bool) -> io::Result<()> {
+        self.inner.set_tcp_inq(inq)
+    }
+
+    /// Gets the value of the `TCP_QUICKACK` option on this socket.
+    ///
+    /// For more information about this option, see [`set_quickack`][link].
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [link]: #method.set_quickack
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn quickack(&self) -> io::Result<bool> {
+        self.inner.quickack()
+    }
+
+    /// Sets the value of the `TCP_QUICKACK` option on this socket.
+    ///
+    /// If enabled, acknowledgements are sent right away rather than being
+    /// delayed to be piggybacked on data, which lowers latency for request
+    /// and response workloads together with `TCP_NODELAY`. The option isn't
+    /// permanent: the kernel may turn it off again as the connection
+    /// progresses, so it's usually set again after each receive.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_quickack(&self, quickack: bool) -> io::Result<()> {
+        self.inner.set_quickack(quickack)
+    }
+
+    /// Gets the value of the `TCP_CONGESTION` option on this socket.
+    ///
+    /// This returns the name of the congestion control algorithm used by
+    /// this socket. For more information about this option, see
+    /// [`set_tcp_congestion`][link].
+    ///
+    /// This function is only available on Linux and FreeBSD.
+    ///
+    /// [link]: #method.set_tcp_congestion
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    pub fn tcp_congestion(&self) -> io::Result<String> {
+        self.inner.tcp_congestion()
+    }
+
+    /// Sets the value of the `TCP_CONGESTION` option on this socket.
+    ///
+    /// This selects the congestion control algorithm used by this socket by
+    /// name, such as `"cubic"` or `"bbr"`. The algorithm has to be available
+    /// in the kernel, and on Linux unprivileged processes can only select
+    /// the algorithms listed in `net.ipv4.tcp_allowed_congestion_control`.
+    /// An error of kind `InvalidInput` is returned if `algorithm` is longer
+    /// than 15 bytes.
+    ///
+    /// This function is only available on Linux and FreeBSD.
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    pub fn set_tcp_congestion(&self, algorithm: &str) -> io::Result<()> {
+        self.inner.set_tcp_congestion(algorithm)
+    }
+
+    /// Returns transport statistics of the TCP connection of this socket,
+    /// such as its round trip time and congestion window.
+    ///
+    /// This uses the `TCP_INFO` option on Linux and FreeBSD, and the
+    /// `SIO_TCP_INFO` control code on Windows, which is available since
+    /// Windows 10 version 1703.
+    ///
+    /// This function is only available on Linux, FreeBSD and Windows.
+    #[cfg(any(target_os = "linux", target_os = "freebsd", windows))]
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        self.inner.tcp_info()
+    }
+
+    /// Gets the value of the `TCP_USER_TIMEOUT` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_tcp_user_timeout`][link].
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [link]: #method.set_tcp_user_timeout
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn tcp_user_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.tcp_user_timeout()
+    }
+
+    /// Sets the value of the `TCP_USER_TIMEOUT` option on this socket.
+    ///
+    /// This bounds how long transmitted data may remain unacknowledged
+    /// before the connection is forcibly closed with `ETIMEDOUT`. When used
+    /// together with keepalive it also bounds how long keepalive probes may
+    /// go unanswered, which detects dead peers sooner than the probe count
+    /// does. `None` restores the system's default.
+    ///
+    /// The timeout is specified in milliseconds, so sub-millisecond
+    /// specifications are rounded down, and durations too long to represent
+    /// are capped.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_user_timeout(&self, timeout: Option<Duration>)
+                                -> io::Result<()> {
+        self.inner.set_tcp_user_timeout(timeout)
+    }
+
+    /// Gets the value of the `IPV6_RECVPATHMTU` option for this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_recv_path_mtu_v6`][link].
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and the
+    /// BSDs.
+    ///
+    /// [link]: #method.set_recv_path_mtu_v6
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd",
+              target_os = "ios", target_os = "macos",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn recv_path_mtu_v6(&self) -> io::Result<bool> {
+        self.inner.recv_path_mtu_v6()
+    }
+
+    /// Sets the value of the `IPV6_RECVPATHMTU` option for this socket.
+    ///
+    /// If enabled, the kernel reports changes of the path MTU to the
+    /// destination as an `IPV6_PATHMTU` control message when a packet larger
+    /// than the path MTU is sent. This allows datagram protocols to discover
+    /// the path MTU themselves rather than relying on fragmentation.
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and the
+    /// BSDs.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd",
+              target_os = "ios", target_os = "macos",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn set_recv_path_mtu_v6(&self, recv: bool) -> io::Result<()> {
+        self.inner.set_recv_path_mtu_v6(recv)
+    }
+
+    /// Gets the value of the `IPV6_USE_MIN_MTU` option for this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_use_min_mtu_v6`][link].
+    ///
+    /// This function is only available on macOS, iOS and the BSDs.
+    ///
+    /// [link]: #method.set_use_min_mtu_v6
+    #[cfg(any(target_os = "dragonfly", target_os = "freebsd",
+              target_os = "ios", target_os = "macos",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn use_min_mtu_v6(&self) -> io::Result<bool> {
+        self.inner.use_min_mtu_v6()
+    }
+
+    /// Sets the value of the `IPV6_USE_MIN_MTU` option for this socket.
+    ///
+    /// If enabled, outgoing packets are fragmented at the IPv6 minimum MTU of
+    /// 1280 bytes instead of the path MTU. If disabled, the path MTU is used
+    /// for all packets. By default the minimum MTU is only used for multicast
+    /// packets, which is reported as `false` by [`use_min_mtu_v6`].
+    ///
+    /// This function is only available on macOS, iOS and the BSDs.
+    ///
+    /// [`use_min_mtu_v6`]: #method.use_min_mtu_v6
+    #[cfg(any(target_os = "dragonfly", target_os = "freebsd",
+              target_os = "ios", target_os = "macos",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn set_use_min_mtu_v6(&self, use_min_mtu: bool) -> io::Result<()> {
+        self.inner.set_use_min_mtu_v6(use_min_mtu)
+    }
+
+    /// Enables the options a QUIC implementation needs on this UDP socket,
+    /// as far as the system supports them, and returns which ones were
+    /// enabled.
+    ///
+    /// This tries:
+    ///
+    /// * `IP_PKTINFO` or `IPV6_RECVPKTINFO`, to receive the destination
+    ///   address of each datagram (`IP_RECVDSTADDR` on FreeBSD),
+    /// * `IP_RECVTOS` or `IPV6_RECVTCLASS`, to receive the ECN bits of each
+    ///   datagram,
+    /// * `UDP_GRO`, to receive several datagrams from the same peer in one
+    ///   call,
+    /// * `IP_RECVERR` or `IPV6_RECVERR`, to receive ICMP errors on the error
+    ///   queue instead of failing calls.
+    ///
+    /// UDP segmentation offload (GSO) is requested per call to `sendmsg`
+    /// with a `UDP_SEGMENT` control message, so only whether the system
+    /// supports it is checked. GSO, GRO and the error queue are only
+    /// available on Linux and Android.
+    ///
+    /// On IPv6 sockets the IPv4 options are set as well where possible, so
+    /// that IPv4 datagrams received by dual-stack sockets are covered. Options
+    /// which can't be enabled are reported as disabled rather than failing.
+    /// An error of kind `InvalidInput` is returned if this isn't an IPv4 or
+    /// IPv6 `SOCK_DGRAM` socket.
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and
+    /// FreeBSD, when the `quic` feature is enabled.
+    #[cfg(all(feature = "quic",
+              any(target_os = "linux", target_os = "android",
+                  target_os = "macos", target_os = "ios",
+                  target_os = "freebsd")))]
+    pub fn configure_for_quic(&self) -> io::Result<QuicFeatures> {
+        let metadata = self.metadata()?;
+        if metadata.ty != c::SOCK_DGRAM ||
+           (metadata.domain != c::AF_INET && metadata.domain != c::AF_INET6) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "QUIC needs an IPv4 or IPv6 datagram \
+                                       socket"))
+        }
+        Ok(self.inner.configure_for_quic(metadata.domain == c::AF_INET6))
+    }
+
+    /// Executes an operation of the `PACKET_ADD_MEMBERSHIP` type.
+    ///
+    /// This adds a link-layer membership to a packet (`AF_PACKET`) socket,
+    /// making the interface with the index `ifindex` receive the packets
+    /// selected by `mode`, such as all packets in promiscuous mode or the
+    /// packets of a link-layer multicast group.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn join_packet_membership(&self,
+                                  ifindex: u32,
+                                  mode: &MulticastMode) -> io::Result<()> {
+        self.inner.join_packet_membership(ifindex, mode)
+    }
+
+    /// Executes an operation of the `PACKET_DROP_MEMBERSHIP` type.
+    ///
+    /// For more information about this option, see
+    /// [`join_packet_membership`][link].
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// [link]: #method.join_packet_membership
+    #[cfg(target_os = "linux")]
+    pub fn leave_packet_membership(&self,
+                                   ifindex: u32,
+                                   mode: &MulticastMode) -> io::Result<()> {
+        self.inner.leave_packet_membership(ifindex, mode)
+    }
+
+    /// Returns the time at which the last packet passed to the user was
+    /// received by the kernel.
+    ///
+    /// This uses the `SIOCGSTAMPNS` ioctl, which is supported by all kernel
+    /// versions and is simpler to use than per-packet timestamping with
+    /// `SO_TIMESTAMPING`, at the cost of an extra system call per packet. An
+    /// error with `ENOENT` is returned if no packet has been received yet.
+    ///
+    /// This function is only available on Linux, and not when the `no-ioctl`
+    /// feature is enabled.
+    #[cfg(all(target_os = "linux", not(feature = "no-ioctl")))]
+    pub fn last_packet_timestamp(&self) -> io::Result<SystemTime> {
+        self.inner.last_packet_timestamp()
+    }
+
+    /// Enables per-packet receive timestamps and drop counts on this socket,
+    /// which are then reported by `MsgHdrMut::timestamps` for messages
+    /// received with `recv_msg`.
+    ///
+    /// This sets the `SO_TIMESTAMPING` option with the flags for software
+    /// receive timestamps, and for raw hardware receive timestamps if
+    /// `hardware` is set, as well as the `SO_RXQ_OVFL` option. Hardware
+    /// timestamps also have to be enabled on the network card itself, with
+    /// the `SIOCSHWTSTAMP` ioctl, which needs the `CAP_NET_ADMIN`
+    /// capability.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn enable_rx_timestamps(&self, hardware: bool) -> io::Result<()> {
+        self.inner.enable_rx_timestamps(hardware)
+    }
+
+    /// Gets the value of the `SO_TS_CLOCK` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_timestamp_clock`][link].
+    ///
+    /// This function is only available on FreeBSD.
+    ///
+    /// [link]: #method.set_timestamp_clock
+    #[cfg(target_os = "freebsd")]
+    pub fn timestamp_clock(&self) -> io::Result<TimestampClock> {
+        match self.inner.timestamp_clock()? {
+            c::SO_TS_REALTIME_MICRO => Ok(TimestampClock::RealtimeMicro),
+            c::SO_TS_BINTIME => Ok(TimestampClock::Bintime),
+            c::SO_TS_REALTIME => Ok(TimestampClock::Realtime),
+            c::SO_TS_MONOTONIC => Ok(TimestampClock::Monotonic),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData,
+                                    "unknown timestamp clock")),
+        }
+    }
+
+    /// Sets the value of the `SO_TS_CLOCK` option on this socket.
+    ///
+    /// This selects the clock, and with it the format of the control
+    /// message, used for the receive timestamps reported while `SO_TIMESTAMP`
+    /// is enabled. Choosing `TimestampClock::Monotonic` allows comparing
+    /// timestamps with a monotonic clock base, as on other platforms.
+    ///
+    /// This function is only available on FreeBSD.
+    #[cfg(target_os = "freebsd")]
+    pub fn set_timestamp_clock(&self, clock: TimestampClock)
+                               -> io::Result<()> {
+        let clock = match clock {
+            TimestampClock::RealtimeMicro => c::SO_TS_REALTIME_MICRO,
+            TimestampClock::Bintime => c::SO_TS_BINTIME,
+            TimestampClock::Realtime => c::SO_TS_REALTIME,
+            TimestampClock::Monotonic => c::SO_TS_MONOTONIC,
+        };
+        self.inner.set_timestamp_clock(clock)
+    }
+
+    /// Gets the value of the `SO_BINDTODEVICE` option on this socket.
+    ///
+    /// This returns the name of the interface this socket is bound to, or
+    /// `None` if it isn't bound to one. For more information about this
+    /// option, see [`bind_device`][link].
+    ///
+    /// This function is only available on Linux and Android.
+    ///
+    /// [link]: #method.bind_device
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn device(&self) -> io::Result<Option<Vec<u8>>> {
+        self.inner.device()
+    }
+
+    /// Sets the value of the `SO_BINDTODEVICE` option on this socket.
+    ///
+    /// If a socket is bound to an interface, only packets received from that
+    /// particular interface are processed by the socket and packets it sends
+    /// leave through that interface. `interface` is the name of the
+    /// interface, such as `b"eth0"`, and `None` removes the binding.
+    ///
+    /// This option has to be set before the socket is bound, and usually
+    /// needs the `CAP_NET_RAW` capability.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn bind_device(&self, interface: Option<&[u8]>) -> io::Result<()> {
+        self.inner.bind_device(interface)
+    }
+
+    /// Prepares this socket for use by a DHCP client on the interface named
+    /// `interface`, and binds it to `0.0.0.0:68`.
+    ///
+    /// A DHCP client has no address yet, so it listens on the wildcard
+    /// address and broadcasts its requests. This sets `SO_REUSEADDR` and
+    /// `SO_BROADCAST`, restricts the socket to `interface` so that only the
+    /// replies received on it are seen when the host has several interfaces,
+    /// and then binds the socket. The options are set before the socket is
+    /// bound as the system requires.
+    ///
+    /// The socket is restricted to the interface with `SO_BINDTODEVICE` on
+    /// Linux and Android, and with `IP_BOUND_IF` on macOS and iOS. The other
+    /// BSDs can't restrict a socket to an interface, so there `IP_RECVIF` is
+    /// set instead, which reports the interface each datagram arrived on in
+    /// an `IP_RECVIF` control message for the caller to filter on. On the
+    /// BSDs `SO_REUSEPORT` is set as well, so that clients on several
+    /// interfaces can bind the port at the same time.
+    ///
+    /// This should be called on a newly created IPv4 `SOCK_DGRAM` socket.
+    ///
+    /// This function is only available on Linux, Android, macOS, iOS and the
+    /// BSDs.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn bind_dhcp_client(&self, interface: &[u8]) -> io::Result<()> {
+        self.set_reuse_address(true)?;
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        self.set_reuse_port(true)?;
+        self.set_broadcast(true)?;
+        self.inner.restrict_to_interface(interface)?;
+        let addr = net::SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 68);
+        self.bind(&addr.into())
+    }
+
+    /// Gets the value of the `SO_MEMINFO` option on this socket.
+    ///
+    /// This returns the kernel's memory accounting for this socket, which can
+    /// be useful for diagnosing memory pressure on a per-socket basis.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn meminfo(&self) -> io::Result<MemInfo> {
+        self.inner.meminfo().map(|meminfo| MemInfo { raw: meminfo })
+    }
+
+    /// Sets the value of the `CAN_RAW_FILTER` option on this socket.
+    ///
+    /// A raw CAN socket only receives frames whose identifier passes at
+    /// least one of `filters`, so uninteresting traffic is dropped by the
+    /// kernel. An empty list of filters disables reception of data frames
+    /// altogether. New sockets receive all frames.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn set_can_filters(&self, filters: &[CanFilter]) -> io::Result<()> {
+        self.inner.set_can_filters(filters)
+    }
+
+    /// Gets the value of the `CAN_RAW_ERR_FILTER` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_can_err_filter`][link].
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// [link]: #method.set_can_err_filter
+    #[cfg(target_os = "linux")]
+    pub fn can_err_filter(&self) -> io::Result<u32> {
+        self.inner.can_err_filter()
+    }
+
+    /// Sets the value of the `CAN_RAW_ERR_FILTER` option on this socket.
+    ///
+    /// This is a mask of the `CAN_ERR_*` error classes which are delivered to
+    /// this raw CAN socket as error frames. No error frames are received by
+    /// default.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn set_can_err_filter(&self, mask: u32) -> io::Result<()> {
+        self.inner.set_can_err_filter(mask)
+    }
+
+    /// Gets the value of the `CAN_RAW_LOOPBACK` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_can_loopback`][link].
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// [link]: #method.set_can_loopback
+    #[cfg(target_os = "linux")]
+    pub fn can_loopback(&self) -> io::Result<bool> {
+        self.inner.can_loopback()
+    }
+
+    /// Sets the value of the `CAN_RAW_LOOPBACK` option on this socket.
+    ///
+    /// If enabled, which is the default, frames sent on the interface are
+    /// also delivered to the other CAN sockets on this host which are bound
+    /// to the same interface.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn set_can_loopback(&self, loopback: bool) -> io::Result<()> {
+        self.inner.set_can_loopback(loopback)
+    }
+
+    /// Gets the value of the `CAN_RAW_FD_FRAMES` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_can_fd_frames`][link].
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// [link]: #method.set_can_fd_frames
+    #[cfg(target_os = "linux")]
+    pub fn can_fd_frames(&self) -> io::Result<bool> {
+        self.inner.can_fd_frames()
+    }
+
+    /// Sets the value of the `CAN_RAW_FD_FRAMES` option on this socket.
+    ///
+    /// If enabled, this raw CAN socket can send and receive CAN FD frames in
+    /// addition to classic CAN frames. A CAN FD frame takes `CANFD_MTU` (72)
+    /// bytes rather than the `CAN_MTU` (16) bytes of a classic frame, so
+    /// receive buffers must be at least 72 bytes long or CAN FD frames are
+    /// truncated. The number of bytes received tells the two kinds of frames
+    /// apart. Enabling this fails if the kernel doesn't support CAN FD.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn set_can_fd_frames(&self, fd_frames: bool) -> io::Result<()> {
+        self.inner.set_can_fd_frames(fd_frames)
+    }
+
+    /// Sets the value of the `RXRPC_SECURITY_KEY` option on this socket.
+    ///
+    /// This selects the key used to secure the calls made by this RxRPC
+    /// client socket, by the description of a key in the process's keyrings,
+    /// such as `"afs@EXAMPLE.COM"` for a kAFS token. It has to be set before
+    /// the first call is made.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn set_rxrpc_security_key(&self, key: &str) -> io::Result<()> {
+        self.inner.set_rxrpc_security_key(key)
+    }
+
+    /// Sets the value of the `RXRPC_SECURITY_KEYRING` option on this socket.
+    ///
+    /// This selects the keyring holding the server keys with which this
+    /// RxRPC server socket secures incoming calls, by its description. It
+    /// has to be set before the socket is bound.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn set_rxrpc_security_keyring(&self, keyring: &str) -> io::Result<()> {
+        self.inner.set_rxrpc_security_keyring(keyring)
+    }
+
+    /// Sets the value of the `RXRPC_MIN_SECURITY_LEVEL` option on this
+    /// socket.
+    ///
+    /// Calls made on this RxRPC socket are secured at least at `level`.
+    /// Without this option the key's default level is used.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn set_rxrpc_min_security_level(&self, level: RxrpcSecurityLevel)
+                                        -> io::Result<()> {
+        let level = match level {
+            RxrpcSecurityLevel::Plain => 0,
+            RxrpcSecurityLevel::Auth => 1,
+            RxrpcSecurityLevel::Encrypt => 2,
+        };
+        self.inner.set_rxrpc_min_security_level(level)
+    }
+
+    /// Executes an operation of the `NETLINK_ADD_MEMBERSHIP` type.
+    ///
+    /// This subscribes a netlink (`AF_NETLINK`) socket to the multicast group
+    /// `group`, such as `RTNLGRP_LINK` for routing sockets, after which it
+    /// receives the notifications sent to that group. Unlike the group bitmask
+    /// in the socket's address, this works for groups numbered above 32.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn join_netlink_membership(&self, group: u32) -> io::Result<()> {
+        self.inner.join_netlink_membership(group)
+    }
+
+    /// Executes an operation of the `NETLINK_DROP_MEMBERSHIP` type.
+    ///
+    /// For more information about this option, see
+    /// [`join_netlink_membership`][link].
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// [link]: #method.join_netlink_membership
+    #[cfg(target_os = "linux")]
+    pub fn leave_netlink_membership(&self, group: u32) -> io::Result<()> {
+        self.inner.leave_netlink_membership(group)
+    }
+
+    /// Gets the value of the `NETLINK_EXT_ACK` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_netlink_ext_ack`][link].
+    ///
+    /// This function is only available on Linux.
+    ///
+    /// [link]: #method.set_netlink_ext_ack
+    #[cfg(target_os = "linux")]
+    pub fn netlink_ext_ack(&self) -> io::Result<bool> {
+        self.inner.netlink_ext_ack()
+    }
+
+    /// Sets the value of the `NETLINK_EXT_ACK` option on this socket.
+    ///
+    /// If enabled, the error messages the kernel sends on this netlink socket
+    /// carry extended acknowledgement attributes, such as a description of
+    /// the error and the offset of the attribute which caused it. Enabling
+    /// this fails on kernels older than 4.12.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn set_netlink_ext_ack(&self, ext_ack: bool) -> io::Result<()> {
+        self.inner.set_netlink_ext_ack(ext_ack)
+    }
+}
+
+// Returns the bandwidth-delay product in bytes, saturating at the largest size
+// the buffer size options can hold.
+fn bdp(bandwidth: u64, rtt: Duration) -> usize {
+    let bytes = bandwidth as u128 * rtt.as_nanos() / 1_000_000_000;
+    cmp::min(bytes, i32::MAX as u128) as usize
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        self.inner.read_vectored(bufs)
+    }
+}
+
+impl<'a> Read for &'a Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&self.inner).read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        (&self.inner).read_vectored(bufs)
+    }
+}
+
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        self.inner.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<'a> Write for &'a Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&self.inner).write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        (&self.inner).write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&self.inner).flush()
+    }
+}
+
+impl fmt::Debug for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl From<net::TcpStream> for Socket {
+    fn from(socket: net::TcpStream) -> Socket {
+        Socket { inner: socket.into() }
+    }
+}
+
+impl From<net::TcpListener> for Socket {
+    fn from(socket: net::TcpListener) -> Socket {
+        Socket { inner: socket.into() }
+    }
+}
+
+impl From<net::UdpSocket> for Socket {
+    fn from(socket: net::UdpSocket) -> Socket {
+        Socket { inner: socket.into() }
+    }
+}
+
+impl From<Socket> for net::TcpStream {
+    fn from(socket: Socket) -> net::TcpStream {
+        socket.inner.into()
+    }
+}
+
+impl From<Socket> for net::TcpListener {
+    fn from(socket: Socket) -> net::TcpListener {
+        socket.inner.into()
+    }
+}
+
+impl From<Socket> for net::UdpSocket {
+    fn from(socket: Socket) -> net::UdpSocket {
+        socket.inner.into()
+    }
+}
+
+#[cfg(windows)]
+impl Event {
+    /// Creates a new, unsignaled, manual-reset event.
+    ///
+    /// This function corresponds to `WSACreateEvent`.
+    pub fn new() -> io::Result<Event> {
+        sys::Event::new().map(|e| Event { inner: e })
+    }
+}
+
+#[cfg(windows)]
+impl NetworkEvents {
+    /// The empty set of events.
+    pub fn empty() -> NetworkEvents {
+        NetworkEvents(0)
+    }
+
+    /// Data is available to read, corresponding to `FD_READ`.
+    pub fn read() -> NetworkEvents {
+        NetworkEvents(sys::FD_READ)
+    }
+
+    /// The socket is ready for writing, corresponding to `FD_WRITE`.
+    pub fn write() -> NetworkEvents {
+        NetworkEvents(sys::FD_WRITE)
+    }
+
+    /// Out-of-band data is available to read, corresponding to `FD_OOB`.
+    pub fn oob() -> NetworkEvents {
+        NetworkEvents(sys::FD_OOB)
+    }
+
+    /// A connection is ready to be accepted, corresponding to `FD_ACCEPT`.
+    pub fn accept() -> NetworkEvents {
+        NetworkEvents(sys::FD_ACCEPT)
+    }
+
+    /// A connection attempt completed, corresponding to `FD_CONNECT`.
+    pub fn connect() -> NetworkEvents {
+        NetworkEvents(sys::FD_CONNECT)
+    }
+
+    /// The connection was closed, corresponding to `FD_CLOSE`.
+    pub fn close() -> NetworkEvents {
+        NetworkEvents(sys::FD_CLOSE)
+    }
+
+    /// Returns whether all events in `other` are also in this set.
+    pub fn contains(&self, other: NetworkEvents) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+#[cfg(windows)]
+impl BitOr for NetworkEvents {
+    type Output = NetworkEvents;
+
+    fn bitor(self, other: NetworkEvents) -> NetworkEvents {
+        NetworkEvents(self.0 | other.0)
+    }
+}
+
+#[cfg(windows)]
+impl From<i32> for NetworkEvents {
+    fn from(a: i32) -> NetworkEvents {
+        NetworkEvents(a)
+    }
+}
+
+#[cfg(windows)]
+impl From<NetworkEvents> for i32 {
+    fn from(a: NetworkEvents) -> i32 {
+        a.0
+    }
+}
+
+impl Domain {
+    /// Domain for IPv4 communication, corresponding to `AF_INET`.
+    pub fn ipv4() -> Domain {
+        Domain(c::AF_INET)
+    }
+
+    /// Domain for IPv6 communication, corresponding to `AF_INET6`.
+    pub fn ipv6() -> Domain {
+        Domain(c::AF_INET6)
+    }
+
+    /// Domain for Unix socket communication, corresponding to `AF_UNIX`.
+    ///
+    /// This function is only available on Unix.
+    #[cfg(unix)]
+    pub fn unix() -> Domain {
+        Domain(c::AF_UNIX)
+    }
+
+    /// Domain for RxRPC communication, corresponding to `AF_RXRPC`.
+    ///
+    /// RxRPC sockets are created with `Type::dgram` and the family of the
+    /// underlying UDP transport, `AF_INET` or `AF_INET6`, as the protocol.
+    ///
+    /// This function is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn rxrpc() -> Domain {
+        Domain(c::AF_RXRPC)
+    }
+
+    /// Domain for any other communication domain, given as its raw `AF_*`
+    /// value.
+    ///
+    /// This allows creating sockets in domains which this crate has no
+    /// dedicated support for. Their addresses can be constructed and read
+    /// with `SockAddr::from_bytes` and `SockAddr::as_bytes`, and are passed
+    /// through to the system unchanged by methods such as `bind`, `connect`
+    /// and `send_to`.
+    pub fn other(raw: i32) -> Domain {
+        Domain(raw)
+    }
+}
+
+impl From<i32> for Domain {
+    fn from(a: i32) -> Domain {
+        Domain(a)
+    }
+}
+
+impl From<Domain> for i32 {
+    fn from(a: Domain) -> i32 {
+        a.0
+    }
+}
+
+impl Type {
+    /// Type corresponding to `SOCK_STREAM`
+    ///
+    /// Used for protocols such as TCP.
+    pub fn stream() -> Type {
+        Type(c::SOCK_STREAM)
+    }
+
+    /// Type corresponding to `SOCK_DGRAM`
+    ///
+    /// Used for protocols such as UDP.
+    pub fn dgram() -> Type {
+        Type(c::SOCK_DGRAM)
+    }
+
+    /// Type corresponding to `SOCK_SEQPACKET`
+    pub fn seqpacket() -> Type {
+        Type(c::SOCK_SEQPACKET)
+    }
+
+    /// Type corresponding to `SOCK_RAW`
+    pub fn raw() -> Type {
+        Type(c::SOCK_RAW)
+    }
+
+    /// Creates the socket in nonblocking mode, corresponding to
+    /// `SOCK_NONBLOCK`.
+    ///
+    /// This saves a call to `set_nonblocking` after creating the socket. On
+    /// kernels which don't support the flag the socket is switched to
+    /// nonblocking mode after it's created instead.
+    ///
+    /// This function is only available on Linux, Android and the BSDs.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn nonblocking(self) -> Type {
+        Type(self.0 | c::SOCK_NONBLOCK)
+    }
+
+    /// Creates the socket with the close-on-exec flag set, corresponding to
+    /// `SOCK_CLOEXEC`.
+    ///
+    /// Sockets created by this crate always have the flag set, and where
+    /// supported it is set atomically when creating the socket. This
+    /// function makes the flag explicit in the type, for example when the
+    /// raw value is passed elsewhere.
+    ///
+    /// This function is only available on Linux, Android and the BSDs.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn cloexec(self) -> Type {
+        Type(self.0 | c::SOCK_CLOEXEC)
+    }
+}
+
+impl From<i32> for Type {
+    fn from(a: i32) -> Type {
+        Type(a)
+    }
+}
+
+impl From<Type> for i32 {
+    fn from(a: Type) -> i32 {
+        a.0
+    }
+}
+
+impl Protocol {
+    /// Protocol corresponding to `ICMPv4`
+    ///
+    /// Ping-style sockets are created with this protocol and `Type::raw()`,
+    /// which on Windows requires the process to run with administrator
+    /// privileges. Windows does not support `Type::dgram()` ICMP sockets,
+    /// while on Linux they can be used without privileges if permitted by the
+    /// `net.ipv4.ping_group_range` sysctl. Note that on Windows and most Unix
+    /// platforms data received on a raw ICMPv4 socket includes the IPv4
+    /// header.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use socket2::{Socket, Domain, Type, Protocol};
+    ///
+    /// let socket = Socket::new(Domain::ipv4(),
+    ///                          Type::raw(),
+    ///                          Some(Protocol::icmpv4())).unwrap();
+    /// ```
+    pub fn icmpv4() -> Protocol {
+        Protocol(sys::IPPROTO_ICMP)
+    }
+
+    /// Protocol corresponding to `ICMPv6`
+    ///
+    /// The same privilege requirements as for [`icmpv4`] apply. Unlike for
+    /// ICMPv4 no IP header is included in received data.
+    ///
+    /// [`icmpv4`]: #method.icmpv4
+    pub fn icmpv6() -> Protocol {
+        Protocol(sys::IPPROTO_ICMPV6)
+    }
+
+    /// Protocol corresponding to `MPTCP`, Multipath TCP.
+    ///
+    /// Multipath TCP sockets are created with this protocol and
+    /// `Type::stream()`, and otherwise used like TCP sockets. See
+    /// `Socket::new_mptcp` for creating one with a fallback to TCP.
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn mptcp() -> Protocol {
+        Protocol(c::IPPROTO_MPTCP)
+    }
+}
+
+impl From<i32> for Protocol {
+    fn from(a: i32) -> Protocol {
+        Protocol(a)
+    }
 }
 
-impl Read for Socket {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner.read(buf)
+impl From<Protocol> for i32 {
+    fn from(a: Protocol) -> i32 {
+        a.0
     }
 }
 
-impl<'a> Read for &'a Socket {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        (&self.inner).read(buf)
+#[cfg(any(target_os = "linux", target_os = "android",
+      target_os = "macos", target_os = "ios",
+      target_os = "freebsd", target_os = "dragonfly",
+      target_os = "openbsd", target_os = "netbsd"))]
+impl UCred {
+    /// Returns the effective user ID of the peer process.
+    pub fn uid(&self) -> u32 {
+        self.uid
     }
-}
 
-impl Write for Socket {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.inner.write(buf)
+    /// Returns the effective group ID of the peer process.
+    pub fn gid(&self) -> u32 {
+        self.gid
     }
 
-    fn flush(&mut self) -> io::Result<()> {
-        self.inner.flush()
+    /// Returns the ID of the peer process, if the system reports it.
+    pub fn pid(&self) -> Option<i32> {
+        self.pid
     }
 }
 
-impl<'a> Write for &'a Socket {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        (&self.inner).write(buf)
+impl SocketOptions {
+    /// Returns the value of `SO_REUSEADDR`.
+    pub fn reuse_address(&self) -> Option<bool> {
+        self.reuse_address
     }
 
-    fn flush(&mut self) -> io::Result<()> {
-        (&self.inner).flush()
+    /// Returns the value of `TCP_NODELAY`.
+    pub fn nodelay(&self) -> Option<bool> {
+        self.nodelay
     }
-}
 
-impl fmt::Debug for Socket {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.inner.fmt(f)
+    /// Returns the value of `SO_RCVBUF`.
+    pub fn recv_buffer_size(&self) -> Option<usize> {
+        self.recv_buffer_size
     }
-}
 
-impl From<net::TcpStream> for Socket {
-    fn from(socket: net::TcpStream) -> Socket {
-        Socket { inner: socket.into() }
+    /// Returns the value of `SO_SNDBUF`.
+    pub fn send_buffer_size(&self) -> Option<usize> {
+        self.send_buffer_size
     }
-}
 
-impl From<net::TcpListener> for Socket {
-    fn from(socket: net::TcpListener) -> Socket {
-        Socket { inner: socket.into() }
+    /// Returns the read timeout, `SO_RCVTIMEO`.
+    pub fn read_timeout(&self) -> Option<Option<Duration>> {
+        self.read_timeout
     }
-}
 
-impl From<net::UdpSocket> for Socket {
-    fn from(socket: net::UdpSocket) -> Socket {
-        Socket { inner: socket.into() }
+    /// Returns the write timeout, `SO_SNDTIMEO`.
+    pub fn write_timeout(&self) -> Option<Option<Duration>> {
+        self.write_timeout
     }
-}
 
-impl From<Socket> for net::TcpStream {
-    fn from(socket: Socket) -> net::TcpStream {
-        socket.inner.into()
+    /// Returns the value of `SO_LINGER`.
+    pub fn linger(&self) -> Option<Option<Duration>> {
+        self.linger
+    }
+
+    /// Returns the keepalive idle time, if keepalive is enabled.
+    pub fn keepalive(&self) -> Option<Option<Duration>> {
+        self.keepalive
+    }
+
+    /// Returns the value of `IP_TTL`.
+    pub fn ttl(&self) -> Option<u32> {
+        self.ttl
     }
 }
 
-impl From<Socket> for net::TcpListener {
-    fn from(socket: Socket) -> net::TcpListener {
-        socket.inner.into()
+impl TcpKeepalive {
+    /// Returns a new set of parameters, with all of them left at the
+    /// system's defaults.
+    pub fn new() -> TcpKeepalive {
+        TcpKeepalive::default()
+    }
+
+    /// Sets the time a connection has to be idle before the first keepalive
+    /// probe is sent.
+    pub fn with_time(mut self, time: Duration) -> TcpKeepalive {
+        self.time = Some(time);
+        self
+    }
+
+    /// Sets the time between keepalive probes which aren't acknowledged.
+    ///
+    /// This function is only available on Windows, Linux, Android, macOS,
+    /// iOS, FreeBSD, DragonFly and NetBSD.
+    #[cfg(any(windows, target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly",
+              target_os = "netbsd"))]
+    pub fn with_interval(mut self, interval: Duration) -> TcpKeepalive {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Sets the number of keepalive probes which may go unacknowledged
+    /// before the connection is dropped.
+    ///
+    /// This function is only available on Windows, Linux, Android, macOS,
+    /// iOS, FreeBSD, DragonFly and NetBSD.
+    #[cfg(any(windows, target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly",
+              target_os = "netbsd"))]
+    pub fn with_retries(mut self, retries: u32) -> TcpKeepalive {
+        self.retries = Some(retries);
+        self
     }
 }
 
-impl From<Socket> for net::UdpSocket {
-    fn from(socket: Socket) -> net::UdpSocket {
-        socket.inner.into()
+#[cfg(all(feature = "quic",
+          any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd")))]
+impl QuicFeatures {
+    /// Returns whether the destination address of received datagrams is
+    /// reported.
+    pub fn pktinfo(&self) -> bool {
+        self.pktinfo
+    }
+
+    /// Returns whether the ECN bits of received datagrams are reported.
+    pub fn ecn(&self) -> bool {
+        self.ecn
+    }
+
+    /// Returns whether the system supports UDP segmentation offload.
+    pub fn gso(&self) -> bool {
+        self.gso
+    }
+
+    /// Returns whether UDP receive offload is enabled.
+    pub fn gro(&self) -> bool {
+        self.gro
+    }
+
+    /// Returns whether ICMP errors are received on the error queue.
+    pub fn recverr(&self) -> bool {
+        self.recverr
     }
 }
 
-impl Domain {
-    /// Domain for IPv4 communication, corresponding to `AF_INET`.
-    pub fn ipv4() -> Domain {
-        Domain(c::AF_INET)
+impl Metadata {
+    /// Returns the communication domain of the socket.
+    pub fn domain(&self) -> Domain {
+        Domain(self.domain)
     }
 
-    /// Domain for IPv6 communication, corresponding to `AF_INET6`.
-    pub fn ipv6() -> Domain {
-        Domain(c::AF_INET6)
+    /// Returns the type of the socket.
+    pub fn ty(&self) -> Type {
+        Type(self.ty)
+    }
+
+    /// Returns the protocol of the socket, if the system reports it.
+    ///
+    /// The protocol is reported on Linux, Android, FreeBSD and Windows.
+    pub fn protocol(&self) -> Option<Protocol> {
+        self.protocol.map(Protocol)
     }
 }
 
-impl From<i32> for Domain {
-    fn from(a: i32) -> Domain {
-        Domain(a)
+#[cfg(target_os = "linux")]
+impl CanFilter {
+    /// Creates a filter passing frames whose identifier matches `id` in the
+    /// bits set in `mask`.
+    pub fn new(id: u32, mask: u32) -> CanFilter {
+        CanFilter { id, mask }
+    }
+
+    /// Returns a filter which passes exactly the frames this filter rejects,
+    /// by setting `CAN_INV_FILTER` in its identifier.
+    pub fn inverted(self) -> CanFilter {
+        CanFilter { id: self.id ^ c::CAN_INV_FILTER, mask: self.mask }
+    }
+
+    /// Returns the identifier of this filter.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the mask of this filter.
+    pub fn mask(&self) -> u32 {
+        self.mask
     }
 }
 
-impl From<Domain> for i32 {
-    fn from(a: Domain) -> i32 {
-        a.into()
+#[cfg(target_os = "linux")]
+impl MemInfo {
+    /// Returns `SK_MEMINFO_RMEM_ALLOC`, the amount of memory allocated for
+    /// receiving data.
+    pub fn rmem_alloc(&self) -> u32 {
+        self.raw[c::SK_MEMINFO_RMEM_ALLOC as usize]
+    }
+
+    /// Returns `SK_MEMINFO_RCVBUF`, the size of the receive buffer.
+    pub fn rcvbuf(&self) -> u32 {
+        self.raw[c::SK_MEMINFO_RCVBUF as usize]
+    }
+
+    /// Returns `SK_MEMINFO_WMEM_ALLOC`, the amount of memory allocated for
+    /// sending data.
+    pub fn wmem_alloc(&self) -> u32 {
+        self.raw[c::SK_MEMINFO_WMEM_ALLOC as usize]
+    }
+
+    /// Returns `SK_MEMINFO_SNDBUF`, the size of the send buffer.
+    pub fn sndbuf(&self) -> u32 {
+        self.raw[c::SK_MEMINFO_SNDBUF as usize]
+    }
+
+    /// Returns `SK_MEMINFO_FWD_ALLOC`, the amount of memory reserved for
+    /// future use.
+    pub fn fwd_alloc(&self) -> u32 {
+        self.raw[c::SK_MEMINFO_FWD_ALLOC as usize]
+    }
+
+    /// Returns `SK_MEMINFO_WMEM_QUEUED`, the amount of memory used by data
+    /// queued for sending.
+    pub fn wmem_queued(&self) -> u32 {
+        self.raw[c::SK_MEMINFO_WMEM_QUEUED as usize]
+    }
+
+    /// Returns `SK_MEMINFO_OPTMEM`, the amount of memory used for socket
+    /// options and ancillary data.
+    pub fn optmem(&self) -> u32 {
+        self.raw[c::SK_MEMINFO_OPTMEM as usize]
+    }
+
+    /// Returns `SK_MEMINFO_BACKLOG`, the amount of memory used by packets in
+    /// the backlog queue.
+    pub fn backlog(&self) -> u32 {
+        self.raw[c::SK_MEMINFO_BACKLOG as usize]
+    }
+
+    /// Returns `SK_MEMINFO_DROPS`, the number of packets dropped by this
+    /// socket.
+    pub fn drops(&self) -> u32 {
+        self.raw[c::SK_MEMINFO_DROPS as usize]
     }
 }
 
-impl Type {
-    /// Type corresponding to `SOCK_STREAM`
-    ///
-    /// Used for protocols such as TCP.
-    pub fn stream() -> Type {
-        Type(c::SOCK_STREAM)
+#[cfg(any(target_os = "linux", target_os = "freebsd", windows))]
+impl TcpInfo {
+    /// Returns the state of the connection.
+    pub fn state(&self) -> TcpState {
+        self.state
     }
 
-    /// Type corresponding to `SOCK_DGRAM`
+    /// Returns the smoothed round trip time estimated by the system.
+    pub fn rtt(&self) -> Duration {
+        self.rtt
+    }
+
+    /// Returns the number of retransmissions on the connection.
     ///
-    /// Used for protocols such as UDP.
-    pub fn dgram() -> Type {
-        Type(c::SOCK_DGRAM)
+    /// On Linux and FreeBSD this is the number of retransmitted segments, on
+    /// Windows the number of fast retransmits and retransmission timeouts.
+    pub fn retransmits(&self) -> u32 {
+        self.retransmits
     }
 
-    /// Type corresponding to `SOCK_SEQPACKET`
-    pub fn seqpacket() -> Type {
-        Type(c::SOCK_SEQPACKET)
+    /// Returns the most recent delivery rate in bytes per second, if the
+    /// system reports it.
+    ///
+    /// This is only reported by Linux 4.9 and later.
+    pub fn delivery_rate(&self) -> Option<u64> {
+        self.delivery_rate
     }
 
-    /// Type corresponding to `SOCK_RAW`
-    pub fn raw() -> Type {
-        Type(c::SOCK_RAW)
+    /// Returns the size of the congestion window in bytes.
+    pub fn cwnd(&self) -> u32 {
+        self.cwnd
     }
 }
 
-impl From<i32> for Type {
-    fn from(a: i32) -> Type {
-        Type(a)
+impl Ipv4Header {
+    // Parses the header at the start of `packet`, returning it along with
+    // the payload following it.
+    fn split(packet: &[u8]) -> io::Result<(Ipv4Header, &[u8])> {
+        let header = Ipv4Header::parse(packet)?;
+        let end = cmp::min(header.total_len, packet.len());
+        Ok((header, &packet[header.header_len..end]))
     }
-}
 
-impl From<Type> for i32 {
-    fn from(a: Type) -> i32 {
-        a.into()
+    fn parse(packet: &[u8]) -> io::Result<Ipv4Header> {
+        let invalid = |msg: &str| {
+            io::Error::new(io::ErrorKind::InvalidData, msg)
+        };
+        if packet.len() < 20 || packet[0] >> 4 != 4 {
+            return Err(invalid("received data isn't an IPv4 packet"))
+        }
+        let header_len = (packet[0] & 0x0f) as usize * 4;
+        if header_len < 20 || packet.len() < header_len {
+            return Err(invalid("invalid IPv4 header length"))
+        }
+        let len = [packet[2], packet[3]];
+        let off = [packet[6], packet[7]];
+        // macOS and iOS hand the header to raw sockets after `ip_input` has
+        // converted these fields and removed the header from the length.
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        let (total_len, flags_fragment) =
+            (u16::from_ne_bytes(len) as usize + header_len,
+             u16::from_ne_bytes(off));
+        #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+        let (total_len, flags_fragment) =
+            (u16::from_be_bytes(len) as usize, u16::from_be_bytes(off));
+        if total_len < header_len {
+            return Err(invalid("invalid IPv4 total length"))
+        }
+        Ok(Ipv4Header {
+            header_len,
+            total_len,
+            tos: packet[1],
+            identification: u16::from_be_bytes([packet[4], packet[5]]),
+            flags_fragment,
+            ttl: packet[8],
+            protocol: packet[9],
+            source: Ipv4Addr::new(packet[12], packet[13],
+                                  packet[14], packet[15]),
+            destination: Ipv4Addr::new(packet[16], packet[17],
+                                       packet[18], packet[19]),
+        })
     }
-}
 
-impl From<i32> for Protocol {
-    fn from(a: i32) -> Protocol {
-        Protocol(a)
+    /// Returns the length of the header in bytes, including any options.
+    pub fn header_len(&self) -> usize {
+        self.header_len
+    }
+
+    /// Returns the total length of the packet in bytes, including the
+    /// header.
+    pub fn total_len(&self) -> usize {
+        self.total_len
+    }
+
+    /// Returns the type-of-service byte of the packet.
+    pub fn tos(&self) -> u8 {
+        self.tos
+    }
+
+    /// Returns the identification field of the packet, used to reassemble
+    /// fragments.
+    pub fn identification(&self) -> u16 {
+        self.identification
+    }
+
+    /// Returns whether the "don't fragment" flag is set.
+    pub fn dont_fragment(&self) -> bool {
+        self.flags_fragment & 0x4000 != 0
+    }
+
+    /// Returns whether the "more fragments" flag is set.
+    pub fn more_fragments(&self) -> bool {
+        self.flags_fragment & 0x2000 != 0
+    }
+
+    /// Returns the offset of this fragment in the original packet, in bytes.
+    pub fn fragment_offset(&self) -> usize {
+        (self.flags_fragment & 0x1fff) as usize * 8
+    }
+
+    /// Returns the time-to-live of the packet.
+    pub fn ttl(&self) -> u8 {
+        self.ttl
+    }
+
+    /// Returns the protocol of the payload, such as `IPPROTO_ICMP`.
+    pub fn protocol(&self) -> u8 {
+        self.protocol
+    }
+
+    /// Returns the source address of the packet.
+    pub fn source(&self) -> Ipv4Addr {
+        self.source
+    }
+
+    /// Returns the destination address of the packet.
+    pub fn destination(&self) -> Ipv4Addr {
+        self.destination
     }
 }
 
-impl From<Protocol> for i32 {
-    fn from(a: Protocol) -> i32 {
-        a.into()
+#[cfg(test)]
+mod tests {
+    use std::io::{self, IoSlice, IoSliceMut, Read};
+    use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::c;
+    #[cfg(unix)]
+    use {BufferProvider, MsgHdr, MsgHdrMut, RecvScratch};
+    use {Domain, Ipv4Header, Protocol, Socket, Type};
+
+    // The largest UDP payload over IPv4: the maximum IP packet size minus
+    // the 20 byte IP header and the 8 byte UDP header.
+    const MAX_DATAGRAM: usize = 65535 - 20 - 8;
+
+    // Returns both ends of a TCP connection over the loopback interface.
+    fn tcp_pair() -> (Socket, Socket) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+        (stream.into(), listener.accept().unwrap().0.into())
+    }
+
+    // Returns two UDP sockets connected to each other, with buffers large
+    // enough for datagrams of the maximum size.
+    fn udp_pair() -> (Socket, Socket) {
+        let any = "127.0.0.1:0".parse::<SocketAddr>().unwrap().into();
+        let a = Socket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+        let b = Socket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+        for socket in [&a, &b].iter() {
+            socket.set_send_buffer_size(1 << 18).unwrap();
+            socket.set_recv_buffer_size(1 << 18).unwrap();
+            socket.bind(&any).unwrap();
+        }
+        a.connect(&b.local_addr().unwrap()).unwrap();
+        b.connect(&a.local_addr().unwrap()).unwrap();
+        (a, b)
+    }
+
+    fn datagram(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    // Builds a packet as the system returns it from a raw socket, with
+    // `options` bytes of options, a payload of `len` bytes and `trailing`
+    // extra bytes after it.
+    fn packet(options: usize, len: usize, trailing: usize) -> Vec<u8> {
+        let header_len = 20 + options;
+        let mut packet = vec![0; header_len + len + trailing];
+        packet[0] = 0x40 | (header_len / 4) as u8;
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        let (total_len, off) = ((len as u16).to_ne_bytes(),
+                                0x4001u16.to_ne_bytes());
+        #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+        let (total_len, off) = (((header_len + len) as u16).to_be_bytes(),
+                                0x4001u16.to_be_bytes());
+        packet[2..4].copy_from_slice(&total_len);
+        packet[6..8].copy_from_slice(&off);
+        packet[8] = 64;
+        packet[9] = 1;
+        packet[12..16].copy_from_slice(&[192, 0, 2, 1]);
+        packet[16..20].copy_from_slice(&[198, 51, 100, 2]);
+        for (i, b) in packet[header_len..].iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        packet
+    }
+
+    #[test]
+    fn ipv4_header() {
+        let packet = packet(0, 8, 0);
+        let (header, payload) = Ipv4Header::split(&packet).unwrap();
+        assert_eq!(header.header_len(), 20);
+        assert_eq!(header.total_len(), 28);
+        assert!(header.dont_fragment());
+        assert!(!header.more_fragments());
+        assert_eq!(header.fragment_offset(), 8);
+        assert_eq!(header.ttl(), 64);
+        assert_eq!(header.protocol(), 1);
+        assert_eq!(header.source(), Ipv4Addr::new(192, 0, 2, 1));
+        assert_eq!(header.destination(), Ipv4Addr::new(198, 51, 100, 2));
+        assert_eq!(payload, &[0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn ipv4_header_options() {
+        let packet = packet(12, 4, 0);
+        let (header, payload) = Ipv4Header::split(&packet).unwrap();
+        assert_eq!(header.header_len(), 32);
+        assert_eq!(header.total_len(), 36);
+        assert_eq!(payload, &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn ipv4_header_trailing_bytes() {
+        let packet = packet(0, 4, 3);
+        let (header, payload) = Ipv4Header::split(&packet).unwrap();
+        assert_eq!(header.total_len(), 24);
+        assert_eq!(payload, &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn ipv4_header_invalid() {
+        let invalid = |packet: &[u8]| {
+            let err = Ipv4Header::split(packet).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        };
+        let valid = packet(8, 4, 0);
+        invalid(&valid[..19]);
+        // The header is longer than the data.
+        invalid(&valid[..24]);
+        let mut v6 = valid.clone();
+        v6[0] = 0x68;
+        invalid(&v6);
+        let mut short = valid.clone();
+        short[0] = 0x44;
+        invalid(&short);
+    }
+
+    #[test]
+    fn recv_vectored_exact_partial() {
+        let (a, b) = tcp_pair();
+        let sender = thread::spawn(move || {
+            for chunk in [&b"abc"[..], b"de", b"fgh"].iter() {
+                a.send(chunk).unwrap();
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+        let (mut x, mut y, mut z) = ([0; 2], [0; 4], [0; 2]);
+        {
+            let mut bufs = [IoSliceMut::new(&mut x), IoSliceMut::new(&mut []),
+                            IoSliceMut::new(&mut y), IoSliceMut::new(&mut z)];
+            b.recv_vectored_exact(&mut &mut bufs[..]).unwrap();
+        }
+        assert_eq!((&x, &y, &z), (b"ab", b"cdef", b"gh"));
+        sender.join().unwrap();
+
+        let mut bufs = [IoSliceMut::new(&mut x)];
+        let err = b.recv_vectored_exact(&mut &mut bufs[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn send_vectored_all_partial() {
+        let (a, b) = tcp_pair();
+        a.set_send_buffer_size(4096).unwrap();
+        let data = (0..1 << 20).map(|i| i as u8).collect::<Vec<_>>();
+        let (x, y) = data.split_at(1000);
+        let mut bufs = [IoSlice::new(x), IoSlice::new(&[]), IoSlice::new(y)];
+        let mut bufs = &mut bufs[..];
+
+        // Nobody reads yet, so the send buffer fills up and the call stops
+        // part way, leaving `bufs` at the data which wasn't sent.
+        a.set_nonblocking(true).unwrap();
+        let err = a.send_vectored_all(&mut bufs).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+        let left = bufs.iter().map(|b| b.len()).sum::<usize>();
+        assert!(left > 0 && left < data.len());
+        assert_eq!(&*bufs[0], &data[data.len() - left..][..bufs[0].len()]);
+
+        let receiver = thread::spawn(move || {
+            let mut received = Vec::new();
+            (&b).read_to_end(&mut received).unwrap();
+            received
+        });
+        a.set_nonblocking(false).unwrap();
+        a.send_vectored_all(&mut bufs).unwrap();
+        assert!(bufs.is_empty());
+        drop(a);
+        assert!(receiver.join().unwrap() == data);
+    }
+
+    #[test]
+    fn raw_values() {
+        assert_eq!(i32::from(Domain::ipv4()), c::AF_INET);
+        assert_eq!(i32::from(Domain::from(c::AF_INET6)), c::AF_INET6);
+        assert_eq!(i32::from(Type::stream()), c::SOCK_STREAM);
+        assert_eq!(i32::from(Type::from(c::SOCK_DGRAM)), c::SOCK_DGRAM);
+        // The protocol numbers are assigned by IANA.
+        assert_eq!(i32::from(Protocol::icmpv4()), 1);
+        assert_eq!(i32::from(Protocol::from(17)), 17);
+    }
+
+    #[test]
+    fn max_size_datagram() {
+        let (a, b) = udp_pair();
+        let data = datagram(MAX_DATAGRAM);
+        assert_eq!(a.send(&data).unwrap(), MAX_DATAGRAM);
+        let mut buf = vec![0; MAX_DATAGRAM + 1];
+        assert_eq!(b.recv(&mut buf).unwrap(), MAX_DATAGRAM);
+        assert!(buf[..MAX_DATAGRAM] == data[..]);
+
+        // One more byte doesn't fit in an IPv4 packet.
+        assert!(a.send(&datagram(MAX_DATAGRAM + 1)).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn truncated_datagram() {
+        let (a, b) = udp_pair();
+        let data = datagram(MAX_DATAGRAM);
+        a.send(&data).unwrap();
+        a.send(b"next").unwrap();
+
+        let mut buf = [0; 1000];
+        let n = {
+            let mut bufs = [IoSliceMut::new(&mut buf)];
+            let mut msg = MsgHdrMut::new().with_buffers(&mut bufs);
+            let n = b.recv_msg(&mut msg).unwrap();
+            assert!(msg.recv_flags().is_truncated());
+            n
+        };
+        assert_eq!(n, buf.len());
+        assert!(buf[..] == data[..buf.len()]);
+
+        // The rest of the truncated datagram is discarded.
+        assert_eq!(b.recv(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], b"next");
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn truncated_datagram_len() {
+        let (a, b) = udp_pair();
+        a.send(&datagram(MAX_DATAGRAM)).unwrap();
+        // With `MSG_TRUNC` Linux returns the datagram's real length.
+        let mut buf = [0; 1000];
+        assert_eq!(b.recv_with_flags(&mut buf, c::MSG_TRUNC).unwrap(),
+                   MAX_DATAGRAM);
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly",
+              target_os = "openbsd", target_os = "netbsd"))]
+    fn bind_dhcp_client() {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let loopback = b"lo";
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let loopback = b"lo0";
+
+        let a = Socket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+        match a.bind_dhcp_client(loopback) {
+            Ok(()) => {}
+            // Port 68 and binding to a device need privileges.
+            Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                return
+            }
+            Err(e) => panic!("{}", e),
+        }
+        assert!(a.reuse_address().unwrap());
+        assert!(a.broadcast().unwrap());
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        assert_eq!(a.device().unwrap(), Some(loopback.to_vec()));
+        assert_eq!(a.local_addr().unwrap().as_std(),
+                   Some("0.0.0.0:68".parse().unwrap()));
+
+        // The options had to be set before binding for a second client to
+        // be able to bind the port too.
+        let b = Socket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+        b.bind_dhcp_client(loopback).unwrap();
+
+        // Setting them afterwards is too late.
+        let c = Socket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+        let addr = "0.0.0.0:68".parse::<SocketAddr>().unwrap();
+        assert_eq!(c.bind(&addr.into()).unwrap_err().kind(),
+                   io::ErrorKind::AddrInUse);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn send_broadcast_on() {
+        let any = "0.0.0.0:0".parse::<SocketAddr>().unwrap().into();
+        let receiver = Socket::new(Domain::ipv4(), Type::dgram(), None)
+            .unwrap();
+        receiver.bind(&any).unwrap();
+        let port = receiver.local_addr().unwrap().as_inet().unwrap().port();
+
+        // Linux routes 127.255.255.255 as the broadcast address of the
+        // loopback network.
+        let sender = Socket::new(Domain::ipv4(), Type::dgram(), None)
+            .unwrap();
+        let n = sender.send_broadcast_on(b"hello", port,
+                                         Ipv4Addr::new(127, 0, 0, 1),
+                                         Ipv4Addr::new(255, 0, 0, 0))
+            .unwrap();
+        assert_eq!(n, 5);
+        assert!(sender.broadcast().unwrap());
+        let mut buf = [0; 16];
+        let (n, from) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"hello");
+        assert!(from.as_inet().unwrap().ip().is_loopback());
+    }
+
+    #[test]
+    fn send_broadcast_on_invalid_netmask() {
+        let socket = Socket::new(Domain::ipv4(), Type::dgram(), None)
+            .unwrap();
+        let addr = Ipv4Addr::new(192, 0, 2, 10);
+        let netmasks = [
+            Ipv4Addr::new(255, 255, 255, 255),
+            Ipv4Addr::new(255, 255, 255, 254),
+            Ipv4Addr::new(255, 0, 255, 0),
+        ];
+        for &netmask in netmasks.iter() {
+            let err = socket.send_broadcast_on(b"x", 9, addr, netmask)
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+
+        let stream = Socket::new(Domain::ipv4(), Type::stream(), None)
+            .unwrap();
+        let err = stream.send_broadcast_on(b"x", 9, addr,
+                                           Ipv4Addr::new(255, 255, 255, 0))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn recv_multiple_with() {
+        let (a, b) = udp_pair();
+        let mut provider = vec![vec![0; 8]; 4];
+        let mut scratch = RecvScratch::new();
+        let mut msgs = Vec::new();
+        for round in 0..2u8 {
+            for i in 0..3 {
+                a.send(&[round, i]).unwrap();
+            }
+            let n = b.recv_multiple_with(&mut provider, 4, &mut scratch,
+                                         &mut msgs)
+                .unwrap();
+            // Datagrams may arrive one by one without `recvmmsg`.
+            assert!(n >= 1 && n == msgs.len());
+            assert_eq!(provider.len(), 4 - n);
+            for (i, (buf, len, addr)) in msgs.drain(..).enumerate() {
+                assert_eq!(&buf[..len], &[round, i as u8]);
+                assert_eq!(addr.as_std(), a.local_addr().unwrap().as_std());
+                provider.give_back(buf);
+            }
+            // Drain whatever wasn't received in the batch.
+            for _ in n..3 {
+                b.recv(&mut [0; 8]).unwrap();
+            }
+        }
+        assert_eq!(provider.len(), 4);
+
+        // Nothing is received when the provider has no buffers.
+        let n = b.recv_multiple_with(&mut Vec::<Vec<u8>>::new(), 4,
+                                     &mut scratch, &mut msgs)
+            .unwrap();
+        assert_eq!(n, 0);
+        assert!(msgs.is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn recv_multiple() {
+        let (a, b) = udp_pair();
+        a.send(b"one").unwrap();
+        a.send(b"two").unwrap();
+        let (mut x, mut y, mut z) = ([0; 8], [0; 8], [0; 8]);
+        let msgs = b.recv_multiple(&mut [&mut x, &mut y, &mut z]).unwrap();
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[0].0, 3);
+        assert_eq!(&x[..3], b"one");
+        assert_eq!(&y[..3], b"two");
+    }
+
+    #[test]
+    fn peek_exact() {
+        let (a, b) = tcp_pair();
+        let sender = thread::spawn(move || {
+            a.send(b"GET ").unwrap();
+            thread::sleep(Duration::from_millis(50));
+            a.send(b"/ HTTP/1.1").unwrap();
+            a
+        });
+        let mut buf = [0; 8];
+        b.peek_exact(&mut buf, Duration::from_secs(10)).unwrap();
+        assert_eq!(&buf, b"GET / HT");
+        let a = sender.join().unwrap();
+
+        // The peeked data is still queued.
+        let mut buf = [0; 4];
+        assert_eq!(b.recv(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"GET ");
+
+        #[cfg(unix)] {
+            use std::mem;
+            use std::net::Shutdown;
+            use std::os::unix::io::AsRawFd;
+
+            // The low-water mark is restored afterwards.
+            let lowat = unsafe {
+                let mut lowat: c::c_int = 0;
+                let mut len = mem::size_of_val(&lowat) as c::socklen_t;
+                assert_eq!(c::getsockopt(b.as_raw_fd(), c::SOL_SOCKET,
+                                         c::SO_RCVLOWAT,
+                                         &mut lowat as *mut _ as *mut _,
+                                         &mut len), 0);
+                lowat
+            };
+            assert_eq!(lowat, 1);
+
+            a.shutdown(Shutdown::Write).unwrap();
+            let mut buf = [0; 32];
+            let err = b.peek_exact(&mut buf, Duration::from_secs(10))
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        }
+        drop(a);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn tcp_inq() {
+        use cmsg_space;
+
+        let (a, b) = tcp_pair();
+        b.set_tcp_inq(true).unwrap();
+        assert!(b.tcp_inq().unwrap());
+        a.send(b"0123456789").unwrap();
+        // Wait for all of the data to be queued before reading part of it.
+        let mut peek = [0; 10];
+        while b.peek(&mut peek).unwrap() < peek.len() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let mut buf = [0; 4];
+        let mut control = vec![0; cmsg_space(4)];
+        let mut bufs = [IoSliceMut::new(&mut buf)];
+        let mut msg = MsgHdrMut::new()
+            .with_buffers(&mut bufs)
+            .with_control(&mut control);
+        assert_eq!(b.recv_msg(&mut msg).unwrap(), 4);
+        assert_eq!(msg.inq(), Some(6));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn pass_sec() {
+        use cmsg_space;
+
+        let (a, b) = Socket::pair(Domain::unix(), Type::dgram(), None)
+            .unwrap();
+        assert!(!b.pass_sec().unwrap());
+        b.set_pass_sec(true).unwrap();
+        assert!(b.pass_sec().unwrap());
+        a.send(b"hello").unwrap();
+
+        let mut buf = [0; 8];
+        let mut control = vec![0; cmsg_space(256)];
+        let mut bufs = [IoSliceMut::new(&mut buf)];
+        let mut msg = MsgHdrMut::new()
+            .with_buffers(&mut bufs)
+            .with_control(&mut control);
+        assert_eq!(b.recv_msg(&mut msg).unwrap(), 5);
+        // The label is only sent when a security module is active.
+        if let Some(label) = msg.security_label() {
+            assert!(!label.is_empty());
+            assert!(!label.contains(&0));
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "quic", target_os = "linux"))]
+    fn extended_error() {
+        use ExtendedError;
+
+        let any = "127.0.0.1:0".parse::<SocketAddr>().unwrap().into();
+        let closed = Socket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+        closed.bind(&any).unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let socket = Socket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+        assert!(socket.configure_for_quic().unwrap().recverr());
+        socket.connect(&closed_addr).unwrap();
+        socket.send(b"hello").unwrap();
+
+        let mut buf = [0; 8];
+        // Leave room for the packet information and TOS which are enabled as
+        // well.
+        let mut control = vec![0; ExtendedError::control_len() + 64];
+        let mut bufs = [IoSliceMut::new(&mut buf)];
+        let mut msg = MsgHdrMut::new()
+            .with_buffers(&mut bufs)
+            .with_control(&mut control)
+            .with_flags(c::MSG_ERRQUEUE | c::MSG_DONTWAIT);
+        // The ICMP error is queued asynchronously.
+        let mut tries = 0;
+        loop {
+            match socket.recv_msg(&mut msg) {
+                Ok(_) => break,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock &&
+                              tries < 100 => {
+                    tries += 1;
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => panic!("{}", e),
+            }
+        }
+        let err = msg.extended_error().unwrap();
+        assert_eq!(err.errno(), c::ECONNREFUSED);
+        assert_eq!(err.origin(), c::SO_EE_ORIGIN_ICMP);
+        assert_eq!((err.icmp_type(), err.icmp_code()), (3, 3));
+        assert_eq!(err.mtu(), None);
+        let offender = err.offender().unwrap().as_inet().unwrap();
+        assert_eq!(*offender.ip(), Ipv4Addr::new(127, 0, 0, 1));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn recv_datagram() {
+        let (a, b) = udp_pair();
+        #[cfg(feature = "quic")]
+        {
+            let features = b.configure_for_quic().unwrap();
+            assert!(features.pktinfo() && features.ecn());
+        }
+        b.enable_rx_timestamps(false).unwrap();
+        // ECT(0), the ECN codepoint of ECN capable transports.
+        a.set_tos(0b10).unwrap();
+
+        // The kernel turns on receive timestamps asynchronously, so the first
+        // datagrams might not be stamped yet.
+        let mut buf = [0; 8];
+        let mut tries = 0;
+        let msg = loop {
+            a.send(b"hello").unwrap();
+            let msg = b.recv_datagram(&mut buf).unwrap();
+            if msg.timestamps().software().is_some() || tries == 100 {
+                break msg
+            }
+            tries += 1;
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(msg.len(), 5);
+        assert_eq!(&buf[..5], b"hello");
+        assert_eq!(msg.addr().as_inet(), a.local_addr().unwrap().as_inet());
+        assert!(!msg.flags().is_truncated());
+        assert!(msg.timestamps().software().is_some());
+        if cfg!(feature = "quic") {
+            let localhost = Ipv4Addr::new(127, 0, 0, 1);
+            assert_eq!(msg.dst_addr(), Some(localhost.into()));
+            assert_eq!(msg.ecn(), Some(0b10));
+        } else {
+            assert_eq!(msg.dst_addr(), None);
+            assert_eq!(msg.tos(), None);
+        }
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn send_vectored_end_of_record() {
+        let (a, b) = Socket::pair(Domain::unix(), Type::seqpacket(), None)
+            .unwrap();
+        let bufs = [IoSlice::new(b"ab"), IoSlice::new(b"cd")];
+        assert_eq!(a.send_vectored_with_flags(&bufs, c::MSG_EOR).unwrap(), 4);
+        a.send(b"ef").unwrap();
+
+        // Both buffers make up a single record.
+        let mut buf = [0; 8];
+        assert_eq!(b.recv(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], b"abcd");
+        assert_eq!(b.recv(&mut buf).unwrap(), 2);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn send_multiple_flags() {
+        let (a, b) = tcp_pair();
+        let (x, y, z) = ([IoSlice::new(b"ab")], [IoSlice::new(b"!")],
+                         [IoSlice::new(b"cd")]);
+        let msgs = [MsgHdr::new().with_buffers(&x),
+                    MsgHdr::new().with_buffers(&y).with_flags(c::MSG_OOB),
+                    MsgHdr::new().with_buffers(&z)];
+        assert_eq!(a.send_multiple(&msgs).unwrap(), vec![2, 1, 2]);
+
+        // Only the message sent with `MSG_OOB` is out-of-band, and reads stop
+        // at its mark.
+        let mut buf = [0; 8];
+        assert_eq!(b.recv(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"ab");
+        let mut tries = 0;
+        loop {
+            match b.recv_out_of_band(&mut buf) {
+                Ok(n) => {
+                    assert_eq!(&buf[..n], b"!");
+                    break
+                }
+                // The urgent data might not have arrived yet.
+                Err(_) if tries < 100 => {
+                    tries += 1;
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => panic!("{}", e),
+            }
+        }
+        assert_eq!(b.recv(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"cd");
+    }
+
+    // Returns a path for a Unix socket which doesn't exist yet.
+    #[cfg(unix)]
+    fn unix_socket_path(name: &str) -> ::std::path::PathBuf {
+        use std::process;
+
+        let path = ::std::env::temp_dir()
+            .join(format!("socket2-{}-{}", process::id(), name));
+        let _ = ::std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn bind_reclaiming_stale() {
+        use std::fs;
+        use SockAddr;
+
+        let path = unix_socket_path("stale");
+        let addr = SockAddr::unix(&path).unwrap();
+        let listener = Socket::new(Domain::unix(), Type::stream(), None)
+            .unwrap();
+        listener.bind_reclaiming_stale(&addr).unwrap();
+        listener.listen(1).unwrap();
+
+        // The path is in use as long as the listener is alive.
+        let socket = Socket::new(Domain::unix(), Type::stream(), None)
+            .unwrap();
+        let err = socket.bind_reclaiming_stale(&addr).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+
+        // Its socket file is left behind when it's closed, and reclaimed.
+        drop(listener);
+        assert!(path.exists());
+        let err = socket.bind(&addr).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        socket.bind_reclaiming_stale(&addr).unwrap();
+        socket.listen(1).unwrap();
+        Socket::new(Domain::unix(), Type::stream(), None).unwrap()
+            .connect(&addr).unwrap();
+        drop(socket);
+
+        // Files which aren't sockets are never removed.
+        fs::remove_file(&path).unwrap();
+        fs::write(&path, b"data").unwrap();
+        let socket = Socket::new(Domain::unix(), Type::stream(), None)
+            .unwrap();
+        let err = socket.bind_reclaiming_stale(&addr).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        assert_eq!(fs::read(&path).unwrap(), b"data");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn unlink_on_drop() {
+        use std::fs;
+        use std::os::unix::io::IntoRawFd;
+        use SockAddr;
+
+        let path = unix_socket_path("unlink");
+        let addr = SockAddr::unix(&path).unwrap();
+        let socket = Socket::new(Domain::unix(), Type::stream(), None)
+            .unwrap();
+        let err = socket.set_unlink_on_drop(true).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        socket.bind(&addr).unwrap();
+        assert!(!socket.unlink_on_drop());
+        socket.set_unlink_on_drop(true).unwrap();
+        assert!(socket.unlink_on_drop());
+        drop(socket);
+        assert!(!path.exists());
+
+        // A file which replaced the socket's isn't removed.
+        let socket = Socket::new(Domain::unix(), Type::stream(), None)
+            .unwrap();
+        socket.bind(&addr).unwrap();
+        socket.set_unlink_on_drop(true).unwrap();
+        fs::remove_file(&path).unwrap();
+        fs::write(&path, b"data").unwrap();
+        drop(socket);
+        assert!(path.exists());
+        fs::remove_file(&path).unwrap();
+
+        // Nor is the file of a socket converted into a raw descriptor.
+        let socket = Socket::new(Domain::unix(), Type::stream(), None)
+            .unwrap();
+        socket.bind(&addr).unwrap();
+        socket.set_unlink_on_drop(true).unwrap();
+        unsafe {
+            c::close(socket.into_raw_fd());
+        }
+        assert!(path.exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    // Returns the number of system calls `f` makes, by running it in a child
+    // process traced with ptrace. The calls are delimited by calls to
+    // `getppid`, which `f` must not make itself.
+    #[cfg(target_os = "linux")]
+    fn count_syscalls<F: FnOnce()>(f: F) -> usize {
+        use std::mem;
+        use std::panic::{self, AssertUnwindSafe};
+        use std::ptr;
+
+        // From linux/ptrace.h, which libc only binds for glibc.
+        const PTRACE_GET_SYSCALL_INFO: c::c_uint = 0x420e;
+        const PTRACE_SYSCALL_INFO_ENTRY: u8 = 1;
+        #[repr(C)]
+        struct SyscallInfo {
+            op: u8,
+            pad: [u8; 3],
+            arch: u32,
+            instruction_pointer: u64,
+            stack_pointer: u64,
+            nr: u64,
+            args: [u64; 6],
+        }
+
+        let null = ptr::null_mut::<c::c_void>();
+        unsafe {
+            let pid = c::fork();
+            assert!(pid >= 0, "fork failed");
+            if pid == 0 {
+                c::ptrace(c::PTRACE_TRACEME, 0, null, null);
+                c::raise(c::SIGSTOP);
+                c::getppid();
+                if panic::catch_unwind(AssertUnwindSafe(f)).is_err() {
+                    c::_exit(1);
+                }
+                c::getppid();
+                c::_exit(0);
+            }
+
+            let mut status = 0;
+            assert_eq!(c::waitpid(pid, &mut status, 0), pid);
+            assert!(c::WIFSTOPPED(status));
+            let options = c::PTRACE_O_TRACESYSGOOD | c::PTRACE_O_EXITKILL;
+            c::ptrace(c::PTRACE_SETOPTIONS, pid, null, options as usize);
+            let (mut markers, mut count, mut signal) = (0, 0, 0);
+            loop {
+                c::ptrace(c::PTRACE_SYSCALL, pid, null, signal as usize);
+                signal = 0;
+                assert_eq!(c::waitpid(pid, &mut status, 0), pid);
+                if !c::WIFSTOPPED(status) {
+                    break
+                }
+                if c::WSTOPSIG(status) != c::SIGTRAP | 0x80 {
+                    // Deliver signals other than syscall stops.
+                    signal = c::WSTOPSIG(status);
+                    continue
+                }
+                let mut info: SyscallInfo = mem::zeroed();
+                c::ptrace(PTRACE_GET_SYSCALL_INFO, pid,
+                          mem::size_of::<SyscallInfo>(),
+                          &mut info as *mut SyscallInfo);
+                if info.op != PTRACE_SYSCALL_INFO_ENTRY {
+                    continue
+                }
+                if info.nr == c::SYS_getppid as u64 {
+                    markers += 1;
+                } else if markers == 1 {
+                    count += 1;
+                }
+            }
+            assert!(c::WIFEXITED(status) && c::WEXITSTATUS(status) == 0,
+                    "traced process failed");
+            assert_eq!(markers, 2);
+            count
+        }
+    }
+
+    // Checks that the hot paths make exactly one system call. This needs to
+    // be allowed to ptrace child processes, which many sandboxes forbid.
+    #[test]
+    #[ignore]
+    #[cfg(target_os = "linux")]
+    fn hot_path_syscalls() {
+        let (a, b) = udp_pair();
+        let addr = b.local_addr().unwrap();
+        let mut buf = [0; 16];
+
+        assert_eq!(count_syscalls(|| {}), 0);
+        assert_eq!(count_syscalls(|| { a.send(b"send").unwrap(); }), 1);
+        assert_eq!(count_syscalls(|| { b.recv(&mut buf).unwrap(); }), 1);
+        assert_eq!(count_syscalls(|| { a.send_to(b"to", &addr).unwrap(); }), 1);
+        assert_eq!(count_syscalls(|| { b.recv_from(&mut buf).unwrap(); }), 1);
+        assert_eq!(count_syscalls(|| {
+            a.send_vectored(&[IoSlice::new(b"vec")]).unwrap();
+        }), 1);
+        assert_eq!(count_syscalls(|| {
+            b.recv_vectored(&mut [IoSliceMut::new(&mut buf)]).unwrap();
+        }), 1);
+        assert_eq!(count_syscalls(|| {
+            let bufs = [IoSlice::new(b"msg")];
+            a.send_msg(&MsgHdr::new().with_buffers(&bufs)).unwrap();
+        }), 1);
+        assert_eq!(count_syscalls(|| {
+            let mut bufs = [IoSliceMut::new(&mut buf)];
+            b.recv_msg(&mut MsgHdrMut::new().with_buffers(&mut bufs)).unwrap();
+        }), 1);
     }
 }
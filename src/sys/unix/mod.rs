@@ -10,18 +10,27 @@
 
 use std::cmp;
 use std::fmt;
-use std::io::{Read, Write, ErrorKind};
+use std::fs;
+use std::io::{Read, Write, ErrorKind, IoSlice, IoSliceMut};
 use std::io;
 use std::mem;
+use std::mem::MaybeUninit;
 use std::net::Shutdown;
-use std::net::{self, Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6, SocketAddr};
+use std::net::{self, Ipv4Addr, Ipv6Addr};
 use std::ops::Neg;
+use std::ptr;
 use std::os::unix::prelude::*;
+use std::path::PathBuf;
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+#[cfg(all(target_os = "linux", not(feature = "no-ioctl")))]
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use libc::{self, c_void, c_int, sockaddr_in, sockaddr_storage, sockaddr_in6};
-use libc::{sockaddr, socklen_t, AF_INET, AF_INET6, ssize_t};
+use libc::{self, c_void, c_int, sockaddr_storage};
+use libc::{socklen_t, ssize_t};
+
+pub use libc::{IPPROTO_ICMP, IPPROTO_ICMPV6};
 
 cfg_if! {
     if #[cfg(any(target_os = "dragonfly", target_os = "freebsd",
@@ -57,7 +66,111 @@ cfg_if! {
     }
 }
 
-use utils::One;
+cfg_if! {
+    if #[cfg(any(target_os = "linux", target_os = "android"))] {
+        use libc::IPV6_RECVPATHMTU;
+    } else if #[cfg(any(target_os = "dragonfly", target_os = "freebsd",
+                        target_os = "ios", target_os = "macos",
+                        target_os = "openbsd", target_os = "netbsd"))] {
+        const IPV6_USE_MIN_MTU: c_int = 42;
+        const IPV6_RECVPATHMTU: c_int = 43;
+    }
+}
+
+cfg_if! {
+    if #[cfg(any(target_os = "linux", target_os = "android",
+                 target_os = "dragonfly", target_os = "freebsd",
+                 target_os = "openbsd", target_os = "netbsd"))] {
+        use libc::SOCK_NONBLOCK;
+        const SOCK_FLAGS: c_int = libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK;
+    } else {
+        const SOCK_NONBLOCK: c_int = 0;
+        const SOCK_FLAGS: c_int = 0;
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+const TCP_CA_NAME_MAX: usize = 16;
+
+#[cfg(all(target_os = "linux", not(feature = "no-ioctl")))]
+const SIOCGSTAMPNS: libc::c_ulong = 0x8907;
+
+cfg_if! {
+    if #[cfg(any(target_os = "linux", target_os = "android"))] {
+        use libc::TCP_NOTSENT_LOWAT;
+    } else if #[cfg(any(target_os = "macos", target_os = "ios"))] {
+        const TCP_NOTSENT_LOWAT: c_int = 0x201;
+    }
+}
+
+cfg_if! {
+    if #[cfg(target_os = "linux")] {
+        use libc::SO_MAX_PACING_RATE;
+    } else if #[cfg(target_os = "android")] {
+        // From asm-generic/socket.h, which libc doesn't bind for Android.
+        const SO_MAX_PACING_RATE: c_int = 47;
+    }
+}
+
+cfg_if! {
+    if #[cfg(target_os = "android")] {
+        use libc::SO_PASSSEC;
+    } else if #[cfg(all(target_os = "linux",
+                        any(target_arch = "sparc", target_arch = "sparc64")))] {
+        // From asm/socket.h, which libc doesn't bind for Linux.
+        const SO_PASSSEC: c_int = 0x1f;
+    } else if #[cfg(target_os = "linux")] {
+        // From asm-generic/socket.h, which libc doesn't bind for Linux.
+        const SO_PASSSEC: c_int = 34;
+    }
+}
+
+// From linux/rxrpc.h, which isn't bound by libc.
+#[cfg(target_os = "linux")]
+const RXRPC_SECURITY_KEY: c_int = 1;
+#[cfg(target_os = "linux")]
+const RXRPC_SECURITY_KEYRING: c_int = 2;
+#[cfg(target_os = "linux")]
+const RXRPC_MIN_SECURITY_LEVEL: c_int = 4;
+
+// `tcp_md5sig` from linux/tcp.h, which isn't bound by libc.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[repr(C)]
+struct tcp_md5sig {
+    tcpm_addr: sockaddr_storage,
+    tcpm_flags: u8,
+    tcpm_prefixlen: u8,
+    tcpm_keylen: u16,
+    tcpm_ifindex: c_int,
+    tcpm_key: [u8; TCP_MD5SIG_MAXKEYLEN],
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const TCP_MD5SIG_MAXKEYLEN: usize = 80;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const TCP_MD5SIG_FLAG_PREFIX: u8 = 1;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const TCP_IS_MPTCP: c_int = 43;
+
+cfg_if! {
+    if #[cfg(feature = "no-ioctl")] {
+    } else if #[cfg(any(target_os = "linux", target_os = "android"))] {
+        const SIOCATMARK: libc::c_ulong = 0x8905;
+    } else {
+        const SIOCATMARK: libc::c_ulong = 0x40047307;
+    }
+}
+
+use utils::{as_uninit_mut, MetadataCache, One};
+use {Metadata, SockAddr, TcpKeepalive};
+#[cfg(all(feature = "quic",
+          any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd")))]
+use QuicFeatures;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+use {TcpInfo, TcpState};
+use msg::{MsgHdr, MsgHdrMut, RecvScratch};
 
 #[macro_use]
 #[cfg(target_os = "linux")]
@@ -65,17 +178,45 @@ mod weak;
 
 pub struct Socket {
     fd: c_int,
+    meta: MetadataCache,
+    unlink_on_drop: Mutex<Option<SocketFile>>,
+}
+
+// The file a Unix domain socket is bound to, identified by its device and
+// inode numbers so that a file created by another socket bound to the same
+// path later isn't mistaken for it.
+struct SocketFile {
+    path: PathBuf,
+    dev: u64,
+    ino: u64,
+}
+
+impl SocketFile {
+    fn unlink(&self) {
+        let same = match fs::symlink_metadata(&self.path) {
+            Ok(metadata) => {
+                metadata.dev() == self.dev && metadata.ino() == self.ino
+            }
+            Err(_) => false,
+        };
+        if same {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
 }
 
 impl Socket {
     pub fn new(family: c_int, ty: c_int, protocol: c_int) -> io::Result<Socket> {
         unsafe {
-            // On linux we first attempt to pass the SOCK_CLOEXEC flag to
-            // atomically create the socket and set it as CLOEXEC. Support for
-            // this option, however, was added in 2.6.27, and we still support
+            // Where supported we first attempt to pass the SOCK_CLOEXEC flag
+            // to atomically create the socket and set it as CLOEXEC, along
+            // with any flags requested through `Type`. Support for these
+            // flags, however, was added in Linux 2.6.27, and we still support
             // 2.6.18 as a kernel, so if the returned error is EINVAL we
             // fallthrough to the fallback.
-            #[cfg(target_os = "linux")] {
+            #[cfg(any(target_os = "linux", target_os = "android",
+                      target_os = "dragonfly", target_os = "freebsd",
+                      target_os = "openbsd", target_os = "netbsd"))] {
                 match cvt(libc::socket(family, ty | libc::SOCK_CLOEXEC, protocol)) {
                     Ok(fd) => return Ok(Socket::from_raw_fd(fd)),
                     Err(ref e) if e.raw_os_error() == Some(libc::EINVAL) => {}
@@ -83,25 +224,68 @@ impl Socket {
                 }
             }
 
-            let fd = cvt(libc::socket(family, ty, protocol))?;
+            let fd = cvt(libc::socket(family, ty & !SOCK_FLAGS, protocol))?;
             let fd = Socket::from_raw_fd(fd);
-            set_cloexec(fd.as_raw_fd())?;
-            #[cfg(target_os = "macos")] {
-                fd.setsockopt(libc::SOL_SOCKET, libc::SO_NOSIGPIPE, 1i32)?;
-            }
+            fd.set_type_flags(ty)?;
             Ok(fd)
         }
     }
 
-    pub fn bind(&self, addr: &SocketAddr) -> io::Result<()> {
+    pub fn pair(family: c_int, ty: c_int, protocol: c_int)
+                -> io::Result<(Socket, Socket)> {
+        unsafe {
+            let mut fds = [0, 0];
+            // See `new` for why failing with EINVAL falls through.
+            #[cfg(any(target_os = "linux", target_os = "android",
+                      target_os = "dragonfly", target_os = "freebsd",
+                      target_os = "openbsd", target_os = "netbsd"))] {
+                match cvt(libc::socketpair(family,
+                                           ty | libc::SOCK_CLOEXEC,
+                                           protocol,
+                                           fds.as_mut_ptr())) {
+                    Ok(_) => {
+                        return Ok((Socket::from_raw_fd(fds[0]),
+                                   Socket::from_raw_fd(fds[1])))
+                    }
+                    Err(ref e) if e.raw_os_error() == Some(libc::EINVAL) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+
+            cvt(libc::socketpair(family, ty & !SOCK_FLAGS, protocol,
+                                 fds.as_mut_ptr()))?;
+            let a = Socket::from_raw_fd(fds[0]);
+            let b = Socket::from_raw_fd(fds[1]);
+            a.set_type_flags(ty)?;
+            b.set_type_flags(ty)?;
+            Ok((a, b))
+        }
+    }
+
+    /// Applies the creation flags in `ty` to a socket created without them,
+    /// along with the options every new socket gets.
+    // `SOCK_NONBLOCK` is zero on the platforms which don't support it.
+    #[allow(clippy::bad_bit_mask)]
+    fn set_type_flags(&self, ty: c_int) -> io::Result<()> {
+        set_cloexec(self.fd)?;
+        if ty & SOCK_NONBLOCK != 0 {
+            self.set_nonblocking(true)?;
+        }
+        #[cfg(target_os = "macos")] unsafe {
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_NOSIGPIPE, 1i32)?;
+        }
+        Ok(())
+    }
+
+    pub fn bind(&self, addr: &SockAddr) -> io::Result<()> {
         #[cfg(not(all(target_arch = "aarch64",target_os = "android")))]
         use libc::socklen_t as len_t;
         #[cfg(all(target_arch = "aarch64",target_os = "android"))]
         use libc::c_int as len_t;
 
-        let (addr, len) = addr2raw(addr);
         unsafe {
-            cvt(libc::bind(self.fd, addr, len as len_t)).map(|_| ())
+            cvt(libc::bind(self.fd, addr.as_ptr(), addr.len() as len_t))
+                .map(|_| ())
         }
     }
 
@@ -111,32 +295,86 @@ impl Socket {
         }
     }
 
-    pub fn connect(&self, addr: &SocketAddr) -> io::Result<()> {
-        let (addr, len) = addr2raw(addr);
+    pub fn connect(&self, addr: &SockAddr) -> io::Result<()> {
         unsafe {
-            cvt(libc::connect(self.fd, addr, len)).map(|_| ())
+            cvt(libc::connect(self.fd, addr.as_ptr(), addr.len())).map(|_| ())
+        }
+    }
+
+    pub fn connect_timeout(&self, addr: &SockAddr, timeout: Duration)
+                           -> io::Result<()> {
+        let deadline = match Instant::now().checked_add(timeout) {
+            Some(deadline) => deadline,
+            None => return self.connect(addr),
+        };
+        let flags = unsafe { cvt(libc::fcntl(self.fd, libc::F_GETFL))? };
+        let was_nonblocking = flags & libc::O_NONBLOCK != 0;
+        if !was_nonblocking {
+            self.set_nonblocking(true)?;
+        }
+        let res = self.connect_deadline(addr, deadline);
+        if !was_nonblocking {
+            self.set_nonblocking(false)?;
+        }
+        res
+    }
+
+    fn connect_deadline(&self, addr: &SockAddr, deadline: Instant)
+                        -> io::Result<()> {
+        match self.connect(addr) {
+            Ok(()) => return Ok(()),
+            Err(ref e) if e.raw_os_error() == Some(libc::EINPROGRESS) => {}
+            Err(e) => return Err(e),
         }
+        self.wait(libc::POLLOUT, deadline)?;
+        match self.take_error()? {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    pub fn unlink_on_drop(&self) -> bool {
+        self.unlink_on_drop.lock().unwrap().is_some()
+    }
+
+    pub fn set_unlink_on_drop(&self, unlink: bool) -> io::Result<()> {
+        let file = if unlink {
+            let addr = self.local_addr()?;
+            let path = match addr.as_pathname() {
+                Some(path) => path.to_path_buf(),
+                None => {
+                    return Err(io::Error::new(ErrorKind::InvalidInput,
+                                              "socket isn't bound to a path"))
+                }
+            };
+            let metadata = fs::symlink_metadata(&path)?;
+            Some(SocketFile { path, dev: metadata.dev(), ino: metadata.ino() })
+        } else {
+            None
+        };
+        *self.unlink_on_drop.lock().unwrap() = file;
+        Ok(())
     }
 
-    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+    pub fn local_addr(&self) -> io::Result<SockAddr> {
         unsafe {
-            let mut storage: libc::sockaddr_storage = mem::zeroed();
-            let mut len = mem::size_of_val(&storage) as libc::socklen_t;
+            let mut storage = addr_storage();
+            let mut len = mem::size_of::<sockaddr_storage>() as socklen_t;
             cvt(libc::getsockname(self.fd,
-                                  &mut storage as *mut _ as *mut _,
+                                  storage.as_mut_ptr() as *mut _,
                                   &mut len))?;
-            raw2addr(&storage, len)
+            Ok(SockAddr::from_raw_parts(storage.as_ptr() as *const _, len))
         }
     }
 
-    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+    pub fn peer_addr(&self) -> io::Result<SockAddr> {
         unsafe {
-            let mut storage: libc::sockaddr_storage = mem::zeroed();
-            let mut len = mem::size_of_val(&storage) as libc::socklen_t;
+            let mut storage = addr_storage();
+            let mut len = mem::size_of::<sockaddr_storage>() as socklen_t;
             cvt(libc::getpeername(self.fd,
-                                  &mut storage as *mut _ as *mut _,
+                                  storage.as_mut_ptr() as *mut _,
                                   &mut len))?;
-            raw2addr(&storage, len)
+            Ok(SockAddr::from_raw_parts(storage.as_ptr() as *const _, len))
         }
     }
 
@@ -172,24 +410,25 @@ impl Socket {
     }
 
     #[allow(unused_mut)]
-    pub fn accept(&self) -> io::Result<(Socket, SocketAddr)> {
-        let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
-        let mut len = mem::size_of_val(&storage) as socklen_t;
+    pub fn accept(&self) -> io::Result<(Socket, SockAddr)> {
+        let mut storage = addr_storage();
+        let mut len = mem::size_of::<sockaddr_storage>() as socklen_t;
 
         let mut socket = None;
         #[cfg(target_os = "linux")] {
             weak! {
-                fn accept4(c_int, *mut sockaddr, *mut socklen_t, c_int) -> c_int
+                fn accept4(c_int, *mut libc::sockaddr, *mut socklen_t,
+                           c_int) -> c_int
             }
             if let Some(f) = accept4.get() {
                 let res = cvt_r(|| unsafe {
                     f(self.fd,
-                      &mut storage as *mut _ as *mut _,
+                      storage.as_mut_ptr() as *mut _,
                       &mut len,
                       libc::SOCK_CLOEXEC)
                 });
                 match res {
-                    Ok(fd) => socket = Some(Socket { fd: fd }),
+                    Ok(fd) => socket = Some(unsafe { Socket::from_raw_fd(fd) }),
                     Err(ref e) if e.raw_os_error() == Some(libc::ENOSYS) => {}
                     Err(e) => return Err(e),
                 }
@@ -201,7 +440,7 @@ impl Socket {
             None => unsafe {
                 let fd = cvt_r(|| {
                     libc::accept(self.fd,
-                                 &mut storage as *mut _ as *mut _,
+                                 storage.as_mut_ptr() as *mut _,
                                  &mut len)
                 })?;
                 let fd = Socket::from_raw_fd(fd);
@@ -209,10 +448,44 @@ impl Socket {
                 fd
             }
         };
-        let addr = raw2addr(&storage, len)?;
+        let addr = unsafe {
+            SockAddr::from_raw_parts(storage.as_ptr() as *const _, len)
+        };
         Ok((socket, addr))
     }
 
+    #[cfg(any(target_os = "linux", target_os = "freebsd",
+              target_os = "dragonfly", target_os = "netbsd",
+              target_os = "openbsd"))]
+    pub fn accept_with_flags(&self, flags: c_int)
+                             -> io::Result<(Socket, SockAddr)> {
+        let mut storage = addr_storage();
+        let mut len = mem::size_of::<sockaddr_storage>() as socklen_t;
+        let res = cvt_r(|| unsafe {
+            libc::accept4(self.fd,
+                          storage.as_mut_ptr() as *mut _,
+                          &mut len,
+                          flags | libc::SOCK_CLOEXEC)
+        });
+        match res {
+            Ok(fd) => {
+                let addr = unsafe {
+                    SockAddr::from_raw_parts(storage.as_ptr() as *const _, len)
+                };
+                Ok((unsafe { Socket::from_raw_fd(fd) }, addr))
+            }
+            // Kernels older than 2.6.28 don't support `accept4`.
+            Err(ref e) if e.raw_os_error() == Some(libc::ENOSYS) => {
+                let (socket, addr) = self.accept()?;
+                if flags & libc::SOCK_NONBLOCK != 0 {
+                    socket.set_nonblocking(true)?;
+                }
+                Ok((socket, addr))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
         unsafe {
             let raw: c_int = self.getsockopt(libc::SOL_SOCKET, libc::SO_ERROR)?;
@@ -239,6 +512,47 @@ impl Socket {
         }
     }
 
+    #[cfg(not(feature = "no-ioctl"))]
+    pub unsafe fn ioctl(&self, request: u64, arg: *mut c_void)
+                        -> io::Result<c_int> {
+        cvt(libc::ioctl(self.fd, request as _, arg))
+    }
+
+    pub unsafe fn fcntl(&self, cmd: c_int, arg: c_int) -> io::Result<c_int> {
+        cvt(libc::fcntl(self.fd, cmd, arg))
+    }
+
+    #[cfg(not(feature = "no-ioctl"))]
+    pub fn at_mark(&self) -> io::Result<bool> {
+        unsafe {
+            let mut raw: c_int = 0;
+            cvt(libc::ioctl(self.fd, SIOCATMARK as _, &mut raw))?;
+            Ok(raw != 0)
+        }
+    }
+
+    pub fn cloexec(&self) -> io::Result<bool> {
+        unsafe {
+            let flags = cvt(libc::fcntl(self.fd, libc::F_GETFD))?;
+            Ok(flags & libc::FD_CLOEXEC != 0)
+        }
+    }
+
+    pub fn set_cloexec(&self, close_on_exec: bool) -> io::Result<()> {
+        unsafe {
+            let previous = cvt(libc::fcntl(self.fd, libc::F_GETFD))?;
+            let new = if close_on_exec {
+                previous | libc::FD_CLOEXEC
+            } else {
+                previous & !libc::FD_CLOEXEC
+            };
+            if new != previous {
+                cvt(libc::fcntl(self.fd, libc::F_SETFD, new))?;
+            }
+            Ok(())
+        }
+    }
+
     pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
         let how = match how {
             Shutdown::Write => libc::SHUT_WR,
@@ -250,82 +564,564 @@ impl Socket {
     }
 
     pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv_with_flags(as_uninit_mut(buf), 0)
+    }
+
+    pub fn recv_out_of_band(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv_with_flags(as_uninit_mut(buf), libc::MSG_OOB)
+    }
+
+    pub fn recv_with_flags(&self, buf: &mut [MaybeUninit<u8>], flags: c_int)
+                           -> io::Result<usize> {
         unsafe {
             let n = cvt({
                 libc::recv(self.fd,
                            buf.as_mut_ptr() as *mut c_void,
                            cmp::min(buf.len(), max_len()),
-                           0)
+                           flags)
             })?;
             Ok(n as usize)
         }
     }
 
-    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+    pub fn recv_vectored(&self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
         unsafe {
-            let n = cvt({
-                libc::recv(self.fd,
-                           buf.as_mut_ptr() as *mut c_void,
-                           cmp::min(buf.len(), max_len()),
-                           libc::MSG_PEEK)
-            })?;
+            let mut msg: libc::msghdr = mem::zeroed();
+            // `IoSliceMut` is guaranteed to be ABI compatible with `iovec`.
+            msg.msg_iov = bufs.as_mut_ptr() as *mut libc::iovec;
+            msg.msg_iovlen = cmp::min(bufs.len(), c_int::MAX as usize) as _;
+            let n = cvt(libc::recvmsg(self.fd, &mut msg, 0))?;
             Ok(n as usize)
         }
     }
 
-    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
-        self.recvfrom(buf, 0)
+    pub fn recv_msg(&self, msg: &mut MsgHdrMut) -> io::Result<usize> {
+        let mut storage = addr_storage();
+        let mut raw = msg.raw(&mut storage);
+        let n = unsafe { cvt(libc::recvmsg(self.fd, &mut raw, msg.flags()))? };
+        msg.finish(&raw, &storage);
+        Ok(n as usize)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn recv_multiple(&self, bufs: &mut [&mut [u8]])
+                         -> io::Result<Vec<(usize, SockAddr)>> {
+        // `MSG_WAITFORONE` only blocks until the first message arrives,
+        // rather than until all the buffers are filled.
+        self.recvmmsg(bufs, libc::MSG_WAITFORONE, None)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn recv_multiple_timeout(&self,
+                                 bufs: &mut [&mut [u8]],
+                                 timeout: Option<Duration>)
+                                 -> io::Result<Vec<(usize, SockAddr)>> {
+        // A timeout too large for an `Instant` is as good as none.
+        let deadline = timeout.and_then(|t| Instant::now().checked_add(t));
+        loop {
+            // When interrupted by a signal `recvmmsg` hasn't received
+            // anything, so it's restarted with the time which is left.
+            let mut timeout = deadline.map(|deadline| {
+                let left = deadline.saturating_duration_since(Instant::now());
+                libc::timespec {
+                    tv_sec: cmp::min(left.as_secs(),
+                                     libc::time_t::MAX as u64) as libc::time_t,
+                    tv_nsec: left.subsec_nanos() as _,
+                }
+            });
+            match self.recvmmsg(bufs, 0, timeout.as_mut()) {
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                res => return res,
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn recvmmsg(&self,
+                bufs: &mut [&mut [u8]],
+                flags: c_int,
+                timeout: Option<&mut libc::timespec>)
+                -> io::Result<Vec<(usize, SockAddr)>> {
+        let mut msgs = Vec::with_capacity(bufs.len());
+        for buf in bufs {
+            msgs.push((&mut **buf, 0, SockAddr::from_bytes(&[])?));
+        }
+        let mut scratch = RecvScratch::new();
+        let n = self.recvmmsg_into(&mut msgs, &mut scratch, flags, timeout)?;
+        Ok(msgs.into_iter().take(n).map(|(_, n, addr)| (n, addr)).collect())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn recv_multiple_into<B>(&self,
+                                 msgs: &mut [(B, usize, SockAddr)],
+                                 scratch: &mut RecvScratch)
+                                 -> io::Result<usize>
+        where B: AsMut<[u8]>
+    {
+        self.recvmmsg_into(msgs, scratch, libc::MSG_WAITFORONE, None)
+    }
+
+    // Receives datagrams into the buffers of `msgs` with `recvmmsg`, filling
+    // in their lengths and source addresses, and returns how many were
+    // received.
+    #[cfg(target_os = "linux")]
+    fn recvmmsg_into<B>(&self,
+                        msgs: &mut [(B, usize, SockAddr)],
+                        scratch: &mut RecvScratch,
+                        flags: c_int,
+                        timeout: Option<&mut libc::timespec>)
+                        -> io::Result<usize>
+        where B: AsMut<[u8]>
+    {
+        let len = cmp::min(msgs.len(), libc::c_uint::MAX as usize);
+        scratch.iovs.extend(msgs[..len].iter_mut().map(|msg| {
+            let buf = msg.0.as_mut();
+            libc::iovec {
+                iov_base: buf.as_mut_ptr() as *mut c_void,
+                iov_len: buf.len(),
+            }
+        }));
+        scratch.addrs.extend((0..len).map(|_| addr_storage()));
+        let mmsgs = scratch.iovs.iter_mut().zip(&mut scratch.addrs);
+        scratch.msgs.extend(mmsgs.map(|(iov, storage)| {
+            let mut msg: libc::mmsghdr = unsafe { mem::zeroed() };
+            msg.msg_hdr.msg_name = storage.as_mut_ptr() as *mut c_void;
+            msg.msg_hdr.msg_namelen =
+                mem::size_of::<sockaddr_storage>() as socklen_t;
+            msg.msg_hdr.msg_iov = iov;
+            msg.msg_hdr.msg_iovlen = 1;
+            msg
+        }));
+        let timeout = timeout.map_or(ptr::null_mut(), |t| t as *mut _);
+        let res = unsafe {
+            cvt(libc::recvmmsg(self.fd,
+                               scratch.msgs.as_mut_ptr(),
+                               len as libc::c_uint,
+                               flags as _,
+                               timeout))
+        };
+        if let Ok(n) = res {
+            let received = scratch.msgs.iter().zip(&scratch.addrs);
+            for (msg, (raw, storage)) in msgs.iter_mut().zip(received)
+                                             .take(n as usize) {
+                msg.1 = raw.msg_len as usize;
+                msg.2 = unsafe {
+                    SockAddr::from_raw_parts(storage.as_ptr() as *const _,
+                                             raw.msg_hdr.msg_namelen)
+                };
+            }
+        }
+        // Don't keep pointers to the buffers past this call.
+        scratch.msgs.clear();
+        scratch.iovs.clear();
+        scratch.addrs.clear();
+        res.map(|n| n as usize)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn recv_multiple_into<B>(&self,
+                                 msgs: &mut [(B, usize, SockAddr)],
+                                 _scratch: &mut RecvScratch)
+                                 -> io::Result<usize>
+        where B: AsMut<[u8]>
+    {
+        let mut n = 0;
+        for msg in msgs {
+            // Only wait for the first message, like `MSG_WAITFORONE`.
+            let flags = if n == 0 { 0 } else { libc::MSG_DONTWAIT };
+            let buf = as_uninit_mut(msg.0.as_mut());
+            match self.recv_from_with_flags(buf, flags) {
+                Ok((len, addr)) => {
+                    msg.1 = len;
+                    msg.2 = addr;
+                    n += 1;
+                }
+                Err(e) => {
+                    if n == 0 {
+                        return Err(e)
+                    }
+                    break
+                }
+            }
+        }
+        Ok(n)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn recv_multiple(&self, bufs: &mut [&mut [u8]])
+                         -> io::Result<Vec<(usize, SockAddr)>> {
+        let mut msgs = Vec::new();
+        for buf in bufs {
+            // Only wait for the first message, like `MSG_WAITFORONE`.
+            let flags = if msgs.is_empty() { 0 } else { libc::MSG_DONTWAIT };
+            match self.recv_from_with_flags(as_uninit_mut(buf), flags) {
+                Ok(msg) => msgs.push(msg),
+                Err(e) => {
+                    if msgs.is_empty() {
+                        return Err(e)
+                    }
+                    break
+                }
+            }
+        }
+        Ok(msgs)
+    }
+
+    pub fn peek(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+        self.recv_with_flags(buf, libc::MSG_PEEK)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn peek_len(&self) -> io::Result<usize> {
+        // With `MSG_TRUNC` the real length of the datagram is returned even
+        // though none of it fits in the buffer.
+        self.recv_with_flags(&mut [], libc::MSG_PEEK | libc::MSG_TRUNC)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android",
+                  feature = "no-ioctl")))]
+    pub fn peek_len(&self) -> io::Result<usize> {
+        unsafe {
+            let mut len: c_int = 0;
+            cvt(libc::ioctl(self.fd, libc::FIONREAD, &mut len))?;
+            Ok(len as usize)
+        }
+    }
+
+    pub fn recv_deadline(&self, buf: &mut [u8], deadline: Instant)
+                         -> io::Result<usize> {
+        // Another thread may consume the data between `poll` and `recv`, so
+        // the receive itself must not block and we go around again instead.
+        loop {
+            self.wait(libc::POLLIN, deadline)?;
+            let n = unsafe {
+                cvt(libc::recv(self.fd,
+                               buf.as_mut_ptr() as *mut c_void,
+                               cmp::min(buf.len(), max_len()),
+                               libc::MSG_DONTWAIT))
+            };
+            match n {
+                Ok(n) => return Ok(n as usize),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn peek_deadline(&self, buf: &mut [u8], deadline: Instant)
+                         -> io::Result<usize> {
+        // See `recv_deadline` for why the peek itself must not block.
+        loop {
+            self.wait(libc::POLLIN, deadline)?;
+            let flags = libc::MSG_PEEK | libc::MSG_DONTWAIT;
+            match self.recv_with_flags(as_uninit_mut(buf), flags) {
+                Ok(n) => return Ok(n),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn peek_exact(&self, buf: &mut [u8], deadline: Instant)
+                      -> io::Result<()> {
+        if buf.is_empty() {
+            return Ok(())
+        }
+        // Peeked data stays queued, so with the low-water mark at the full
+        // length `poll` only reports the socket readable once all of `buf`
+        // can be peeked, or once the connection is closed.
+        let lowat = cmp::min(buf.len(), c_int::MAX as usize);
+        let old: c_int = unsafe {
+            self.getsockopt(libc::SOL_SOCKET, libc::SO_RCVLOWAT)?
+        };
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_RCVLOWAT,
+                            lowat as c_int)?;
+        }
+        let res = self.peek_deadline(buf, deadline).and_then(|n| {
+            if n < buf.len() {
+                Err(io::Error::new(ErrorKind::UnexpectedEof,
+                                   "connection closed before enough data \
+                                    could be peeked"))
+            } else {
+                Ok(())
+            }
+        });
+        let restored = unsafe {
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_RCVLOWAT, old)
+        };
+        res.and(restored)
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SockAddr)> {
+        self.recv_from_with_flags(as_uninit_mut(buf), 0)
     }
 
-    pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
-        self.recvfrom(buf, libc::MSG_PEEK)
+    pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SockAddr)> {
+        self.recv_from_with_flags(as_uninit_mut(buf), libc::MSG_PEEK)
     }
 
-    fn recvfrom(&self, buf: &mut [u8], flags: c_int)
-                -> io::Result<(usize, SocketAddr)> {
+    pub fn recv_from_with_flags(&self,
+                                buf: &mut [MaybeUninit<u8>],
+                                flags: c_int)
+                                -> io::Result<(usize, SockAddr)> {
         unsafe {
-            let mut storage: libc::sockaddr_storage = mem::zeroed();
-            let mut addrlen = mem::size_of_val(&storage) as socklen_t;
+            let mut storage = addr_storage();
+            let mut addrlen = mem::size_of::<sockaddr_storage>() as socklen_t;
 
             let n = cvt({
                 libc::recvfrom(self.fd,
                                buf.as_mut_ptr() as *mut c_void,
                                cmp::min(buf.len(), max_len()),
                                flags,
-                               &mut storage as *mut _ as *mut _,
+                               storage.as_mut_ptr() as *mut _,
                                &mut addrlen)
             })?;
-            Ok((n as usize, raw2addr(&storage, addrlen)?))
+            let addr = SockAddr::from_raw_parts(storage.as_ptr() as *const _,
+                                                addrlen);
+            Ok((n as usize, addr))
         }
     }
 
     pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.send_with_flags(buf, 0)
+    }
+
+    pub fn send_out_of_band(&self, buf: &[u8]) -> io::Result<usize> {
+        self.send_with_flags(buf, libc::MSG_OOB)
+    }
+
+    pub fn send_with_flags(&self, buf: &[u8], flags: c_int)
+                           -> io::Result<usize> {
         unsafe {
             let n = cvt({
                 libc::send(self.fd,
                            buf.as_ptr() as *const c_void,
                            cmp::min(buf.len(), max_len()),
-                           MSG_NOSIGNAL)
+                           flags | MSG_NOSIGNAL)
             })?;
             Ok(n as usize)
         }
     }
 
-    pub fn send_to(&self, buf: &[u8], addr: &SocketAddr) -> io::Result<usize> {
+    pub fn send_vectored(&self, bufs: &[IoSlice]) -> io::Result<usize> {
+        self.send_vectored_with_flags(bufs, 0)
+    }
+
+    pub fn send_vectored_with_flags(&self, bufs: &[IoSlice], flags: c_int)
+                                    -> io::Result<usize> {
+        unsafe {
+            let mut msg: libc::msghdr = mem::zeroed();
+            // `IoSlice` is guaranteed to be ABI compatible with `iovec`.
+            msg.msg_iov = bufs.as_ptr() as *mut libc::iovec;
+            msg.msg_iovlen = cmp::min(bufs.len(), c_int::MAX as usize) as _;
+            let n = cvt(libc::sendmsg(self.fd, &msg, flags | MSG_NOSIGNAL))?;
+            Ok(n as usize)
+        }
+    }
+
+    pub fn send_msg(&self, msg: &MsgHdr) -> io::Result<usize> {
+        let raw = msg.raw();
+        unsafe {
+            let n = cvt(libc::sendmsg(self.fd, &raw, msg.flags() | MSG_NOSIGNAL))?;
+            Ok(n as usize)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn send_multiple(&self, msgs: &[MsgHdr]) -> io::Result<Vec<usize>> {
+        let len = cmp::min(msgs.len(), libc::c_uint::MAX as usize);
+        let mut raw = msgs[..len].iter().map(|msg| {
+            libc::mmsghdr { msg_hdr: msg.raw(), msg_len: 0 }
+        }).collect::<Vec<_>>();
+        // `sendmmsg` takes a single set of flags for all messages, so the
+        // messages are sent in runs of the same flags.
+        let mut sent = 0;
+        while sent < len {
+            let flags = msgs[sent].flags();
+            let run = msgs[sent..len].iter()
+                .take_while(|msg| msg.flags() == flags)
+                .count();
+            let res = cvt(unsafe {
+                libc::sendmmsg(self.fd,
+                               raw[sent..].as_mut_ptr(),
+                               run as libc::c_uint,
+                               (flags | MSG_NOSIGNAL) as _)
+            });
+            match res {
+                Ok(n) => {
+                    sent += n as usize;
+                    if (n as usize) < run {
+                        break
+                    }
+                }
+                Err(e) => {
+                    if sent == 0 {
+                        return Err(e)
+                    }
+                    break
+                }
+            }
+        }
+        Ok(raw[..sent].iter().map(|msg| msg.msg_len as usize).collect())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn send_multiple(&self, msgs: &[MsgHdr]) -> io::Result<Vec<usize>> {
+        let mut sent = Vec::new();
+        for msg in msgs {
+            let raw = msg.raw();
+            let flags = msg.flags() | MSG_NOSIGNAL;
+            match cvt(unsafe { libc::sendmsg(self.fd, &raw, flags) }) {
+                Ok(n) => sent.push(n as usize),
+                Err(e) => {
+                    if sent.is_empty() {
+                        return Err(e)
+                    }
+                    break
+                }
+            }
+        }
+        Ok(sent)
+    }
+
+    pub fn send_deadline(&self, buf: &[u8], deadline: Instant)
+                         -> io::Result<usize> {
+        loop {
+            self.wait(libc::POLLOUT, deadline)?;
+            let n = unsafe {
+                cvt(libc::send(self.fd,
+                               buf.as_ptr() as *const c_void,
+                               cmp::min(buf.len(), max_len()),
+                               MSG_NOSIGNAL | libc::MSG_DONTWAIT))
+            };
+            match n {
+                Ok(n) => return Ok(n as usize),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn send_to(&self, buf: &[u8], addr: &SockAddr) -> io::Result<usize> {
+        self.send_to_with_flags(buf, addr, 0)
+    }
+
+    pub fn send_to_with_flags(&self,
+                              buf: &[u8],
+                              addr: &SockAddr,
+                              flags: c_int) -> io::Result<usize> {
         unsafe {
-            let (addr, len) = addr2raw(addr);
             let n = cvt({
                 libc::sendto(self.fd,
                              buf.as_ptr() as *const c_void,
                              cmp::min(buf.len(), max_len()),
-                             MSG_NOSIGNAL,
-                             addr,
-                             len)
+                             flags | MSG_NOSIGNAL,
+                             addr.as_ptr(),
+                             addr.len())
             })?;
             Ok(n as usize)
         }
     }
 
+    pub fn send_socket(&self, socket: &Socket) -> io::Result<()> {
+        // The socket's type is sent along with it so the receiver can check
+        // it against the type of the descriptor it actually got.
+        let payload = socket.metadata()?.ty.to_ne_bytes();
+        let n = self.send_with_fds(&payload, &[socket.fd])?;
+        if n != payload.len() {
+            return Err(io::Error::new(ErrorKind::WriteZero,
+                                      "failed to send the socket's type"))
+        }
+        Ok(())
+    }
+
+    pub fn recv_socket(&self) -> io::Result<Socket> {
+        let mut payload = [0; 4];
+        let mut fds = [-1];
+        let (n, count) = self.recv_with_fds(&mut payload, &mut fds)?;
+        if count == 0 {
+            if n == 0 {
+                return Err(io::Error::new(ErrorKind::UnexpectedEof,
+                                          "connection closed before a socket \
+                                           was received"))
+            }
+            return Err(io::Error::new(ErrorKind::InvalidData,
+                                      "message did not carry a socket"))
+        }
+        let socket = unsafe { Socket::from_raw_fd(fds[0]) };
+        if n != payload.len() {
+            return Err(io::Error::new(ErrorKind::InvalidData,
+                                      "invalid socket type in message"))
+        }
+        // This also fails with `ENOTSOCK` if the peer sent some other kind of
+        // file descriptor.
+        let ty: c_int = unsafe {
+            socket.getsockopt(libc::SOL_SOCKET, libc::SO_TYPE)?
+        };
+        if ty != c_int::from_ne_bytes(payload) {
+            return Err(io::Error::new(ErrorKind::InvalidData,
+                                      "received socket has a different type \
+                                       than the one sent"))
+        }
+        Ok(socket)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn peer_cred(&self)
+                     -> io::Result<(libc::uid_t, libc::gid_t, Option<libc::pid_t>)> {
+        unsafe {
+            let cred: libc::ucred = self.getsockopt(libc::SOL_SOCKET,
+                                                    libc::SO_PEERCRED)?;
+            Ok((cred.uid, cred.gid, Some(cred.pid)))
+        }
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly"))]
+    #[allow(clippy::io_other_error)]
+    pub fn peer_cred(&self)
+                     -> io::Result<(libc::uid_t, libc::gid_t, Option<libc::pid_t>)> {
+        unsafe {
+            let cred: libc::xucred = self.getsockopt(0, libc::LOCAL_PEERCRED)?;
+            if cred.cr_ngroups < 1 {
+                return Err(io::Error::new(ErrorKind::Other,
+                                          "peer credentials have no group"))
+            }
+            #[cfg(any(target_os = "macos", target_os = "ios"))]
+            let pid = Some(self.getsockopt(0, libc::LOCAL_PEERPID)?);
+            #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+            let pid = None;
+            Ok((cred.cr_uid, cred.cr_groups[0], pid))
+        }
+    }
+
+    #[cfg(any(target_os = "openbsd", target_os = "netbsd"))]
+    pub fn peer_cred(&self)
+                     -> io::Result<(libc::uid_t, libc::gid_t, Option<libc::pid_t>)> {
+        unsafe {
+            let mut uid = 0;
+            let mut gid = 0;
+            cvt(libc::getpeereid(self.fd, &mut uid, &mut gid))?;
+            Ok((uid, gid, None))
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn pass_sec(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::SOL_SOCKET, SO_PASSSEC)?;
+            Ok(raw != 0)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_pass_sec(&self, pass: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET, SO_PASSSEC, pass as c_int)
+        }
+    }
+
     // ================================================
 
     pub fn ttl(&self) -> io::Result<u32> {
@@ -337,283 +1133,1545 @@ impl Socket {
 
     pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
         unsafe {
-            self.setsockopt(libc::IPPROTO_IP, libc::IP_TTL, ttl as c_int)
+            self.setsockopt(libc::IPPROTO_IP, libc::IP_TTL, ttl as c_int)
+        }
+    }
+
+    pub fn unicast_hops_v6(&self) -> io::Result<u32> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IPV6,
+                                             libc::IPV6_UNICAST_HOPS)?;
+            Ok(raw as u32)
+        }
+    }
+
+    pub fn set_unicast_hops_v6(&self, hops: u32) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IPV6,
+                            libc::IPV6_UNICAST_HOPS,
+                            hops as c_int)
+        }
+    }
+
+    pub fn tos(&self) -> io::Result<u32> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IP, libc::IP_TOS)?;
+            Ok(raw as u32)
+        }
+    }
+
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IP, libc::IP_TOS, tos as c_int)
+        }
+    }
+
+    pub fn tclass_v6(&self) -> io::Result<u32> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IPV6,
+                                             libc::IPV6_TCLASS)?;
+            Ok(raw as u32)
+        }
+    }
+
+    pub fn set_tclass_v6(&self, tclass: u32) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IPV6,
+                            libc::IPV6_TCLASS,
+                            tclass as c_int)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn recv_tos(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IP,
+                                             libc::IP_RECVTOS)?;
+            Ok(raw != 0)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn set_recv_tos(&self, recv: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IP, libc::IP_RECVTOS, recv as c_int)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn recv_tclass_v6(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IPV6,
+                                             libc::IPV6_RECVTCLASS)?;
+            Ok(raw != 0)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn set_recv_tclass_v6(&self, recv: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IPV6,
+                            libc::IPV6_RECVTCLASS,
+                            recv as c_int)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn recv_ttl(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IP,
+                                             libc::IP_RECVTTL)?;
+            Ok(raw != 0)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn set_recv_ttl(&self, recv: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IP, libc::IP_RECVTTL, recv as c_int)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn recv_hoplimit_v6(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IPV6,
+                                             libc::IPV6_RECVHOPLIMIT)?;
+            Ok(raw != 0)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd"))]
+    pub fn set_recv_hoplimit_v6(&self, recv: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IPV6,
+                            libc::IPV6_RECVHOPLIMIT,
+                            recv as c_int)
+        }
+    }
+
+    pub fn only_v6(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IPV6,
+                                             libc::IPV6_V6ONLY)?;
+            Ok(raw != 0)
+        }
+    }
+
+    pub fn set_only_v6(&self, only_v6: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IPV6,
+                            libc::IPV6_V6ONLY,
+                            only_v6 as c_int)
+        }
+    }
+
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        unsafe {
+            Ok(timeval2dur(self.getsockopt(libc::SOL_SOCKET, libc::SO_RCVTIMEO)?))
+        }
+    }
+
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET,
+                            libc::SO_RCVTIMEO,
+                            dur2timeval(dur)?)
+        }
+    }
+
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        unsafe {
+            Ok(timeval2dur(self.getsockopt(libc::SOL_SOCKET, libc::SO_SNDTIMEO)?))
+        }
+    }
+
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET,
+                            libc::SO_SNDTIMEO,
+                            dur2timeval(dur)?)
+        }
+    }
+
+    pub fn nodelay(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_TCP,
+                                             libc::TCP_NODELAY)?;
+            Ok(raw != 0)
+        }
+    }
+
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_TCP,
+                            libc::TCP_NODELAY,
+                            nodelay as c_int)
+        }
+    }
+
+    pub fn mss(&self) -> io::Result<u32> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_TCP,
+                                             libc::TCP_MAXSEG)?;
+            Ok(raw as u32)
+        }
+    }
+
+    pub fn set_mss(&self, mss: u32) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_TCP, libc::TCP_MAXSEG, mss as c_int)
+        }
+    }
+
+    pub fn broadcast(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::SOL_SOCKET,
+                                             libc::SO_BROADCAST)?;
+            Ok(raw != 0)
+        }
+    }
+
+    pub fn set_broadcast(&self, broadcast: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET,
+                            libc::SO_BROADCAST,
+                            broadcast as c_int)
+        }
+    }
+
+    pub fn out_of_band_inline(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::SOL_SOCKET, libc::SO_OOBINLINE)?;
+            Ok(raw != 0)
+        }
+    }
+
+    pub fn set_out_of_band_inline(&self, oob_inline: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET,
+                            libc::SO_OOBINLINE,
+                            oob_inline as c_int)
+        }
+    }
+
+    pub fn multicast_loop_v4(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IP,
+                                             libc::IP_MULTICAST_LOOP)?;
+            Ok(raw != 0)
+        }
+    }
+
+    pub fn set_multicast_loop_v4(&self, multicast_loop_v4: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IP,
+                            libc::IP_MULTICAST_LOOP,
+                            multicast_loop_v4 as c_int)
+        }
+    }
+
+    pub fn multicast_ttl_v4(&self) -> io::Result<u32> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IP,
+                                             libc::IP_MULTICAST_TTL)?;
+            Ok(raw as u32)
+        }
+    }
+
+    pub fn set_multicast_ttl_v4(&self, multicast_ttl_v4: u32) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IP,
+                            libc::IP_MULTICAST_TTL,
+                            multicast_ttl_v4 as c_int)
+        }
+    }
+
+    pub fn multicast_loop_v6(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IPV6,
+                                             libc::IPV6_MULTICAST_LOOP)?;
+            Ok(raw != 0)
+        }
+    }
+
+    pub fn set_multicast_loop_v6(&self, multicast_loop_v6: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IPV6,
+                            libc::IPV6_MULTICAST_LOOP,
+                            multicast_loop_v6 as c_int)
+        }
+    }
+
+    pub fn join_multicast_v4(&self,
+                             multiaddr: &Ipv4Addr,
+                             interface: &Ipv4Addr) -> io::Result<()> {
+        let multiaddr = to_s_addr(multiaddr);
+        let interface = to_s_addr(interface);
+        let mreq = libc::ip_mreq {
+            imr_multiaddr: libc::in_addr { s_addr: multiaddr },
+            imr_interface: libc::in_addr { s_addr: interface },
+        };
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IP, libc::IP_ADD_MEMBERSHIP, mreq)
+        }
+    }
+
+    pub fn join_multicast_v6(&self,
+                             multiaddr: &Ipv6Addr,
+                             interface: u32) -> io::Result<()> {
+        let multiaddr = to_in6_addr(multiaddr);
+        let mreq = libc::ipv6_mreq {
+            ipv6mr_multiaddr: multiaddr,
+            ipv6mr_interface: to_ipv6mr_interface(interface),
+        };
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IP, IPV6_ADD_MEMBERSHIP, mreq)
+        }
+    }
+
+    pub fn leave_multicast_v4(&self,
+                              multiaddr: &Ipv4Addr,
+                              interface: &Ipv4Addr) -> io::Result<()> {
+        let multiaddr = to_s_addr(multiaddr);
+        let interface = to_s_addr(interface);
+        let mreq = libc::ip_mreq {
+            imr_multiaddr: libc::in_addr { s_addr: multiaddr },
+            imr_interface: libc::in_addr { s_addr: interface },
+        };
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IP, libc::IP_DROP_MEMBERSHIP, mreq)
+        }
+    }
+
+    pub fn leave_multicast_v6(&self,
+                              multiaddr: &Ipv6Addr,
+                              interface: u32) -> io::Result<()> {
+        let multiaddr = to_in6_addr(multiaddr);
+        let mreq = libc::ipv6_mreq {
+            ipv6mr_multiaddr: multiaddr,
+            ipv6mr_interface: to_ipv6mr_interface(interface),
+        };
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IP, IPV6_DROP_MEMBERSHIP, mreq)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn join_anycast_v6(&self,
+                           anyaddr: &Ipv6Addr,
+                           interface: u32) -> io::Result<()> {
+        let mreq = libc::ipv6_mreq {
+            ipv6mr_multiaddr: to_in6_addr(anyaddr),
+            ipv6mr_interface: to_ipv6mr_interface(interface),
+        };
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_JOIN_ANYCAST, mreq)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn leave_anycast_v6(&self,
+                            anyaddr: &Ipv6Addr,
+                            interface: u32) -> io::Result<()> {
+        let mreq = libc::ipv6_mreq {
+            ipv6mr_multiaddr: to_in6_addr(anyaddr),
+            ipv6mr_interface: to_ipv6mr_interface(interface),
+        };
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_LEAVE_ANYCAST, mreq)
+        }
+    }
+
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        unsafe {
+            Ok(linger2dur(self.getsockopt(libc::SOL_SOCKET, libc::SO_LINGER)?))
+        }
+    }
+
+    pub fn set_linger(&self, dur: Option<Duration>) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_LINGER, dur2linger(dur))
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn tcp_linger2(&self) -> io::Result<Option<Duration>> {
+        unsafe {
+            let secs: c_int = self.getsockopt(libc::IPPROTO_TCP,
+                                              libc::TCP_LINGER2)?;
+            if secs < 0 {
+                Ok(None)
+            } else {
+                Ok(Some(Duration::from_secs(secs as u64)))
+            }
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_linger2(&self, dur: Option<Duration>) -> io::Result<()> {
+        let secs = match dur {
+            Some(dur) => {
+                let mut secs = dur.as_secs();
+                if dur.subsec_nanos() > 0 {
+                    secs = secs.saturating_add(1);
+                }
+                cmp::min(secs, c_int::MAX as u64) as c_int
+            }
+            None => -1,
+        };
+        unsafe {
+            self.setsockopt(libc::IPPROTO_TCP, libc::TCP_LINGER2, secs)
+        }
+    }
+
+    pub fn set_reuse_address(&self, reuse: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_REUSEADDR, reuse as c_int)
+        }
+    }
+
+    pub fn reuse_address(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::SOL_SOCKET,
+                                             libc::SO_REUSEADDR)?;
+            Ok(raw != 0)
+        }
+    }
+
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::SOL_SOCKET, libc::SO_RCVBUF)?;
+            Ok(raw as usize)
+        }
+    }
+
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        unsafe {
+            // TODO: casting usize to a c_int should be a checked cast
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_RCVBUF, size as c_int)
+        }
+    }
+
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::SOL_SOCKET, libc::SO_SNDBUF)?;
+            Ok(raw as usize)
+        }
+    }
+
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        unsafe {
+            // TODO: casting usize to a c_int should be a checked cast
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_SNDBUF, size as c_int)
+        }
+    }
+
+    pub fn send_low_watermark(&self) -> io::Result<usize> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::SOL_SOCKET,
+                                             libc::SO_SNDLOWAT)?;
+            Ok(raw as usize)
+        }
+    }
+
+    pub fn set_send_low_watermark(&self, lowat: usize) -> io::Result<()> {
+        let lowat = cmp::min(lowat, c_int::MAX as usize) as c_int;
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_SNDLOWAT, lowat)
+        }
+    }
+
+    #[cfg(all(any(target_os = "linux", target_os = "android"),
+              not(feature = "no-ioctl")))]
+    pub fn send_queue_len(&self) -> io::Result<usize> {
+        unsafe {
+            let mut len: c_int = 0;
+            // `SIOCOUTQ` is an alias of `TIOCOUTQ`, which isn't bound by libc
+            // under its socket name.
+            cvt(libc::ioctl(self.fd, libc::TIOCOUTQ as _, &mut len))?;
+            Ok(len as usize)
+        }
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn send_queue_len(&self) -> io::Result<usize> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::SOL_SOCKET,
+                                             libc::SO_NWRITE)?;
+            Ok(raw as usize)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn force_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_RCVBUFFORCE, size as c_int)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn force_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_SNDBUFFORCE, size as c_int)
+        }
+    }
+
+    pub fn keepalive(&self) -> io::Result<Option<Duration>> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::SOL_SOCKET,
+                                             libc::SO_KEEPALIVE)?;
+            if raw == 0 {
+                return Ok(None)
+            }
+            let secs: c_int = self.getsockopt(libc::IPPROTO_TCP,
+                                              KEEPALIVE_OPTION)?;
+            Ok(Some(Duration::new(secs as u64, 0)))
+        }
+    }
+
+    pub fn set_keepalive(&self, keepalive: Option<Duration>) -> io::Result<()> {
+        match keepalive {
+            Some(dur) => self.set_tcp_keepalive(&TcpKeepalive::new().with_time(dur)),
+            None => unsafe {
+                self.setsockopt(libc::SOL_SOCKET, libc::SO_KEEPALIVE, 0 as c_int)
+            },
+        }
+    }
+
+    pub fn set_tcp_keepalive(&self, params: &TcpKeepalive) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_KEEPALIVE, 1 as c_int)?;
+            if let Some(time) = params.time {
+                self.setsockopt(libc::IPPROTO_TCP, KEEPALIVE_OPTION,
+                                keepalive_secs(time))?;
+            }
+            #[cfg(any(target_os = "linux", target_os = "android",
+                      target_os = "macos", target_os = "ios",
+                      target_os = "freebsd", target_os = "dragonfly",
+                      target_os = "netbsd"))] {
+                if let Some(interval) = params.interval {
+                    self.setsockopt(libc::IPPROTO_TCP, libc::TCP_KEEPINTVL,
+                                    keepalive_secs(interval))?;
+                }
+                if let Some(retries) = params.retries {
+                    let retries = cmp::min(retries, c_int::MAX as u32);
+                    self.setsockopt(libc::IPPROTO_TCP, libc::TCP_KEEPCNT,
+                                    retries as c_int)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn reuse_port(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::SOL_SOCKET,
+                                             libc::SO_REUSEPORT)?;
+            Ok(raw != 0)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn set_reuse_port(&self, reuse: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_REUSEPORT, reuse as c_int)
+        }
+    }
+
+    #[cfg(target_os = "freebsd")]
+    pub fn reuse_port_lb(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::SOL_SOCKET,
+                                             libc::SO_REUSEPORT_LB)?;
+            Ok(raw != 0)
+        }
+    }
+
+    #[cfg(target_os = "freebsd")]
+    pub fn set_reuse_port_lb(&self, reuse: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_REUSEPORT_LB,
+                            reuse as c_int)
+        }
+    }
+
+    #[cfg(target_os = "freebsd")]
+    pub fn timestamp_clock(&self) -> io::Result<c_int> {
+        unsafe { self.getsockopt(libc::SOL_SOCKET, libc::SO_TS_CLOCK) }
+    }
+
+    #[cfg(target_os = "freebsd")]
+    pub fn set_timestamp_clock(&self, clock: c_int) -> io::Result<()> {
+        unsafe { self.setsockopt(libc::SOL_SOCKET, libc::SO_TS_CLOCK, clock) }
+    }
+
+    #[cfg(target_os = "freebsd")]
+    pub fn accept_filter(&self) -> io::Result<Option<String>> {
+        let arg: libc::accept_filter_arg = unsafe {
+            match self.getsockopt(libc::SOL_SOCKET, libc::SO_ACCEPTFILTER) {
+                Ok(arg) => arg,
+                // There's no filter installed.
+                Err(ref e) if e.raw_os_error() == Some(libc::EINVAL) => {
+                    return Ok(None)
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        let name = arg.af_name.iter()
+                              .take_while(|&&c| c != 0)
+                              .map(|&c| c as u8)
+                              .collect::<Vec<_>>();
+        Ok(Some(String::from_utf8_lossy(&name).into_owned()))
+    }
+
+    #[cfg(target_os = "freebsd")]
+    pub fn set_accept_filter(&self, filter: Option<&str>) -> io::Result<()> {
+        let filter = match filter {
+            Some(filter) => filter,
+            None => unsafe {
+                cvt(libc::setsockopt(self.fd,
+                                     libc::SOL_SOCKET,
+                                     libc::SO_ACCEPTFILTER,
+                                     ptr::null(),
+                                     0))?;
+                return Ok(())
+            },
+        };
+        unsafe {
+            let mut arg: libc::accept_filter_arg = mem::zeroed();
+            // The name has to be nul terminated.
+            if filter.len() >= arg.af_name.len() {
+                return Err(io::Error::new(ErrorKind::InvalidInput,
+                                          "accept filter name is too long"))
+            }
+            for (dst, &src) in arg.af_name.iter_mut().zip(filter.as_bytes()) {
+                *dst = src as libc::c_char;
+            }
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_ACCEPTFILTER, arg)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd",
+              target_os = "ios", target_os = "macos",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn recv_path_mtu_v6(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IPV6,
+                                             IPV6_RECVPATHMTU)?;
+            Ok(raw != 0)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd",
+              target_os = "ios", target_os = "macos",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn set_recv_path_mtu_v6(&self, recv: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IPV6, IPV6_RECVPATHMTU, recv as c_int)
+        }
+    }
+
+    #[cfg(all(feature = "quic",
+              any(target_os = "linux", target_os = "android",
+                  target_os = "macos", target_os = "ios",
+                  target_os = "freebsd")))]
+    pub fn configure_for_quic(&self, v6: bool) -> QuicFeatures {
+        let enable = |level, opt| unsafe {
+            self.setsockopt(level, opt, 1 as c_int).is_ok()
+        };
+        // Dual-stack IPv6 sockets also receive IPv4 datagrams, whose
+        // ancillary data is controlled by the IPv4 options on some systems,
+        // so those are tried as well but don't affect the result.
+        let either = |v4_opt, v6_opt| {
+            if v6 {
+                let _ = enable(libc::IPPROTO_IP, v4_opt);
+                enable(libc::IPPROTO_IPV6, v6_opt)
+            } else {
+                enable(libc::IPPROTO_IP, v4_opt)
+            }
+        };
+        #[cfg(not(target_os = "freebsd"))]
+        let ip_pktinfo = libc::IP_PKTINFO;
+        #[cfg(target_os = "freebsd")]
+        let ip_pktinfo = libc::IP_RECVDSTADDR;
+        // Segmentation offload is requested per call with a control message,
+        // so only check that the kernel knows the option.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let (gso, gro, recverr) = (
+            unsafe {
+                self.getsockopt::<c_int>(libc::SOL_UDP, libc::UDP_SEGMENT)
+                    .is_ok()
+            },
+            enable(libc::SOL_UDP, libc::UDP_GRO),
+            either(libc::IP_RECVERR, libc::IPV6_RECVERR),
+        );
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let (gso, gro, recverr) = (false, false, false);
+        QuicFeatures {
+            pktinfo: either(ip_pktinfo, libc::IPV6_RECVPKTINFO),
+            ecn: either(libc::IP_RECVTOS, libc::IPV6_RECVTCLASS),
+            gso,
+            gro,
+            recverr,
+        }
+    }
+
+    #[cfg(any(target_os = "dragonfly", target_os = "freebsd",
+              target_os = "ios", target_os = "macos",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn use_min_mtu_v6(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_IPV6,
+                                             IPV6_USE_MIN_MTU)?;
+            Ok(raw == 1)
+        }
+    }
+
+    #[cfg(any(target_os = "dragonfly", target_os = "freebsd",
+              target_os = "ios", target_os = "macos",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn set_use_min_mtu_v6(&self, use_min_mtu: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_IPV6,
+                            IPV6_USE_MIN_MTU,
+                            use_min_mtu as c_int)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn join_packet_membership(&self,
+                                  ifindex: u32,
+                                  mode: &::MulticastMode) -> io::Result<()> {
+        let mreq = to_packet_mreq(ifindex, mode)?;
+        unsafe {
+            self.setsockopt(libc::SOL_PACKET, libc::PACKET_ADD_MEMBERSHIP, mreq)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn leave_packet_membership(&self,
+                                   ifindex: u32,
+                                   mode: &::MulticastMode) -> io::Result<()> {
+        let mreq = to_packet_mreq(ifindex, mode)?;
+        unsafe {
+            self.setsockopt(libc::SOL_PACKET, libc::PACKET_DROP_MEMBERSHIP, mreq)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn attach_reuse_port_hash_filter(&self, sockets: u32) -> io::Result<()> {
+        if sockets == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "number of sockets must not be zero"))
+        }
+        // A = skb->hash; A %= sockets; return A
+        let mut filter = [
+            libc::sock_filter {
+                code: (libc::BPF_LD | libc::BPF_W | libc::BPF_ABS) as u16,
+                jt: 0,
+                jf: 0,
+                k: (libc::SKF_AD_OFF + libc::SKF_AD_RXHASH) as u32,
+            },
+            libc::sock_filter {
+                code: (libc::BPF_ALU | libc::BPF_MOD | libc::BPF_K) as u16,
+                jt: 0,
+                jf: 0,
+                k: sockets,
+            },
+            libc::sock_filter {
+                code: (libc::BPF_RET | libc::BPF_A) as u16,
+                jt: 0,
+                jf: 0,
+                k: 0,
+            },
+        ];
+        let prog = libc::sock_fprog {
+            len: filter.len() as libc::c_ushort,
+            filter: filter.as_mut_ptr(),
+        };
+        unsafe {
+            self.setsockopt(libc::SOL_SOCKET,
+                            libc::SO_ATTACH_REUSEPORT_CBPF,
+                            prog)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn tcp_inq(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_TCP, libc::TCP_INQ)?;
+            Ok(raw != 0)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn set_tcp_inq(&self, inq: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_TCP, libc::TCP_INQ, inq as c_int)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn quickack(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(libc::IPPROTO_TCP,
+                                             libc::TCP_QUICKACK)?;
+            Ok(raw != 0)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_quickack(&self, quickack: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(libc::IPPROTO_TCP, libc::TCP_QUICKACK,
+                            quickack as c_int)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    pub fn tcp_congestion(&self) -> io::Result<String> {
+        let name: [u8; TCP_CA_NAME_MAX] = unsafe {
+            self.getsockopt(libc::IPPROTO_TCP, libc::TCP_CONGESTION)?
+        };
+        let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        Ok(String::from_utf8_lossy(&name[..len]).into_owned())
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    pub fn set_tcp_congestion(&self, algorithm: &str) -> io::Result<()> {
+        // The name is passed nul terminated, which both systems accept.
+        let mut name = [0u8; TCP_CA_NAME_MAX];
+        if algorithm.len() >= name.len() {
+            return Err(io::Error::new(ErrorKind::InvalidInput,
+                                      "congestion control algorithm name is \
+                                       too long"))
+        }
+        name[..algorithm.len()].copy_from_slice(algorithm.as_bytes());
+        unsafe {
+            self.setsockopt(libc::IPPROTO_TCP, libc::TCP_CONGESTION, name)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        // Like `meminfo`, older kernels fill in only a prefix of the
+        // structure, so the length tells which fields are valid.
+        let (info, len) = unsafe {
+            let mut info: libc::tcp_info = mem::zeroed();
+            let mut len = mem::size_of_val(&info) as libc::socklen_t;
+            cvt(libc::getsockopt(self.fd,
+                                 libc::IPPROTO_TCP,
+                                 libc::TCP_INFO,
+                                 &mut info as *mut _ as *mut _,
+                                 &mut len))?;
+            (info, len as usize)
+        };
+        let rate_end = &info.tcpi_delivery_rate as *const _ as usize -
+                       &info as *const _ as usize +
+                       mem::size_of_val(&info.tcpi_delivery_rate);
+        let state = match info.tcpi_state {
+            1 => TcpState::Established,
+            2 => TcpState::SynSent,
+            3 | 12 => TcpState::SynReceived,
+            4 => TcpState::FinWait1,
+            5 => TcpState::FinWait2,
+            6 => TcpState::TimeWait,
+            8 => TcpState::CloseWait,
+            9 => TcpState::LastAck,
+            10 => TcpState::Listen,
+            11 => TcpState::Closing,
+            _ => TcpState::Closed,
+        };
+        Ok(TcpInfo {
+            state,
+            rtt: Duration::from_micros(info.tcpi_rtt as u64),
+            retransmits: info.tcpi_total_retrans,
+            delivery_rate: if len >= rate_end {
+                Some(info.tcpi_delivery_rate)
+            } else {
+                None
+            },
+            // The congestion window is reported in segments.
+            cwnd: info.tcpi_snd_cwnd.saturating_mul(info.tcpi_snd_mss),
+        })
+    }
+
+    #[cfg(target_os = "freebsd")]
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        let info: libc::tcp_info = unsafe {
+            self.getsockopt(libc::IPPROTO_TCP, libc::TCP_INFO)?
+        };
+        let state = match info.tcpi_state {
+            1 => TcpState::Listen,
+            2 => TcpState::SynSent,
+            3 => TcpState::SynReceived,
+            4 => TcpState::Established,
+            5 => TcpState::CloseWait,
+            6 => TcpState::FinWait1,
+            7 => TcpState::Closing,
+            8 => TcpState::LastAck,
+            9 => TcpState::FinWait2,
+            10 => TcpState::TimeWait,
+            _ => TcpState::Closed,
+        };
+        Ok(TcpInfo {
+            state,
+            rtt: Duration::from_micros(info.tcpi_rtt as u64),
+            retransmits: info.tcpi_snd_rexmitpack,
+            delivery_rate: None,
+            cwnd: info.tcpi_snd_cwnd,
+        })
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn tcp_user_timeout(&self) -> io::Result<Option<Duration>> {
+        unsafe {
+            let ms: libc::c_uint = self.getsockopt(libc::IPPROTO_TCP,
+                                                   libc::TCP_USER_TIMEOUT)?;
+            if ms == 0 {
+                Ok(None)
+            } else {
+                Ok(Some(Duration::from_millis(ms as u64)))
+            }
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_user_timeout(&self, timeout: Option<Duration>)
+                                -> io::Result<()> {
+        // The option is a `c_uint`, but the kernel rejects values which don't
+        // fit in a `c_int`.
+        let ms = match timeout {
+            Some(dur) => {
+                let ms = dur.as_secs()
+                            .saturating_mul(1000)
+                            .saturating_add(dur.subsec_millis() as u64);
+                cmp::min(ms, c_int::MAX as u64) as libc::c_uint
+            }
+            None => 0,
+        };
+        unsafe {
+            self.setsockopt(libc::IPPROTO_TCP, libc::TCP_USER_TIMEOUT, ms)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn deferred_accept(&self) -> io::Result<Duration> {
+        unsafe {
+            let secs: c_int = self.getsockopt(libc::IPPROTO_TCP,
+                                              libc::TCP_DEFER_ACCEPT)?;
+            Ok(Duration::from_secs(secs as u64))
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_deferred_accept(&self, timeout: Duration) -> io::Result<()> {
+        let mut secs = timeout.as_secs();
+        if timeout.subsec_nanos() > 0 {
+            secs = secs.saturating_add(1);
+        }
+        let secs = cmp::min(secs, c_int::MAX as u64) as c_int;
+        unsafe {
+            self.setsockopt(libc::IPPROTO_TCP, libc::TCP_DEFER_ACCEPT, secs)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn tcp_syncnt(&self) -> io::Result<u32> {
+        unsafe {
+            self.getsockopt::<c_int>(libc::IPPROTO_TCP, libc::TCP_SYNCNT)
+                .map(|c| c as u32)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_syncnt(&self, count: u32) -> io::Result<()> {
+        let count = cmp::min(count, c_int::MAX as u32) as c_int;
+        unsafe {
+            self.setsockopt(libc::IPPROTO_TCP, libc::TCP_SYNCNT, count)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn window_clamp(&self) -> io::Result<u32> {
+        unsafe {
+            self.getsockopt::<c_int>(libc::IPPROTO_TCP, libc::TCP_WINDOW_CLAMP)
+                .map(|c| c as u32)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_window_clamp(&self, clamp: u32) -> io::Result<()> {
+        let clamp = cmp::min(clamp, c_int::MAX as u32) as c_int;
+        unsafe {
+            self.setsockopt(libc::IPPROTO_TCP, libc::TCP_WINDOW_CLAMP, clamp)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios"))]
+    pub fn notsent_lowat(&self) -> io::Result<u32> {
+        unsafe {
+            self.getsockopt::<c_int>(libc::IPPROTO_TCP, TCP_NOTSENT_LOWAT)
+                .map(|c| c as u32)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios"))]
+    pub fn set_notsent_lowat(&self, lowat: u32) -> io::Result<()> {
+        let lowat = cmp::min(lowat, c_int::MAX as u32) as c_int;
+        unsafe {
+            self.setsockopt(libc::IPPROTO_TCP, TCP_NOTSENT_LOWAT, lowat)
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_md5sig(&self, addr: &SockAddr, prefix_len: Option<u8>,
+                          key: &[u8]) -> io::Result<()> {
+        if key.len() > TCP_MD5SIG_MAXKEYLEN {
+            return Err(io::Error::new(ErrorKind::InvalidInput,
+                                      "TCP MD5 signature key is too long"))
+        }
+        unsafe {
+            let mut sig: tcp_md5sig = mem::zeroed();
+            ptr::copy_nonoverlapping(addr.as_ptr() as *const u8,
+                                     &mut sig.tcpm_addr as *mut _ as *mut u8,
+                                     addr.len() as usize);
+            sig.tcpm_keylen = key.len() as u16;
+            sig.tcpm_key[..key.len()].copy_from_slice(key);
+            let opt = match prefix_len {
+                Some(prefix_len) => {
+                    sig.tcpm_flags = TCP_MD5SIG_FLAG_PREFIX;
+                    sig.tcpm_prefixlen = prefix_len;
+                    libc::TCP_MD5SIG_EXT
+                }
+                None => libc::TCP_MD5SIG,
+            };
+            self.setsockopt(libc::IPPROTO_TCP, opt, sig)
         }
     }
 
-    pub fn only_v6(&self) -> io::Result<bool> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn save_syn(&self) -> io::Result<bool> {
         unsafe {
-            let raw: c_int = self.getsockopt(libc::IPPROTO_IPV6,
-                                             libc::IPV6_V6ONLY)?;
+            let raw: c_int = self.getsockopt(libc::IPPROTO_TCP,
+                                             libc::TCP_SAVE_SYN)?;
             Ok(raw != 0)
         }
     }
 
-    pub fn set_only_v6(&self, only_v6: bool) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_save_syn(&self, save: bool) -> io::Result<()> {
         unsafe {
-            self.setsockopt(libc::IPPROTO_IPV6,
-                            libc::IPV6_V6ONLY,
-                            only_v6 as c_int)
+            self.setsockopt(libc::IPPROTO_TCP,
+                            libc::TCP_SAVE_SYN,
+                            save as c_int)
         }
     }
 
-    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
-        unsafe {
-            Ok(timeval2dur(self.getsockopt(libc::SOL_SOCKET, libc::SO_RCVTIMEO)?))
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn saved_syn(&self) -> io::Result<Vec<u8>> {
+        // The headers of a SYN with IP and TCP options fit in 512 bytes, but
+        // if they don't the kernel fails with `EINVAL` and reports the
+        // needed length, leaving the headers in place for another try.
+        let mut buf = vec![0u8; 512];
+        loop {
+            let mut len = buf.len() as socklen_t;
+            let res = unsafe {
+                libc::getsockopt(self.fd,
+                                 libc::IPPROTO_TCP,
+                                 libc::TCP_SAVED_SYN,
+                                 buf.as_mut_ptr() as *mut c_void,
+                                 &mut len)
+            };
+            match cvt(res) {
+                Ok(_) => {
+                    buf.truncate(len as usize);
+                    return Ok(buf)
+                }
+                Err(ref e) if e.raw_os_error() == Some(libc::EINVAL) &&
+                              len as usize > buf.len() => {
+                    buf.resize(len as usize, 0);
+                }
+                Err(e) => return Err(e),
+            }
         }
     }
 
-    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn tcp_fastopen_key(&self) -> io::Result<[u8; 16]> {
+        // The backup key follows the primary key if there is one, so ask
+        // for both.
+        let mut keys = [0u8; 32];
+        let mut len = keys.len() as socklen_t;
         unsafe {
-            self.setsockopt(libc::SOL_SOCKET,
-                            libc::SO_RCVTIMEO,
-                            dur2timeval(dur))
+            cvt(libc::getsockopt(self.fd,
+                                 libc::IPPROTO_TCP,
+                                 libc::TCP_FASTOPEN_KEY,
+                                 keys.as_mut_ptr() as *mut c_void,
+                                 &mut len))?;
         }
+        let mut key = [0u8; 16];
+        key.copy_from_slice(&keys[..16]);
+        Ok(key)
     }
 
-    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_fastopen_key(&self, keys: &[u8]) -> io::Result<()> {
         unsafe {
-            Ok(timeval2dur(self.getsockopt(libc::SOL_SOCKET, libc::SO_SNDTIMEO)?))
+            cvt(libc::setsockopt(self.fd,
+                                 libc::IPPROTO_TCP,
+                                 libc::TCP_FASTOPEN_KEY,
+                                 keys.as_ptr() as *const c_void,
+                                 keys.len() as socklen_t))?;
+            Ok(())
         }
     }
 
-    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn is_multipath(&self) -> io::Result<bool> {
         unsafe {
-            self.setsockopt(libc::SOL_SOCKET,
-                            libc::SO_SNDTIMEO,
-                            dur2timeval(dur))
+            let raw: c_int = self.getsockopt(libc::IPPROTO_TCP, TCP_IS_MPTCP)?;
+            Ok(raw != 0)
         }
     }
 
-    pub fn nodelay(&self) -> io::Result<bool> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn max_pacing_rate(&self) -> io::Result<Option<u64>> {
+        // Kernels before 4.20 report the rate as a `u32`, and later ones as a
+        // `u64` if there's room for it, with all bits set for no limit.
+        let mut rate = 0u64;
+        let mut len = mem::size_of_val(&rate) as socklen_t;
         unsafe {
-            let raw: c_int = self.getsockopt(libc::IPPROTO_TCP,
-                                             libc::TCP_NODELAY)?;
-            Ok(raw != 0)
+            cvt(libc::getsockopt(self.fd,
+                                 libc::SOL_SOCKET,
+                                 SO_MAX_PACING_RATE,
+                                 &mut rate as *mut _ as *mut c_void,
+                                 &mut len))?;
+        }
+        if len as usize == mem::size_of::<u32>() {
+            let rate = unsafe { *(&rate as *const u64 as *const u32) };
+            Ok(if rate == u32::MAX { None } else { Some(rate as u64) })
+        } else {
+            Ok(if rate == u64::MAX { None } else { Some(rate) })
         }
     }
 
-    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_max_pacing_rate(&self, rate: Option<u64>) -> io::Result<()> {
+        // Pass rates which fit as a `u32`, which all kernels understand.
+        let rate = rate.unwrap_or(u64::MAX);
         unsafe {
-            self.setsockopt(libc::IPPROTO_TCP,
-                            libc::TCP_NODELAY,
-                            nodelay as c_int)
+            if rate == u64::MAX {
+                self.setsockopt(libc::SOL_SOCKET, SO_MAX_PACING_RATE,
+                                u32::MAX)
+            } else if rate < u32::MAX as u64 {
+                self.setsockopt(libc::SOL_SOCKET, SO_MAX_PACING_RATE,
+                                rate as u32)
+            } else {
+                self.setsockopt(libc::SOL_SOCKET, SO_MAX_PACING_RATE,
+                                rate)
+            }
         }
     }
 
-    pub fn broadcast(&self) -> io::Result<bool> {
+    #[cfg(target_os = "linux")]
+    pub fn tx_rehash(&self) -> io::Result<Option<bool>> {
+        // The default is stored as `SOCK_TXREHASH_DEFAULT` (255) until the
+        // sysctl's value is taken over when connecting.
         unsafe {
             let raw: c_int = self.getsockopt(libc::SOL_SOCKET,
-                                             libc::SO_BROADCAST)?;
-            Ok(raw != 0)
+                                             libc::SO_TXREHASH)?;
+            Ok(match raw {
+                0 => Some(false),
+                1 => Some(true),
+                _ => None,
+            })
         }
     }
 
-    pub fn set_broadcast(&self, broadcast: bool) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    pub fn set_tx_rehash(&self, rehash: Option<bool>) -> io::Result<()> {
+        let raw: c_int = match rehash {
+            Some(rehash) => rehash as c_int,
+            None => -1,
+        };
+        unsafe { self.setsockopt(libc::SOL_SOCKET, libc::SO_TXREHASH, raw) }
+    }
+
+    #[cfg(all(target_os = "linux", not(feature = "no-ioctl")))]
+    pub fn last_packet_timestamp(&self) -> io::Result<SystemTime> {
         unsafe {
-            self.setsockopt(libc::SOL_SOCKET,
-                            libc::SO_BROADCAST,
-                            broadcast as c_int)
+            let mut ts: libc::timespec = mem::zeroed();
+            cvt(libc::ioctl(self.fd, SIOCGSTAMPNS as _, &mut ts))?;
+            Ok(UNIX_EPOCH + Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
         }
     }
 
-    pub fn multicast_loop_v4(&self) -> io::Result<bool> {
+    #[cfg(target_os = "linux")]
+    pub fn enable_rx_timestamps(&self, hardware: bool) -> io::Result<()> {
+        let mut flags = libc::SOF_TIMESTAMPING_RX_SOFTWARE |
+                        libc::SOF_TIMESTAMPING_SOFTWARE;
+        if hardware {
+            flags |= libc::SOF_TIMESTAMPING_RX_HARDWARE |
+                     libc::SOF_TIMESTAMPING_RAW_HARDWARE;
+        }
         unsafe {
-            let raw: c_int = self.getsockopt(libc::IPPROTO_IP,
-                                             libc::IP_MULTICAST_LOOP)?;
-            Ok(raw != 0)
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_TIMESTAMPING, flags)?;
+            self.setsockopt(libc::SOL_SOCKET, libc::SO_RXQ_OVFL, 1 as c_int)
         }
     }
 
-    pub fn set_multicast_loop_v4(&self, multicast_loop_v4: bool) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn device(&self) -> io::Result<Option<Vec<u8>>> {
         unsafe {
-            self.setsockopt(libc::IPPROTO_IP,
-                            libc::IP_MULTICAST_LOOP,
-                            multicast_loop_v4 as c_int)
+            let mut buf = [0u8; libc::IFNAMSIZ];
+            let mut len = buf.len() as libc::socklen_t;
+            cvt(libc::getsockopt(self.fd,
+                                 libc::SOL_SOCKET,
+                                 libc::SO_BINDTODEVICE,
+                                 buf.as_mut_ptr() as *mut _,
+                                 &mut len))?;
+            // The name is reported with its nul terminator, if at all.
+            let name = &buf[..len as usize];
+            let name = match name.iter().position(|&b| b == 0) {
+                Some(end) => &name[..end],
+                None => name,
+            };
+            if name.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(name.to_vec()))
+            }
         }
     }
 
-    pub fn multicast_ttl_v4(&self) -> io::Result<u32> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn bind_device(&self, interface: Option<&[u8]>) -> io::Result<()> {
+        let (ptr, len) = match interface {
+            Some(name) => (name.as_ptr(), name.len()),
+            None => (ptr::null(), 0),
+        };
         unsafe {
-            let raw: c_int = self.getsockopt(libc::IPPROTO_IP,
-                                             libc::IP_MULTICAST_TTL)?;
-            Ok(raw as u32)
+            cvt(libc::setsockopt(self.fd,
+                                 libc::SOL_SOCKET,
+                                 libc::SO_BINDTODEVICE,
+                                 ptr as *const c_void,
+                                 len as socklen_t))?;
+            Ok(())
         }
     }
 
-    pub fn set_multicast_ttl_v4(&self, multicast_ttl_v4: u32) -> io::Result<()> {
+    // Makes the socket only see datagrams received on the interface named
+    // `interface`, as far as the platform supports it, for
+    // `Socket::bind_dhcp_client`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn restrict_to_interface(&self, interface: &[u8]) -> io::Result<()> {
+        self.bind_device(Some(interface))
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn restrict_to_interface(&self, interface: &[u8]) -> io::Result<()> {
+        let index = interface_index(interface)?;
         unsafe {
-            self.setsockopt(libc::IPPROTO_IP,
-                            libc::IP_MULTICAST_TTL,
-                            multicast_ttl_v4 as c_int)
+            self.setsockopt(libc::IPPROTO_IP, libc::IP_BOUND_IF,
+                            index as c_int)
         }
     }
 
-    pub fn multicast_loop_v6(&self) -> io::Result<bool> {
+    #[cfg(any(target_os = "freebsd", target_os = "dragonfly",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn restrict_to_interface(&self, interface: &[u8]) -> io::Result<()> {
+        // There's no way to bind to an interface, so only make sure that it
+        // exists and have the receiving interface reported instead.
+        interface_index(interface)?;
         unsafe {
-            let raw: c_int = self.getsockopt(libc::IPPROTO_IPV6,
-                                             libc::IPV6_MULTICAST_LOOP)?;
-            Ok(raw != 0)
+            self.setsockopt(libc::IPPROTO_IP, libc::IP_RECVIF, 1 as c_int)
         }
     }
 
-    pub fn set_multicast_loop_v6(&self, multicast_loop_v6: bool) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    pub fn meminfo(&self) -> io::Result<[u32; 9]> {
+        // Older kernels report fewer than 9 values, so unlike `getsockopt`
+        // below a short length is fine here and the remainder stays zeroed.
         unsafe {
-            self.setsockopt(libc::IPPROTO_IPV6,
-                            libc::IPV6_MULTICAST_LOOP,
-                            multicast_loop_v6 as c_int)
+            let mut slot = [0u32; 9];
+            let mut len = mem::size_of_val(&slot) as libc::socklen_t;
+            cvt(libc::getsockopt(self.fd,
+                                 libc::SOL_SOCKET,
+                                 libc::SO_MEMINFO,
+                                 slot.as_mut_ptr() as *mut _,
+                                 &mut len))?;
+            Ok(slot)
         }
     }
 
-    pub fn join_multicast_v4(&self,
-                             multiaddr: &Ipv4Addr,
-                             interface: &Ipv4Addr) -> io::Result<()> {
-        let multiaddr = to_s_addr(multiaddr);
-        let interface = to_s_addr(interface);
-        let mreq = libc::ip_mreq {
-            imr_multiaddr: libc::in_addr { s_addr: multiaddr },
-            imr_interface: libc::in_addr { s_addr: interface },
-        };
+    #[cfg(target_os = "linux")]
+    pub fn set_can_filters(&self, filters: &[::CanFilter]) -> io::Result<()> {
+        let filters = filters.iter().map(|f| {
+            libc::can_filter { can_id: f.id, can_mask: f.mask }
+        }).collect::<Vec<_>>();
         unsafe {
-            self.setsockopt(libc::IPPROTO_IP, libc::IP_ADD_MEMBERSHIP, mreq)
+            cvt(libc::setsockopt(self.fd,
+                                 libc::SOL_CAN_RAW,
+                                 libc::CAN_RAW_FILTER,
+                                 filters.as_ptr() as *const c_void,
+                                 mem::size_of_val(&filters[..]) as socklen_t))?;
+            Ok(())
         }
     }
 
-    pub fn join_multicast_v6(&self,
-                             multiaddr: &Ipv6Addr,
-                             interface: u32) -> io::Result<()> {
-        let multiaddr = to_in6_addr(multiaddr);
-        let mreq = libc::ipv6_mreq {
-            ipv6mr_multiaddr: multiaddr,
-            ipv6mr_interface: to_ipv6mr_interface(interface),
-        };
+    #[cfg(target_os = "linux")]
+    pub fn can_err_filter(&self) -> io::Result<u32> {
         unsafe {
-            self.setsockopt(libc::IPPROTO_IP, IPV6_ADD_MEMBERSHIP, mreq)
+            self.getsockopt(libc::SOL_CAN_RAW, libc::CAN_RAW_ERR_FILTER)
         }
     }
 
-    pub fn leave_multicast_v4(&self,
-                              multiaddr: &Ipv4Addr,
-                              interface: &Ipv4Addr) -> io::Result<()> {
-        let multiaddr = to_s_addr(multiaddr);
-        let interface = to_s_addr(interface);
-        let mreq = libc::ip_mreq {
-            imr_multiaddr: libc::in_addr { s_addr: multiaddr },
-            imr_interface: libc::in_addr { s_addr: interface },
-        };
+    #[cfg(target_os = "linux")]
+    pub fn set_can_err_filter(&self, mask: u32) -> io::Result<()> {
         unsafe {
-            self.setsockopt(libc::IPPROTO_IP, libc::IP_DROP_MEMBERSHIP, mreq)
+            self.setsockopt(libc::SOL_CAN_RAW, libc::CAN_RAW_ERR_FILTER, mask)
         }
     }
 
-    pub fn leave_multicast_v6(&self,
-                              multiaddr: &Ipv6Addr,
-                              interface: u32) -> io::Result<()> {
-        let multiaddr = to_in6_addr(multiaddr);
-        let mreq = libc::ipv6_mreq {
-            ipv6mr_multiaddr: multiaddr,
-            ipv6mr_interface: to_ipv6mr_interface(interface),
-        };
+    #[cfg(target_os = "linux")]
+    pub fn can_loopback(&self) -> io::Result<bool> {
         unsafe {
-            self.setsockopt(libc::IPPROTO_IP, IPV6_DROP_MEMBERSHIP, mreq)
+            let raw: c_int = self.getsockopt(libc::SOL_CAN_RAW,
+                                             libc::CAN_RAW_LOOPBACK)?;
+            Ok(raw != 0)
         }
     }
 
-    pub fn linger(&self) -> io::Result<Option<Duration>> {
+    #[cfg(target_os = "linux")]
+    pub fn set_can_loopback(&self, loopback: bool) -> io::Result<()> {
         unsafe {
-            Ok(linger2dur(self.getsockopt(libc::SOL_SOCKET, libc::SO_LINGER)?))
+            self.setsockopt(libc::SOL_CAN_RAW,
+                            libc::CAN_RAW_LOOPBACK,
+                            loopback as c_int)
         }
     }
 
-    pub fn set_linger(&self, dur: Option<Duration>) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    pub fn can_fd_frames(&self) -> io::Result<bool> {
         unsafe {
-            self.setsockopt(libc::SOL_SOCKET, libc::SO_LINGER, dur2linger(dur))
+            let raw: c_int = self.getsockopt(libc::SOL_CAN_RAW,
+                                             libc::CAN_RAW_FD_FRAMES)?;
+            Ok(raw != 0)
         }
     }
 
-    pub fn set_reuse_address(&self, reuse: bool) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    pub fn set_can_fd_frames(&self, fd_frames: bool) -> io::Result<()> {
         unsafe {
-            self.setsockopt(libc::SOL_SOCKET, libc::SO_REUSEADDR, reuse as c_int)
+            self.setsockopt(libc::SOL_CAN_RAW,
+                            libc::CAN_RAW_FD_FRAMES,
+                            fd_frames as c_int)
         }
     }
 
-    pub fn reuse_address(&self) -> io::Result<bool> {
+    #[cfg(target_os = "linux")]
+    pub fn set_rxrpc_security_key(&self, key: &str) -> io::Result<()> {
+        self.set_rxrpc_str(RXRPC_SECURITY_KEY, key)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn set_rxrpc_security_keyring(&self, keyring: &str) -> io::Result<()> {
+        self.set_rxrpc_str(RXRPC_SECURITY_KEYRING, keyring)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn set_rxrpc_str(&self, opt: c_int, value: &str) -> io::Result<()> {
         unsafe {
-            let raw: c_int = self.getsockopt(libc::SOL_SOCKET,
-                                             libc::SO_REUSEADDR)?;
-            Ok(raw != 0)
+            cvt(libc::setsockopt(self.fd,
+                                 libc::SOL_RXRPC,
+                                 opt,
+                                 value.as_ptr() as *const c_void,
+                                 value.len() as socklen_t))?;
+            Ok(())
         }
     }
 
-    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+    #[cfg(target_os = "linux")]
+    pub fn set_rxrpc_min_security_level(&self, level: u32) -> io::Result<()> {
         unsafe {
-            let raw: c_int = self.getsockopt(libc::SOL_SOCKET, libc::SO_RCVBUF)?;
-            Ok(raw as usize)
+            self.setsockopt(libc::SOL_RXRPC, RXRPC_MIN_SECURITY_LEVEL, level)
         }
     }
 
-    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    pub fn join_netlink_membership(&self, group: u32) -> io::Result<()> {
         unsafe {
-            // TODO: casting usize to a c_int should be a checked cast
-            self.setsockopt(libc::SOL_SOCKET, libc::SO_RCVBUF, size as c_int)
+            self.setsockopt(libc::SOL_NETLINK,
+                            libc::NETLINK_ADD_MEMBERSHIP,
+                            group as c_int)
         }
     }
 
-    pub fn send_buffer_size(&self) -> io::Result<usize> {
+    #[cfg(target_os = "linux")]
+    pub fn leave_netlink_membership(&self, group: u32) -> io::Result<()> {
         unsafe {
-            let raw: c_int = self.getsockopt(libc::SOL_SOCKET, libc::SO_SNDBUF)?;
-            Ok(raw as usize)
+            self.setsockopt(libc::SOL_NETLINK,
+                            libc::NETLINK_DROP_MEMBERSHIP,
+                            group as c_int)
         }
     }
 
-    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    pub fn netlink_ext_ack(&self) -> io::Result<bool> {
         unsafe {
-            // TODO: casting usize to a c_int should be a checked cast
-            self.setsockopt(libc::SOL_SOCKET, libc::SO_SNDBUF, size as c_int)
+            let raw: c_int = self.getsockopt(libc::SOL_NETLINK,
+                                             libc::NETLINK_EXT_ACK)?;
+            Ok(raw != 0)
         }
     }
 
-    pub fn keepalive(&self) -> io::Result<Option<Duration>> {
+    #[cfg(target_os = "linux")]
+    pub fn set_netlink_ext_ack(&self, ext_ack: bool) -> io::Result<()> {
         unsafe {
-            let raw: c_int = self.getsockopt(libc::SOL_SOCKET,
-                                             libc::SO_KEEPALIVE)?;
-            if raw == 0 {
-                return Ok(None)
-            }
-            let secs: c_int = self.getsockopt(libc::IPPROTO_TCP,
-                                              KEEPALIVE_OPTION)?;
-            Ok(Some(Duration::new(secs as u64, 0)))
+            self.setsockopt(libc::SOL_NETLINK,
+                            libc::NETLINK_EXT_ACK,
+                            ext_ack as c_int)
         }
     }
 
-    pub fn set_keepalive(&self, keepalive: Option<Duration>) -> io::Result<()> {
+    pub fn send_with_fds(&self, buf: &[u8], fds: &[c_int]) -> io::Result<usize> {
+        let fds_len = mem::size_of_val(fds);
+        let mut control = cmsg_buffer(fds_len);
         unsafe {
-            self.setsockopt(libc::SOL_SOCKET,
-                            libc::SO_KEEPALIVE,
-                            keepalive.is_some() as c_int)?;
-            if let Some(dur) = keepalive {
-                // TODO: checked cast here
-                self.setsockopt(libc::IPPROTO_TCP,
-                                KEEPALIVE_OPTION,
-                                (dur.as_secs() / 1000) as c_int)?;
+            let mut iov = libc::iovec {
+                iov_base: buf.as_ptr() as *mut c_void,
+                iov_len: cmp::min(buf.len(), max_len()),
+            };
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            if !fds.is_empty() {
+                msg.msg_control = control.as_mut_ptr() as *mut c_void;
+                msg.msg_controllen = libc::CMSG_SPACE(fds_len as u32) as _;
+                let cmsg = libc::CMSG_FIRSTHDR(&msg);
+                (*cmsg).cmsg_level = libc::SOL_SOCKET;
+                (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(fds_len as u32) as _;
+                ptr::copy_nonoverlapping(fds.as_ptr() as *const u8,
+                                         libc::CMSG_DATA(cmsg),
+                                         fds_len);
             }
-            Ok(())
+            let n = cvt_r(|| libc::sendmsg(self.fd, &msg, MSG_NOSIGNAL))?;
+            Ok(n as usize)
         }
     }
 
-    #[cfg(all(unix, feature = "reuseport"))]
-    pub fn reuse_port(&self) -> io::Result<bool> {
+    pub fn recv_with_fds(&self, buf: &mut [u8], fds: &mut [c_int])
+                         -> io::Result<(usize, usize)> {
+        let fds_len = mem::size_of_val(fds);
+        let mut control = cmsg_buffer(fds_len);
         unsafe {
-            let raw: c_int = self.getsockopt(libc::SOL_SOCKET,
-                                             libc::SO_REUSEPORT)?;
-            Ok(raw != 0)
+            let mut iov = libc::iovec {
+                iov_base: buf.as_mut_ptr() as *mut c_void,
+                iov_len: cmp::min(buf.len(), max_len()),
+            };
+            let mut msg: libc::msghdr = mem::zeroed();
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            if !fds.is_empty() {
+                msg.msg_control = control.as_mut_ptr() as *mut c_void;
+                msg.msg_controllen = libc::CMSG_SPACE(fds_len as u32) as _;
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            let flags = libc::MSG_CMSG_CLOEXEC;
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            let flags = 0;
+            let n = cvt_r(|| libc::recvmsg(self.fd, &mut msg, flags))?;
+
+            let mut count = 0;
+            let mut overflow = false;
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+            while !cmsg.is_null() {
+                if (*cmsg).cmsg_level == libc::SOL_SOCKET &&
+                   (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                    let data = libc::CMSG_DATA(cmsg);
+                    let len = (*cmsg).cmsg_len as usize -
+                              (data as usize - cmsg as usize);
+                    for i in 0..len / mem::size_of::<c_int>() {
+                        let fd = ptr::read_unaligned((data as *const c_int).add(i));
+                        if count < fds.len() {
+                            fds[count] = fd;
+                            count += 1;
+                        } else {
+                            libc::close(fd);
+                            overflow = true;
+                        }
+                    }
+                }
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+
+            if overflow || msg.msg_flags & libc::MSG_CTRUNC != 0 {
+                for &fd in &fds[..count] {
+                    libc::close(fd);
+                }
+                return Err(io::Error::new(ErrorKind::InvalidData,
+                                          "too many file descriptors in \
+                                           message"))
+            }
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            for &fd in &fds[..count] {
+                set_cloexec(fd)?;
+            }
+            Ok((n as usize, count))
         }
     }
 
-    #[cfg(all(unix, feature = "reuseport"))]
-    pub fn set_reuse_port(&self, reuse: bool) -> io::Result<()> {
-        unsafe {
-            self.setsockopt(libc::SOL_SOCKET, libc::SO_REUSEPORT, reuse as c_int)
+    // Blocks until one of the `interest` events is signaled on this socket,
+    // returning a `TimedOut` error if that doesn't happen before `deadline`.
+    fn wait(&self, interest: libc::c_short, deadline: Instant) -> io::Result<()> {
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(ErrorKind::TimedOut,
+                                          "deadline has elapsed"))
+            }
+            let mut pollfd = libc::pollfd {
+                fd: self.fd,
+                events: interest,
+                revents: 0,
+            };
+            let timeout = dur2ms(deadline - now);
+            match cvt(unsafe { libc::poll(&mut pollfd, 1, timeout) }) {
+                Ok(0) => {}
+                Ok(_) => return Ok(()),
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
         }
     }
 
@@ -647,6 +2705,10 @@ impl Read for Socket {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         <&Socket>::read(&mut &*self, buf)
     }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        <&Socket>::read_vectored(&mut &*self, bufs)
+    }
 }
 
 impl<'a> Read for &'a Socket {
@@ -660,6 +2722,10 @@ impl<'a> Read for &'a Socket {
             Ok(n as usize)
         }
     }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        self.recv_vectored(bufs)
+    }
 }
 
 impl Write for Socket {
@@ -667,6 +2733,10 @@ impl Write for Socket {
         <&Socket>::write(&mut &*self, buf)
     }
 
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        <&Socket>::write_vectored(&mut &*self, bufs)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         <&Socket>::flush(&mut &*self)
     }
@@ -677,6 +2747,10 @@ impl<'a> Write for &'a Socket {
         self.send(buf)
     }
 
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        self.send_vectored(bufs)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         Ok(())
     }
@@ -686,6 +2760,27 @@ impl fmt::Debug for Socket {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut f = f.debug_struct("Socket");
         f.field("fd", &self.fd);
+        if let Ok(meta) = self.metadata() {
+            f.field("domain", &meta.domain);
+            f.field("type", &meta.ty);
+            if let Some(protocol) = meta.protocol {
+                f.field("protocol", &protocol);
+            }
+        }
+        if let Ok(flags) = cvt(unsafe { libc::fcntl(self.fd, libc::F_GETFL) }) {
+            f.field("nonblocking", &(flags & libc::O_NONBLOCK != 0));
+        }
+        #[cfg(any(target_os = "linux", target_os = "android",
+                  target_os = "macos", target_os = "ios",
+                  target_os = "freebsd", target_os = "dragonfly",
+                  target_os = "openbsd", target_os = "netbsd"))] {
+            let listening = unsafe {
+                self.getsockopt::<c_int>(libc::SOL_SOCKET, libc::SO_ACCEPTCONN)
+            };
+            if let Ok(listening) = listening {
+                f.field("listening", &(listening != 0));
+            }
+        }
         if let Ok(addr) = self.local_addr() {
             f.field("local_addr", &addr);
         }
@@ -696,6 +2791,45 @@ impl fmt::Debug for Socket {
     }
 }
 
+impl Socket {
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        self.meta.get_or_try_init(|| {
+            let ty = unsafe { self.getsockopt(libc::SOL_SOCKET, libc::SO_TYPE)? };
+            Ok(Metadata {
+                domain: self.domain()?,
+                ty,
+                protocol: self.protocol(),
+            })
+        })
+    }
+
+    // The address family is read directly where the system supports it and
+    // taken from the local address elsewhere.
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "freebsd"))]
+    fn domain(&self) -> io::Result<c_int> {
+        unsafe { self.getsockopt(libc::SOL_SOCKET, libc::SO_DOMAIN) }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android",
+                  target_os = "freebsd")))]
+    fn domain(&self) -> io::Result<c_int> {
+        self.local_addr().map(|addr| addr.family() as c_int)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "freebsd"))]
+    fn protocol(&self) -> Option<c_int> {
+        unsafe { self.getsockopt(libc::SOL_SOCKET, libc::SO_PROTOCOL).ok() }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android",
+                  target_os = "freebsd")))]
+    fn protocol(&self) -> Option<c_int> {
+        None
+    }
+}
+
 impl AsRawFd for Socket {
     fn as_raw_fd(&self) -> c_int {
         self.fd
@@ -705,14 +2839,22 @@ impl AsRawFd for Socket {
 impl IntoRawFd for Socket {
     fn into_raw_fd(self) -> c_int {
         let fd = self.fd;
+        // The socket file stays in place, but the rest of the socket has to
+        // be dropped as `forget` skips it.
+        let unlink_on_drop = unsafe { ptr::read(&self.unlink_on_drop) };
         mem::forget(self);
+        drop(unlink_on_drop);
         return fd
     }
 }
 
 impl FromRawFd for Socket {
     unsafe fn from_raw_fd(fd: c_int) -> Socket {
-        Socket { fd: fd }
+        Socket {
+            fd: fd,
+            meta: MetadataCache::new(),
+            unlink_on_drop: Mutex::new(None),
+        }
     }
 }
 
@@ -736,6 +2878,15 @@ impl FromRawFd for ::Socket {
 
 impl Drop for Socket {
     fn drop(&mut self) {
+        // The file is removed before the socket is closed, so that it
+        // disappears together with the socket for clients.
+        let file = match self.unlink_on_drop.get_mut() {
+            Ok(file) => file.take(),
+            Err(err) => err.into_inner().take(),
+        };
+        if let Some(file) = file {
+            file.unlink();
+        }
         unsafe {
             let _ = libc::close(self.fd);
         }
@@ -815,6 +2966,12 @@ fn cvt_r<F, T>(mut f: F) -> io::Result<T>
     }
 }
 
+// Keepalive times are set in whole seconds, saturating at the largest value
+// the options can hold.
+fn keepalive_secs(dur: Duration) -> c_int {
+    cmp::min(dur.as_secs(), c_int::MAX as u64) as c_int
+}
+
 fn set_cloexec(fd: c_int) -> io::Result<()> {
     unsafe {
         let previous = cvt(libc::fcntl(fd, libc::F_GETFD))?;
@@ -826,57 +2983,27 @@ fn set_cloexec(fd: c_int) -> io::Result<()> {
     }
 }
 
-fn addr2raw(addr: &SocketAddr) -> (*const sockaddr, socklen_t) {
-    match *addr {
-        SocketAddr::V4(ref a) => {
-            (a as *const _ as *const _, mem::size_of_val(a) as socklen_t)
-        }
-        SocketAddr::V6(ref a) => {
-            (a as *const _ as *const _, mem::size_of_val(a) as socklen_t)
-        }
+// Returns storage for a socket address which is about to be filled in by the
+// kernel.
+//
+// Zeroing the entire `sockaddr_storage` on every call shows up in profiles of
+// datagram-heavy workloads, so only the family is initialized here. That's
+// enough for `SockAddr::from_raw_parts`, which only copies the part of the
+// storage the kernel wrote.
+fn addr_storage() -> MaybeUninit<sockaddr_storage> {
+    let mut storage = MaybeUninit::<sockaddr_storage>::uninit();
+    unsafe {
+        (*storage.as_mut_ptr()).ss_family = 0;
     }
+    storage
 }
 
-fn raw2addr(storage: &sockaddr_storage, len: socklen_t) -> io::Result<SocketAddr> {
-    match storage.ss_family as c_int {
-        AF_INET => {
-            unsafe {
-                assert!(len as usize >= mem::size_of::<sockaddr_in>());
-                let sa = storage as *const _ as *const sockaddr_in;
-                let bits = ::ntoh((*sa).sin_addr.s_addr);
-                let ip = Ipv4Addr::new((bits >> 24) as u8,
-                                       (bits >> 16) as u8,
-                                       (bits >> 8) as u8,
-                                       bits as u8);
-                Ok(SocketAddr::V4(SocketAddrV4::new(ip, ::ntoh((*sa).sin_port))))
-            }
-        }
-        AF_INET6 => {
-            unsafe {
-                assert!(len as usize >= mem::size_of::<sockaddr_in6>());
-
-                let sa = storage as *const _ as *const sockaddr_in6;
-                let arr = (*sa).sin6_addr.s6_addr;
-
-                let ip = Ipv6Addr::new(
-                    (arr[0] as u16) << 8 | (arr[1] as u16),
-                    (arr[2] as u16) << 8 | (arr[3] as u16),
-                    (arr[4] as u16) << 8 | (arr[5] as u16),
-                    (arr[6] as u16) << 8 | (arr[7] as u16),
-                    (arr[8] as u16) << 8 | (arr[9] as u16),
-                    (arr[10] as u16) << 8 | (arr[11] as u16),
-                    (arr[12] as u16) << 8 | (arr[13] as u16),
-                    (arr[14] as u16) << 8 | (arr[15] as u16),
-                );
-
-                Ok(SocketAddr::V6(SocketAddrV6::new(ip,
-                                                    ::ntoh((*sa).sin6_port),
-                                                    (*sa).sin6_flowinfo,
-                                                    (*sa).sin6_scope_id)))
-            }
-        }
-        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid argument")),
-    }
+// Returns a buffer suitably aligned for control messages holding `len` bytes
+// of `SCM_RIGHTS` data.
+fn cmsg_buffer(len: usize) -> Vec<libc::cmsghdr> {
+    let space = unsafe { libc::CMSG_SPACE(len as u32) as usize };
+    let hdr = mem::size_of::<libc::cmsghdr>();
+    (0..space.div_ceil(hdr)).map(|_| unsafe { mem::zeroed() }).collect()
 }
 
 fn dur2timeval(dur: Option<Duration>) -> io::Result<libc::timeval> {
@@ -910,6 +3037,15 @@ fn dur2timeval(dur: Option<Duration>) -> io::Result<libc::timeval> {
     }
 }
 
+// Converts a duration to milliseconds for `poll`, rounding up so a short
+// timeout doesn't turn into a busy loop.
+fn dur2ms(dur: Duration) -> c_int {
+    let ms = dur.as_secs()
+        .saturating_mul(1000)
+        .saturating_add(dur.subsec_nanos().div_ceil(1_000_000) as u64);
+    cmp::min(ms, c_int::MAX as u64) as c_int
+}
+
 fn timeval2dur(raw: libc::timeval) -> Option<Duration> {
     if raw.tv_sec == 0 && raw.tv_usec == 0 {
         None
@@ -928,12 +3064,69 @@ fn to_s_addr(addr: &Ipv4Addr) -> libc::in_addr_t {
            ((octets[3] as libc::in_addr_t) <<  0))
 }
 
-fn to_in6_addr(addr: &Ipv6Addr) -> libc::in6_addr {
+pub fn to_in_addr(addr: &Ipv4Addr) -> libc::in_addr {
+    libc::in_addr { s_addr: to_s_addr(addr) }
+}
+
+pub fn from_in_addr(addr: libc::in_addr) -> Ipv4Addr {
+    Ipv4Addr::from(::ntoh(addr.s_addr))
+}
+
+pub fn to_in6_addr(addr: &Ipv6Addr) -> libc::in6_addr {
     let mut ret: libc::in6_addr = unsafe { mem::zeroed() };
     ret.s6_addr = addr.octets();
     return ret
 }
 
+#[cfg(any(target_os = "macos", target_os = "ios",
+          target_os = "freebsd", target_os = "dragonfly",
+          target_os = "openbsd", target_os = "netbsd"))]
+fn interface_index(name: &[u8]) -> io::Result<u32> {
+    let name = ::std::ffi::CString::new(name).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput,
+                       "interface name contains a NUL byte")
+    })?;
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(io::Error::last_os_error()),
+        index => Ok(index),
+    }
+}
+
+#[cfg(not(feature = "no-ioctl"))]
+pub fn interface_name(index: u32) -> io::Result<String> {
+    let mut name = [0 as libc::c_char; libc::IF_NAMESIZE];
+    if unsafe { libc::if_indextoname(index, name.as_mut_ptr()) }.is_null() {
+        return Err(io::Error::last_os_error())
+    }
+    let name = unsafe { ::std::ffi::CStr::from_ptr(name.as_ptr()) };
+    Ok(name.to_string_lossy().into_owned())
+}
+
+#[cfg(target_os = "linux")]
+fn to_packet_mreq(ifindex: u32, mode: &::MulticastMode)
+                  -> io::Result<libc::packet_mreq> {
+    let mut mreq: libc::packet_mreq = unsafe { mem::zeroed() };
+    mreq.mr_ifindex = ifindex as c_int;
+    match *mode {
+        ::MulticastMode::Promiscuous => {
+            mreq.mr_type = libc::PACKET_MR_PROMISC as libc::c_ushort;
+        }
+        ::MulticastMode::AllMulticast => {
+            mreq.mr_type = libc::PACKET_MR_ALLMULTI as libc::c_ushort;
+        }
+        ::MulticastMode::Multicast(addr) => {
+            if addr.len() > mreq.mr_address.len() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          "link-layer address too long"))
+            }
+            mreq.mr_type = libc::PACKET_MR_MULTICAST as libc::c_ushort;
+            mreq.mr_alen = addr.len() as libc::c_ushort;
+            mreq.mr_address[..addr.len()].copy_from_slice(addr);
+        }
+    }
+    Ok(mreq)
+}
+
 #[cfg(target_os = "android")]
 fn to_ipv6mr_interface(value: u32) -> c_int {
     value as c_int
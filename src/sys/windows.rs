@@ -8,29 +8,90 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::any::Any;
 use std::cmp;
 use std::fmt;
-use std::io::{Read, Write};
+use std::io::{Read, Write, IoSlice, IoSliceMut};
 use std::io;
 use std::mem;
+use std::mem::MaybeUninit;
 use std::net::Shutdown;
-use std::net::{self, Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6, SocketAddr};
+use std::net::{self, Ipv4Addr, Ipv6Addr};
 use std::os::windows::prelude::*;
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Once, ONCE_INIT};
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use kernel32;
 use winapi::*;
 use ws2_32;
 
+use {Metadata, SockAddr, TcpInfo, TcpKeepalive, TcpState};
+use utils::{as_uninit_mut, MetadataCache};
+
+const CF_ACCEPT: c_int = 0x0000;
+const CF_REJECT: c_int = 0x0001;
+const FIONREAD: c_long = 0x4004667F;
 const HANDLE_FLAG_INHERIT: DWORD = 0x00000001;
+const IF_NAMESIZE: usize = 257;
+const IP_TOS: c_int = 3;
+const IPV6_TCLASS: c_int = 39;
+const IPV6_UNICAST_HOPS: c_int = 4;
+const MSG_OOB: c_int = 0x1;
 const MSG_PEEK: c_int = 0x2;
 const SD_BOTH: c_int = 2;
 const SD_RECEIVE: c_int = 0;
 const SD_SEND: c_int = 1;
+const SIOCATMARK: c_long = 0x40047307;
+const SO_ACCEPTCONN: c_int = 0x0002;
+const SO_CONDITIONAL_ACCEPT: c_int = 0x3002;
 const SIO_KEEPALIVE_VALS: DWORD = 0x98000004;
+const SIO_TCP_INFO: DWORD = 0xD8000027;
+const SO_EXCLUSIVEADDRUSE: c_int = !0x0004;
+const SO_OOBINLINE: c_int = 0x0100;
+const SO_PROTOCOL_INFOW: c_int = 0x2005;
+const TCP_KEEPCNT: c_int = 16;
 const WSA_FLAG_OVERLAPPED: DWORD = 0x01;
+const WSA_FLAG_NO_HANDLE_INHERIT: DWORD = 0x80;
+
+pub const FD_READ: c_long = 0x01;
+pub const FD_WRITE: c_long = 0x02;
+pub const FD_OOB: c_long = 0x04;
+pub const FD_ACCEPT: c_long = 0x08;
+pub const FD_CONNECT: c_long = 0x10;
+pub const FD_CLOSE: c_long = 0x20;
+
+pub const IPPROTO_ICMP: c_int = ::winapi::IPPROTO_ICMP.0 as c_int;
+pub const IPPROTO_ICMPV6: c_int = ::winapi::IPPROTO_ICMPV6.0 as c_int;
+
+// The condition function of `WSAAccept`, with the unused QoS and group
+// arguments left as opaque pointers.
+type ConditionProc = unsafe extern "system" fn(caller_id: *mut WSABUF,
+                                               caller_data: *mut WSABUF,
+                                               sqos: *mut c_void,
+                                               gqos: *mut c_void,
+                                               callee_id: *mut WSABUF,
+                                               callee_data: *mut WSABUF,
+                                               g: *mut c_uint,
+                                               callback_data: usize) -> c_int;
+
+// `WSAAccept` isn't bound by the version of `ws2_32-sys` in use.
+#[link(name = "ws2_32")]
+extern "system" {
+    fn WSAAccept(s: SOCKET,
+                 addr: *mut SOCKADDR,
+                 addrlen: *mut c_int,
+                 condition: Option<ConditionProc>,
+                 callback_data: usize) -> SOCKET;
+}
+
+#[link(name = "iphlpapi")]
+extern "system" {
+    fn if_indextoname(index: c_ulong, name: *mut c_char) -> *mut c_char;
+}
 
 #[repr(C)]
 struct tcp_keepalive {
@@ -39,6 +100,31 @@ struct tcp_keepalive {
     keepaliveinterval: c_ulong,
 }
 
+// `TCP_INFO_v0` from mstcpip.h, returned by `SIO_TCP_INFO`.
+#[repr(C)]
+#[allow(dead_code)]
+struct tcp_info_v0 {
+    state: c_int,
+    mss: c_ulong,
+    connection_time_ms: u64,
+    timestamps_enabled: BOOLEAN,
+    rtt_us: c_ulong,
+    min_rtt_us: c_ulong,
+    bytes_in_flight: c_ulong,
+    cwnd: c_ulong,
+    snd_wnd: c_ulong,
+    rcv_wnd: c_ulong,
+    rcv_buf: c_ulong,
+    bytes_out: u64,
+    bytes_in: u64,
+    bytes_reordered: c_ulong,
+    bytes_retrans: c_ulong,
+    fast_retrans: c_ulong,
+    dup_acks_in: c_ulong,
+    timeout_episodes: c_ulong,
+    syn_retrans: u8,
+}
+
 fn init() {
     static INIT: Once = ONCE_INIT;
 
@@ -56,12 +142,36 @@ fn last_error() -> io::Error {
 
 pub struct Socket {
     socket: SOCKET,
+    meta: MetadataCache,
+    // Windows can't report whether a socket is nonblocking, so the mode last
+    // set through `set_nonblocking` is recorded here. Sockets are assumed to
+    // start out blocking, as they do when created by `WSASocketW`.
+    nonblocking: AtomicBool,
 }
 
 impl Socket {
     pub fn new(family: c_int, ty: c_int, protocol: c_int) -> io::Result<Socket> {
         init();
         unsafe {
+            // We first attempt to create the socket as non-inheritable
+            // atomically. The flag is only supported since Windows 7 SP1, so
+            // if it's rejected we fall back to setting it afterwards.
+            match ws2_32::WSASocketW(family,
+                                     ty,
+                                     protocol,
+                                     ptr::null_mut(),
+                                     0,
+                                     WSA_FLAG_OVERLAPPED |
+                                     WSA_FLAG_NO_HANDLE_INHERIT) {
+                INVALID_SOCKET => {
+                    let err = last_error();
+                    if err.raw_os_error() != Some(WSAEINVAL as i32) {
+                        return Err(err)
+                    }
+                }
+                socket => return Ok(Socket::from_raw_socket(socket)),
+            }
+
             let socket = match ws2_32::WSASocketW(family,
                                                   ty,
                                                   protocol,
@@ -77,10 +187,9 @@ impl Socket {
         }
     }
 
-    pub fn bind(&self, addr: &SocketAddr) -> io::Result<()> {
-        let (addr, len) = addr2raw(addr);
+    pub fn bind(&self, addr: &SockAddr) -> io::Result<()> {
         unsafe {
-            if ws2_32::bind(self.socket, addr, len) == 0 {
+            if ws2_32::bind(self.socket, addr.as_ptr(), addr.len()) == 0 {
                 Ok(())
             } else {
                 Err(last_error())
@@ -98,10 +207,9 @@ impl Socket {
         }
     }
 
-    pub fn connect(&self, addr: &SocketAddr) -> io::Result<()> {
-        let (addr, len) = addr2raw(addr);
+    pub fn connect(&self, addr: &SockAddr) -> io::Result<()> {
         unsafe {
-            if ws2_32::connect(self.socket, addr, len) == 0 {
+            if ws2_32::connect(self.socket, addr.as_ptr(), addr.len()) == 0 {
                 Ok(())
             } else {
                 Err(last_error())
@@ -109,29 +217,52 @@ impl Socket {
         }
     }
 
-    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+    pub fn connect_timeout(&self, addr: &SockAddr, timeout: Duration)
+                           -> io::Result<()> {
+        let deadline = match Instant::now().checked_add(timeout) {
+            Some(deadline) => deadline,
+            None => return self.connect(addr),
+        };
+        self.with_nonblocking(|| self.connect_deadline(addr, deadline))
+    }
+
+    fn connect_deadline(&self, addr: &SockAddr, deadline: Instant)
+                        -> io::Result<()> {
+        match self.connect(addr) {
+            Ok(()) => return Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+        self.wait(true, deadline)?;
+        match self.take_error()? {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SockAddr> {
         unsafe {
-            let mut storage: SOCKADDR_STORAGE = mem::zeroed();
-            let mut len = mem::size_of_val(&storage) as c_int;
+            let mut storage = addr_storage();
+            let mut len = mem::size_of::<SOCKADDR_STORAGE>() as c_int;
             if ws2_32::getsockname(self.socket,
-                                   &mut storage as *mut _ as *mut _,
+                                   storage.as_mut_ptr() as *mut _,
                                    &mut len) != 0 {
                 return Err(last_error())
             }
-            raw2addr(&storage, len)
+            Ok(SockAddr::from_raw_parts(storage.as_ptr() as *const _, len))
         }
     }
 
-    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+    pub fn peer_addr(&self) -> io::Result<SockAddr> {
         unsafe {
-            let mut storage: SOCKADDR_STORAGE = mem::zeroed();
-            let mut len = mem::size_of_val(&storage) as c_int;
+            let mut storage = addr_storage();
+            let mut len = mem::size_of::<SOCKADDR_STORAGE>() as c_int;
             if ws2_32::getpeername(self.socket,
-                                   &mut storage as *mut _ as *mut _,
+                                   storage.as_mut_ptr() as *mut _,
                                    &mut len) != 0 {
                 return Err(last_error())
             }
-            raw2addr(&storage, len)
+            Ok(SockAddr::from_raw_parts(storage.as_ptr() as *const _, len))
         }
     }
 
@@ -154,30 +285,107 @@ impl Socket {
                 INVALID_SOCKET => return Err(last_error()),
                 n => Socket::from_raw_socket(n),
             };
+            socket.inherit_nonblocking(self);
             socket.set_no_inherit()?;
             Ok(socket)
         }
     }
 
-    pub fn accept(&self) -> io::Result<(Socket, SocketAddr)> {
+    pub fn accept(&self) -> io::Result<(Socket, SockAddr)> {
         unsafe {
-            let mut storage: SOCKADDR_STORAGE = mem::zeroed();
-            let mut len = mem::size_of_val(&storage) as c_int;
+            let mut storage = addr_storage();
+            let mut len = mem::size_of::<SOCKADDR_STORAGE>() as c_int;
             let socket = {
                 ws2_32::accept(self.socket,
-                               &mut storage as *mut _ as *mut _,
+                               storage.as_mut_ptr() as *mut _,
                                &mut len)
             };
             let socket = match socket {
                 INVALID_SOCKET => return Err(last_error()),
                 socket => Socket::from_raw_socket(socket),
             };
+            socket.inherit_nonblocking(self);
             socket.set_no_inherit()?;
-            let addr = raw2addr(&storage, len)?;
+            let addr = SockAddr::from_raw_parts(storage.as_ptr() as *const _,
+                                                len);
             Ok((socket, addr))
         }
     }
 
+    pub fn accept_conditional<F>(&self, condition: F)
+                                 -> io::Result<(Socket, SockAddr)>
+        where F: FnMut(&SockAddr) -> bool
+    {
+        // The condition is called on this thread from within `WSAAccept`, so
+        // a panic in it is caught there and resumed once it returns.
+        struct State<F> {
+            condition: F,
+            panic: Option<Box<dyn Any + Send>>,
+        }
+
+        unsafe extern "system" fn call<F>(caller_id: *mut WSABUF,
+                                          _caller_data: *mut WSABUF,
+                                          _sqos: *mut c_void,
+                                          _gqos: *mut c_void,
+                                          _callee_id: *mut WSABUF,
+                                          _callee_data: *mut WSABUF,
+                                          _g: *mut c_uint,
+                                          callback_data: usize) -> c_int
+            where F: FnMut(&SockAddr) -> bool
+        {
+            let state = &mut *(callback_data as *mut State<F>);
+            let caller_id = &*caller_id;
+            let addr = SockAddr::from_raw_parts(caller_id.buf as *const _,
+                                                caller_id.len as c_int);
+            let condition = &mut state.condition;
+            match panic::catch_unwind(AssertUnwindSafe(|| condition(&addr))) {
+                Ok(true) => CF_ACCEPT,
+                Ok(false) => CF_REJECT,
+                Err(e) => {
+                    state.panic = Some(e);
+                    CF_REJECT
+                }
+            }
+        }
+
+        let mut state = State { condition, panic: None };
+        unsafe {
+            let mut storage = addr_storage();
+            let mut len = mem::size_of::<SOCKADDR_STORAGE>() as c_int;
+            let socket = WSAAccept(self.socket,
+                                   storage.as_mut_ptr() as *mut _,
+                                   &mut len,
+                                   Some(call::<F>),
+                                   &mut state as *mut _ as usize);
+            if let Some(e) = state.panic {
+                panic::resume_unwind(e)
+            }
+            let socket = match socket {
+                INVALID_SOCKET => return Err(last_error()),
+                socket => Socket::from_raw_socket(socket),
+            };
+            socket.inherit_nonblocking(self);
+            socket.set_no_inherit()?;
+            let addr = SockAddr::from_raw_parts(storage.as_ptr() as *const _,
+                                                len);
+            Ok((socket, addr))
+        }
+    }
+
+    pub fn conditional_accept(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(SOL_SOCKET, SO_CONDITIONAL_ACCEPT)?;
+            Ok(raw != 0)
+        }
+    }
+
+    pub fn set_conditional_accept(&self, conditional: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(SOL_SOCKET, SO_CONDITIONAL_ACCEPT,
+                            conditional as c_int)
+        }
+    }
+
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
         unsafe {
             let raw: c_int = self.getsockopt(SOL_SOCKET, SO_ERROR)?;
@@ -190,6 +398,33 @@ impl Socket {
     }
 
     pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.ioctl_nonblocking(nonblocking)?;
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+
+    // Runs `f` with the socket in nonblocking mode, switching it back to the
+    // mode recorded by `set_nonblocking` afterwards.
+    fn with_nonblocking<T, F>(&self, f: F) -> io::Result<T>
+        where F: FnOnce() -> io::Result<T>
+    {
+        if self.nonblocking.load(Ordering::Relaxed) {
+            return f()
+        }
+        self.ioctl_nonblocking(true)?;
+        let res = f();
+        self.ioctl_nonblocking(false)?;
+        res
+    }
+
+    // Accepted and duplicated sockets share the mode of the socket they came
+    // from.
+    fn inherit_nonblocking(&self, other: &Socket) {
+        let nonblocking = other.nonblocking.load(Ordering::Relaxed);
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+    }
+
+    fn ioctl_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
         unsafe {
             let mut nonblocking = nonblocking as c_ulong;
             let r = ws2_32::ioctlsocket(self.socket,
@@ -203,6 +438,20 @@ impl Socket {
         }
     }
 
+    pub fn at_mark(&self) -> io::Result<bool> {
+        unsafe {
+            let mut raw: c_ulong = 0;
+            let r = ws2_32::ioctlsocket(self.socket,
+                                        SIOCATMARK as c_int,
+                                        &mut raw);
+            if r == 0 {
+                Ok(raw != 0)
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        }
+    }
+
     pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
 
         let how = match how {
@@ -218,75 +467,169 @@ impl Socket {
     }
 
     pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv_with_flags(as_uninit_mut(buf), 0)
+    }
+
+    pub fn recv_out_of_band(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv_with_flags(as_uninit_mut(buf), MSG_OOB)
+    }
+
+    pub fn recv_with_flags(&self, buf: &mut [MaybeUninit<u8>], flags: c_int)
+                           -> io::Result<usize> {
         unsafe {
             let n = {
                 ws2_32::recv(self.socket,
                              buf.as_mut_ptr() as *mut c_char,
                              clamp(buf.len()),
-                             0)
+                             flags)
             };
             match n {
-                SOCKET_ERROR if ws2_32::WSAGetLastError() == WSAESHUTDOWN as i32 => Ok(0),
-                SOCKET_ERROR => Err(last_error()),
+                SOCKET_ERROR => self.recv_error(),
                 n => Ok(n as usize)
             }
         }
     }
 
-    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+    pub fn recv_vectored(&self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
         unsafe {
-            let n = {
-                ws2_32::recv(self.socket,
-                             buf.as_mut_ptr() as *mut c_char,
-                             clamp(buf.len()),
-                             MSG_PEEK)
-            };
-            match n {
-                SOCKET_ERROR if ws2_32::WSAGetLastError() == WSAESHUTDOWN as i32 => Ok(0),
-                SOCKET_ERROR => Err(last_error()),
-                n => Ok(n as usize)
+            let mut nread = 0;
+            let mut flags = 0;
+            // `IoSliceMut` is guaranteed to be ABI compatible with `WSABUF`.
+            let count = cmp::min(bufs.len(), DWORD::MAX as usize);
+            let r = ws2_32::WSARecv(self.socket,
+                                    bufs.as_mut_ptr() as *mut WSABUF,
+                                    count as DWORD,
+                                    &mut nread,
+                                    &mut flags,
+                                    ptr::null_mut(),
+                                    None);
+            match r {
+                0 => Ok(nread as usize),
+                _ => self.recv_error(),
             }
         }
     }
 
-    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
-        self.recvfrom(buf, 0)
+    pub fn peek(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+        self.recv_with_flags(buf, MSG_PEEK)
     }
 
-    pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
-        self.recvfrom(buf, MSG_PEEK)
+    pub fn peek_len(&self) -> io::Result<usize> {
+        unsafe {
+            let mut len: c_ulong = 0;
+            let r = ws2_32::ioctlsocket(self.socket,
+                                        FIONREAD as c_int,
+                                        &mut len);
+            if r == 0 {
+                Ok(len as usize)
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        }
     }
 
-    fn recvfrom(&self, buf: &mut [u8], flags: c_int)
-                -> io::Result<(usize, SocketAddr)> {
+    pub fn recv_deadline(&self, buf: &mut [u8], deadline: Instant)
+                         -> io::Result<usize> {
+        // Another reader may take the data between `wait` and `recv`, so the
+        // socket is nonblocking while receiving in order not to block past
+        // the deadline.
+        self.with_nonblocking(|| loop {
+            self.wait(false, deadline)?;
+            match self.recv(buf) {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                res => return res,
+            }
+        })
+    }
+
+    pub fn peek_deadline(&self, buf: &mut [u8], deadline: Instant)
+                         -> io::Result<usize> {
+        self.with_nonblocking(|| loop {
+            self.wait(false, deadline)?;
+            match self.peek(as_uninit_mut(buf)) {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                res => return res,
+            }
+        })
+    }
+
+    pub fn peek_exact(&self, buf: &mut [u8], deadline: Instant)
+                      -> io::Result<()> {
+        // Winsock has no receive low-water mark, and sockets report being
+        // readable as soon as any data arrives, so while only part of the
+        // data has arrived sleep between attempts.
+        let mut backoff = Duration::from_millis(1);
+        while !buf.is_empty() {
+            let n = self.peek_deadline(buf, deadline)?;
+            if n >= buf.len() {
+                break
+            }
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                          "connection closed before enough \
+                                           data could be peeked"))
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(io::ErrorKind::TimedOut,
+                                          "deadline has elapsed"))
+            }
+            thread::sleep(cmp::min(backoff, deadline - now));
+            backoff = cmp::min(backoff * 2, Duration::from_millis(50));
+        }
+        Ok(())
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SockAddr)> {
+        self.recv_from_with_flags(as_uninit_mut(buf), 0)
+    }
+
+    pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SockAddr)> {
+        self.recv_from_with_flags(as_uninit_mut(buf), MSG_PEEK)
+    }
+
+    pub fn recv_from_with_flags(&self,
+                                buf: &mut [MaybeUninit<u8>],
+                                flags: c_int)
+                                -> io::Result<(usize, SockAddr)> {
         unsafe {
-            let mut storage: SOCKADDR_STORAGE = mem::zeroed();
-            let mut addrlen = mem::size_of_val(&storage) as c_int;
+            let mut storage = addr_storage();
+            let mut addrlen = mem::size_of::<SOCKADDR_STORAGE>() as c_int;
 
             let n = {
                 ws2_32::recvfrom(self.socket,
                                  buf.as_mut_ptr() as *mut c_char,
                                  clamp(buf.len()),
                                  flags,
-                                 &mut storage as *mut _ as *mut _,
+                                 storage.as_mut_ptr() as *mut _,
                                  &mut addrlen)
             };
             let n = match n {
-                SOCKET_ERROR if ws2_32::WSAGetLastError() == WSAESHUTDOWN as i32 => 0,
-                SOCKET_ERROR => return Err(last_error()),
+                SOCKET_ERROR => self.recv_error()?,
                 n => n as usize,
             };
-            Ok((n, raw2addr(&storage, addrlen)?))
+            let addr = SockAddr::from_raw_parts(storage.as_ptr() as *const _,
+                                                addrlen);
+            Ok((n, addr))
         }
     }
 
     pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.send_with_flags(buf, 0)
+    }
+
+    pub fn send_out_of_band(&self, buf: &[u8]) -> io::Result<usize> {
+        self.send_with_flags(buf, MSG_OOB)
+    }
+
+    pub fn send_with_flags(&self, buf: &[u8], flags: c_int)
+                           -> io::Result<usize> {
         unsafe {
             let n = {
                 ws2_32::send(self.socket,
                              buf.as_ptr() as *const c_char,
                              clamp(buf.len()),
-                             0)
+                             flags)
             };
             if n == SOCKET_ERROR {
                 Err(last_error())
@@ -296,16 +639,60 @@ impl Socket {
         }
     }
 
-    pub fn send_to(&self, buf: &[u8], addr: &SocketAddr) -> io::Result<usize> {
+    pub fn send_vectored(&self, bufs: &[IoSlice]) -> io::Result<usize> {
+        self.send_vectored_with_flags(bufs, 0)
+    }
+
+    pub fn send_vectored_with_flags(&self, bufs: &[IoSlice], flags: c_int)
+                                    -> io::Result<usize> {
+        unsafe {
+            let mut nsent = 0;
+            // `IoSlice` is guaranteed to be ABI compatible with `WSABUF`.
+            let count = cmp::min(bufs.len(), DWORD::MAX as usize);
+            let r = ws2_32::WSASend(self.socket,
+                                    bufs.as_ptr() as *mut WSABUF,
+                                    count as DWORD,
+                                    &mut nsent,
+                                    flags as DWORD,
+                                    ptr::null_mut(),
+                                    None);
+            if r == 0 {
+                Ok(nsent as usize)
+            } else {
+                Err(last_error())
+            }
+        }
+    }
+
+    pub fn send_deadline(&self, buf: &[u8], deadline: Instant)
+                         -> io::Result<usize> {
+        // As with `recv_deadline`, a blocking `send` could outlast the
+        // deadline if other writers fill the buffer after `wait` returns.
+        self.with_nonblocking(|| loop {
+            self.wait(true, deadline)?;
+            match self.send(buf) {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                res => return res,
+            }
+        })
+    }
+
+    pub fn send_to(&self, buf: &[u8], addr: &SockAddr) -> io::Result<usize> {
+        self.send_to_with_flags(buf, addr, 0)
+    }
+
+    pub fn send_to_with_flags(&self,
+                              buf: &[u8],
+                              addr: &SockAddr,
+                              flags: c_int) -> io::Result<usize> {
         unsafe {
-            let (addr, len) = addr2raw(addr);
             let n = {
                 ws2_32::sendto(self.socket,
                                buf.as_ptr() as *const c_char,
                                clamp(buf.len()),
-                               0,
-                               addr,
-                               len)
+                               flags,
+                               addr.as_ptr(),
+                               addr.len())
             };
             if n == SOCKET_ERROR {
                 Err(last_error())
@@ -315,6 +702,29 @@ impl Socket {
         }
     }
 
+    pub fn event_select(&self, event: &Event, events: c_long)
+                        -> io::Result<()> {
+        unsafe {
+            if ws2_32::WSAEventSelect(self.socket, event.handle, events) == 0 {
+                Ok(())
+            } else {
+                Err(last_error())
+            }
+        }
+    }
+
+    pub fn network_events(&self, event: &Event) -> io::Result<c_long> {
+        unsafe {
+            let mut events: WSANETWORKEVENTS = mem::zeroed();
+            if ws2_32::WSAEnumNetworkEvents(self.socket,
+                                            event.handle,
+                                            &mut events) != 0 {
+                return Err(last_error())
+            }
+            Ok(events.lNetworkEvents)
+        }
+    }
+
     // ================================================
 
     pub fn ttl(&self) -> io::Result<u32> {
@@ -330,6 +740,51 @@ impl Socket {
         }
     }
 
+    pub fn unicast_hops_v6(&self) -> io::Result<u32> {
+        unsafe {
+            let raw: c_int = self.getsockopt(IPPROTO_IPV6.0 as c_int,
+                                             IPV6_UNICAST_HOPS)?;
+            Ok(raw as u32)
+        }
+    }
+
+    pub fn set_unicast_hops_v6(&self, hops: u32) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(IPPROTO_IPV6.0 as c_int,
+                            IPV6_UNICAST_HOPS,
+                            hops as c_int)
+        }
+    }
+
+    pub fn tos(&self) -> io::Result<u32> {
+        unsafe {
+            let raw: c_int = self.getsockopt(IPPROTO_IP, IP_TOS)?;
+            Ok(raw as u32)
+        }
+    }
+
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(IPPROTO_IP, IP_TOS, tos as c_int)
+        }
+    }
+
+    pub fn tclass_v6(&self) -> io::Result<u32> {
+        unsafe {
+            let raw: c_int = self.getsockopt(IPPROTO_IPV6.0 as c_int,
+                                             IPV6_TCLASS)?;
+            Ok(raw as u32)
+        }
+    }
+
+    pub fn set_tclass_v6(&self, tclass: u32) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(IPPROTO_IPV6.0 as c_int,
+                            IPV6_TCLASS,
+                            tclass as c_int)
+        }
+    }
+
     pub fn only_v6(&self) -> io::Result<bool> {
         unsafe {
             let raw: c_int = self.getsockopt(IPPROTO_IPV6.0 as c_int,
@@ -399,6 +854,19 @@ impl Socket {
         }
     }
 
+    pub fn out_of_band_inline(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(SOL_SOCKET, SO_OOBINLINE)?;
+            Ok(raw != 0)
+        }
+    }
+
+    pub fn set_out_of_band_inline(&self, oob_inline: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(SOL_SOCKET, SO_OOBINLINE, oob_inline as c_int)
+        }
+    }
+
     pub fn multicast_loop_v4(&self) -> io::Result<bool> {
         unsafe {
             let raw: c_int = self.getsockopt(IPPROTO_IP, IP_MULTICAST_LOOP)?;
@@ -524,6 +992,19 @@ impl Socket {
         }
     }
 
+    pub fn exclusive_address_use(&self) -> io::Result<bool> {
+        unsafe {
+            let raw: c_int = self.getsockopt(SOL_SOCKET, SO_EXCLUSIVEADDRUSE)?;
+            Ok(raw != 0)
+        }
+    }
+
+    pub fn set_exclusive_address_use(&self, exclusive: bool) -> io::Result<()> {
+        unsafe {
+            self.setsockopt(SOL_SOCKET, SO_EXCLUSIVEADDRUSE, exclusive as c_int)
+        }
+    }
+
     pub fn recv_buffer_size(&self) -> io::Result<usize> {
         unsafe {
             let raw: c_int = self.getsockopt(SOL_SOCKET, SO_RCVBUF)?;
@@ -587,16 +1068,86 @@ impl Socket {
     pub fn set_keepalive(&self, keepalive: Option<Duration>) -> io::Result<()> {
         let ms = dur2ms(keepalive)?;
         // TODO: checked casts here
-        let ka = tcp_keepalive {
+        self.set_keepalive_vals(&tcp_keepalive {
             onoff: keepalive.is_some() as c_ulong,
             keepalivetime: ms as c_ulong,
             keepaliveinterval: ms as c_ulong,
+        })
+    }
+
+    pub fn set_tcp_keepalive(&self, params: &TcpKeepalive) -> io::Result<()> {
+        // `SIO_KEEPALIVE_VALS` always sets both times, so the ones which
+        // aren't given are set to the system's defaults.
+        let time = match params.time {
+            Some(time) => dur2ms(Some(time))?,
+            None => 2 * 60 * 60 * 1000,
+        };
+        let interval = match params.interval {
+            Some(interval) => dur2ms(Some(interval))?,
+            None => 1000,
         };
+        self.set_keepalive_vals(&tcp_keepalive {
+            onoff: 1,
+            keepalivetime: time as c_ulong,
+            keepaliveinterval: interval as c_ulong,
+        })?;
+        if let Some(retries) = params.retries {
+            unsafe {
+                self.setsockopt(IPPROTO_TCP.0 as c_int, TCP_KEEPCNT,
+                                retries as DWORD)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        // Version 0 of the structure is the only one needed here.
+        let mut version: DWORD = 0;
+        let mut info: tcp_info_v0 = unsafe { mem::zeroed() };
+        let mut len: DWORD = 0;
+        let n = unsafe {
+            ws2_32::WSAIoctl(self.socket,
+                             SIO_TCP_INFO,
+                             &mut version as *mut _ as *mut _,
+                             mem::size_of_val(&version) as DWORD,
+                             &mut info as *mut _ as *mut _,
+                             mem::size_of_val(&info) as DWORD,
+                             &mut len,
+                             ptr::null_mut(),
+                             None)
+        };
+        if n != 0 {
+            return Err(last_error())
+        }
+        let state = match info.state {
+            1 => TcpState::Listen,
+            2 => TcpState::SynSent,
+            3 => TcpState::SynReceived,
+            4 => TcpState::Established,
+            5 => TcpState::FinWait1,
+            6 => TcpState::FinWait2,
+            7 => TcpState::CloseWait,
+            8 => TcpState::Closing,
+            9 => TcpState::LastAck,
+            10 => TcpState::TimeWait,
+            _ => TcpState::Closed,
+        };
+        Ok(TcpInfo {
+            state,
+            rtt: Duration::from_micros(info.rtt_us as u64),
+            retransmits: info.fast_retrans
+                             .saturating_add(info.timeout_episodes),
+            delivery_rate: None,
+            cwnd: info.cwnd,
+        })
+    }
+
+    fn set_keepalive_vals(&self, ka: &tcp_keepalive) -> io::Result<()> {
         let n = unsafe {
             ws2_32::WSAIoctl(self.socket,
                              SIO_KEEPALIVE_VALS,
-                             &ka as *const _ as *mut _,
-                             mem::size_of_val(&ka) as DWORD,
+                             ka as *const _ as *mut _,
+                             mem::size_of_val(ka) as DWORD,
                              0 as *mut _,
                              0,
                              0 as *mut _,
@@ -610,6 +1161,74 @@ impl Socket {
         }
     }
 
+    // Handles a failed receive. Receiving on a stream socket which was shut
+    // down for reading fails with `WSAESHUTDOWN` on Windows, which is mapped
+    // to end of file like on Unix. For other sockets it's a real error.
+    fn recv_error(&self) -> io::Result<usize> {
+        let err = last_error();
+        if err.raw_os_error() == Some(WSAESHUTDOWN as i32) &&
+           self.metadata().ok().map(|m| m.ty) == Some(SOCK_STREAM) {
+            Ok(0)
+        } else {
+            Err(err)
+        }
+    }
+
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        self.meta.get_or_try_init(|| {
+            let info: WSAPROTOCOL_INFOW = unsafe {
+                self.getsockopt(SOL_SOCKET, SO_PROTOCOL_INFOW)?
+            };
+            Ok(Metadata {
+                domain: info.iAddressFamily,
+                ty: info.iSocketType,
+                protocol: Some(info.iProtocol),
+            })
+        })
+    }
+
+    // Blocks until this socket is readable (or writable if `write` is set),
+    // returning a `TimedOut` error if that doesn't happen before `deadline`.
+    fn wait(&self, write: bool, deadline: Instant) -> io::Result<()> {
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(io::ErrorKind::TimedOut,
+                                          "deadline has elapsed"))
+            }
+            let timeout = deadline - now;
+            let mut timeout = timeval {
+                tv_sec: cmp::min(timeout.as_secs(),
+                                 c_long::MAX as u64) as c_long,
+                tv_usec: timeout.subsec_micros() as c_long,
+            };
+            if timeout.tv_sec == 0 && timeout.tv_usec == 0 {
+                timeout.tv_usec = 1;
+            }
+
+            let mut fds = unsafe { mem::zeroed::<fd_set>() };
+            fds.fd_count = 1;
+            fds.fd_array[0] = self.socket;
+            let mut errors = unsafe { mem::zeroed::<fd_set>() };
+            errors.fd_count = 1;
+            errors.fd_array[0] = self.socket;
+
+            let (readfds, writefds) = if write {
+                (ptr::null_mut(), &mut fds as *mut _)
+            } else {
+                (&mut fds as *mut _, ptr::null_mut())
+            };
+            let n = unsafe {
+                ws2_32::select(1, readfds, writefds, &mut errors, &timeout)
+            };
+            match n {
+                SOCKET_ERROR => return Err(last_error()),
+                0 => {}
+                _ => return Ok(()),
+            }
+        }
+    }
+
     unsafe fn setsockopt<T>(&self,
                             opt: c_int,
                             val: c_int,
@@ -661,12 +1280,20 @@ impl Read for Socket {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         <&Socket>::read(&mut &*self, buf)
     }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        <&Socket>::read_vectored(&mut &*self, bufs)
+    }
 }
 
 impl<'a> Read for &'a Socket {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         self.recv(buf)
     }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        self.recv_vectored(bufs)
+    }
 }
 
 impl Write for Socket {
@@ -674,6 +1301,10 @@ impl Write for Socket {
         <&Socket>::write(&mut &*self, buf)
     }
 
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        <&Socket>::write_vectored(&mut &*self, bufs)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         <&Socket>::flush(&mut &*self)
     }
@@ -684,6 +1315,10 @@ impl<'a> Write for &'a Socket {
         self.send(buf)
     }
 
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        self.send_vectored(bufs)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         Ok(())
     }
@@ -693,6 +1328,19 @@ impl fmt::Debug for Socket {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut f = f.debug_struct("Socket");
         f.field("socket", &self.socket);
+        if let Ok(meta) = self.metadata() {
+            f.field("domain", &meta.domain);
+            f.field("type", &meta.ty);
+            if let Some(protocol) = meta.protocol {
+                f.field("protocol", &protocol);
+            }
+        }
+        let listening = unsafe {
+            self.getsockopt::<c_int>(SOL_SOCKET, SO_ACCEPTCONN)
+        };
+        if let Ok(listening) = listening {
+            f.field("listening", &(listening != 0));
+        }
         if let Ok(addr) = self.local_addr() {
             f.field("local_addr", &addr);
         }
@@ -719,7 +1367,11 @@ impl IntoRawSocket for Socket {
 
 impl FromRawSocket for Socket {
     unsafe fn from_raw_socket(socket: SOCKET) -> Socket {
-        Socket { socket: socket }
+        Socket {
+            socket: socket,
+            meta: MetadataCache::new(),
+            nonblocking: AtomicBool::new(false),
+        }
     }
 }
 
@@ -785,61 +1437,63 @@ impl From<net::UdpSocket> for Socket {
     }
 }
 
-fn clamp(input: usize) -> c_int {
-    cmp::min(input, <c_int>::max_value() as usize) as c_int
+#[derive(Debug)]
+pub struct Event {
+    handle: WSAEVENT,
 }
 
-fn addr2raw(addr: &SocketAddr) -> (*const SOCKADDR, c_int) {
-    match *addr {
-        SocketAddr::V4(ref a) => {
-            (a as *const _ as *const _, mem::size_of_val(a) as c_int)
-        }
-        SocketAddr::V6(ref a) => {
-            (a as *const _ as *const _, mem::size_of_val(a) as c_int)
+impl Event {
+    pub fn new() -> io::Result<Event> {
+        init();
+        let handle = unsafe { ws2_32::WSACreateEvent() };
+        if handle.is_null() {
+            Err(last_error())
+        } else {
+            Ok(Event { handle })
         }
     }
 }
 
-fn raw2addr(storage: &SOCKADDR_STORAGE, len: c_int) -> io::Result<SocketAddr> {
-    match storage.ss_family as c_int {
-        AF_INET => {
-            unsafe {
-                assert!(len as usize >= mem::size_of::<SOCKADDR_IN>());
-                let sa = storage as *const _ as *const SOCKADDR_IN;
-                let bits = ::ntoh((*sa).sin_addr.S_un);
-                let ip = Ipv4Addr::new((bits >> 24) as u8,
-                                       (bits >> 16) as u8,
-                                       (bits >> 8) as u8,
-                                       bits as u8);
-                Ok(SocketAddr::V4(SocketAddrV4::new(ip, ::ntoh((*sa).sin_port))))
-            }
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ws2_32::WSACloseEvent(self.handle);
         }
-        AF_INET6 => {
-            unsafe {
-                assert!(len as usize >= mem::size_of::<sockaddr_in6>());
+    }
+}
 
-                let sa = storage as *const _ as *const sockaddr_in6;
-                let arr = (*sa).sin6_addr.s6_addr;
+impl AsRawHandle for ::Event {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.inner.handle as RawHandle
+    }
+}
 
-                let ip = Ipv6Addr::new(
-                    (arr[0] as u16) << 8 | (arr[1] as u16),
-                    (arr[2] as u16) << 8 | (arr[3] as u16),
-                    (arr[4] as u16) << 8 | (arr[5] as u16),
-                    (arr[6] as u16) << 8 | (arr[7] as u16),
-                    (arr[8] as u16) << 8 | (arr[9] as u16),
-                    (arr[10] as u16) << 8 | (arr[11] as u16),
-                    (arr[12] as u16) << 8 | (arr[13] as u16),
-                    (arr[14] as u16) << 8 | (arr[15] as u16),
-                );
+impl IntoRawHandle for ::Event {
+    fn into_raw_handle(self) -> RawHandle {
+        let handle = self.inner.handle;
+        mem::forget(self);
+        handle as RawHandle
+    }
+}
 
-                Ok(SocketAddr::V6(SocketAddrV6::new(ip,
-                                                    ::ntoh((*sa).sin6_port),
-                                                    (*sa).sin6_flowinfo,
-                                                    (*sa).sin6_scope_id)))
-            }
-        }
-        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid argument")),
+impl FromRawHandle for ::Event {
+    unsafe fn from_raw_handle(handle: RawHandle) -> ::Event {
+        ::Event { inner: Event { handle: handle as WSAEVENT } }
+    }
+}
+
+fn clamp(input: usize) -> c_int {
+    cmp::min(input, <c_int>::max_value() as usize) as c_int
+}
+
+// Returns storage for a socket address which is about to be filled in by
+// winsock, see the Unix implementation for why only the family is initialized.
+fn addr_storage() -> MaybeUninit<SOCKADDR_STORAGE> {
+    let mut storage = MaybeUninit::<SOCKADDR_STORAGE>::uninit();
+    unsafe {
+        (*storage.as_mut_ptr()).ss_family = 0;
     }
+    storage
 }
 
 fn dur2ms(dur: Option<Duration>) -> io::Result<DWORD> {
@@ -891,12 +1545,29 @@ fn to_s_addr(addr: &Ipv4Addr) -> ULONG {
            ((octets[3] as ULONG) <<  0))
 }
 
-fn to_in6_addr(addr: &Ipv6Addr) -> in6_addr {
+pub fn to_in_addr(addr: &Ipv4Addr) -> in_addr {
+    in_addr { S_un: to_s_addr(addr) }
+}
+
+pub fn from_in_addr(addr: in_addr) -> Ipv4Addr {
+    Ipv4Addr::from(::ntoh(addr.S_un))
+}
+
+pub fn to_in6_addr(addr: &Ipv6Addr) -> in6_addr {
     let mut ret: in6_addr = unsafe { mem::zeroed() };
     ret.s6_addr = addr.octets();
     return ret
 }
 
+pub fn interface_name(index: u32) -> io::Result<String> {
+    let mut name = [0 as c_char; IF_NAMESIZE];
+    if unsafe { if_indextoname(index, name.as_mut_ptr()) }.is_null() {
+        return Err(io::Error::last_os_error())
+    }
+    let name = unsafe { ::std::ffi::CStr::from_ptr(name.as_ptr()) };
+    Ok(name.to_string_lossy().into_owned())
+}
+
 fn linger2dur(linger_opt: linger) -> Option<Duration> {
     if linger_opt.l_onoff == 0 {
         None
@@ -8,6 +8,12 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::io::{self, IoSlice, IoSliceMut};
+use std::mem::{self, MaybeUninit};
+use std::slice;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use Metadata;
 
 #[doc(hidden)]
 pub trait NetInt {
@@ -49,3 +55,143 @@ macro_rules! zero {
 
 zero! { i8 i16 i32 i64 isize u8 u16 u32 u64 usize }
 
+
+// Views an initialized buffer as a possibly uninitialized one, for passing it
+// to functions which only ever write initialized bytes into the buffer.
+pub fn as_uninit_mut(buf: &mut [u8]) -> &mut [MaybeUninit<u8>] {
+    unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) }
+}
+
+// Advances `bufs` past the first `n` bytes, removing the buffers which were
+// consumed entirely and shrinking the first one left. This is
+// `IoSliceMut::advance_slices`, which needs a newer Rust than this crate.
+pub fn advance_slices_mut(bufs: &mut &mut [IoSliceMut], n: usize) {
+    let (remove, left) = consumed(bufs.iter().map(|b| b.len()), n);
+    *bufs = &mut mem::take(bufs)[remove..];
+    if let Some(first) = bufs.first_mut() {
+        // The new slice borrows the same memory as the one it replaces.
+        unsafe {
+            let ptr = first.as_mut_ptr().add(left);
+            let len = first.len() - left;
+            *first = IoSliceMut::new(slice::from_raw_parts_mut(ptr, len));
+        }
+    }
+}
+
+// The same as `advance_slices_mut`, for `IoSlice::advance_slices`.
+pub fn advance_slices(bufs: &mut &mut [IoSlice], n: usize) {
+    let (remove, left) = consumed(bufs.iter().map(|b| b.len()), n);
+    *bufs = &mut mem::take(bufs)[remove..];
+    if let Some(first) = bufs.first_mut() {
+        unsafe {
+            let ptr = first.as_ptr().add(left);
+            let len = first.len() - left;
+            *first = IoSlice::new(slice::from_raw_parts(ptr, len));
+        }
+    }
+}
+
+// Returns how many of the buffers with the given lengths the first `n` bytes
+// cover entirely, and how many bytes of the next buffer they cover.
+fn consumed<I: Iterator<Item = usize>>(lens: I, n: usize) -> (usize, usize) {
+    let mut remove = 0;
+    let mut left = n;
+    for len in lens {
+        if len > left {
+            return (remove, left)
+        }
+        left -= len;
+        remove += 1;
+    }
+    assert!(left == 0, "advancing IoSlices beyond their length");
+    (remove, 0)
+}
+
+// A lazily populated copy of a socket's `Metadata`, which doesn't change over
+// the socket's lifetime. Concurrent lookups may both query the system, but
+// they always store the same values.
+pub struct MetadataCache {
+    init: AtomicBool,
+    domain: AtomicUsize,
+    ty: AtomicUsize,
+    protocol: AtomicUsize,
+}
+
+impl MetadataCache {
+    pub fn new() -> MetadataCache {
+        MetadataCache {
+            init: AtomicBool::new(false),
+            domain: AtomicUsize::new(0),
+            ty: AtomicUsize::new(0),
+            protocol: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn get_or_try_init<F>(&self, f: F) -> io::Result<Metadata>
+        where F: FnOnce() -> io::Result<Metadata>
+    {
+        if self.init.load(Ordering::Acquire) {
+            let protocol = match self.protocol.load(Ordering::Relaxed) {
+                usize::MAX => None,
+                protocol => Some(protocol as i32),
+            };
+            return Ok(Metadata {
+                domain: self.domain.load(Ordering::Relaxed) as i32,
+                ty: self.ty.load(Ordering::Relaxed) as i32,
+                protocol,
+            })
+        }
+        let meta = f()?;
+        self.domain.store(meta.domain as usize, Ordering::Relaxed);
+        self.ty.store(meta.ty as usize, Ordering::Relaxed);
+        self.protocol.store(meta.protocol.map_or(usize::MAX, |p| p as usize),
+                            Ordering::Relaxed);
+        self.init.store(true, Ordering::Release);
+        Ok(meta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{IoSlice, IoSliceMut};
+
+    use super::*;
+
+    #[test]
+    fn advance() {
+        let (a, b, c) = ([1, 2, 3], [], [4, 5]);
+        let mut bufs = [IoSlice::new(&a), IoSlice::new(&b), IoSlice::new(&c)];
+        let mut bufs = &mut bufs[..];
+        advance_slices(&mut bufs, 0);
+        assert_eq!(bufs.len(), 3);
+        advance_slices(&mut bufs, 2);
+        assert_eq!(&*bufs[0], &[3]);
+        advance_slices(&mut bufs, 1);
+        assert_eq!(bufs.len(), 1);
+        assert_eq!(&*bufs[0], &[4, 5]);
+        advance_slices(&mut bufs, 2);
+        assert!(bufs.is_empty());
+    }
+
+    #[test]
+    fn advance_mut() {
+        let (mut a, mut b) = ([1, 2, 3], [4, 5]);
+        let mut bufs = [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)];
+        let mut bufs = &mut bufs[..];
+        advance_slices_mut(&mut bufs, 4);
+        assert_eq!(bufs.len(), 1);
+        assert_eq!(&*bufs[0], &[5]);
+        bufs[0][0] = 6;
+        advance_slices_mut(&mut bufs, 1);
+        assert!(bufs.is_empty());
+        assert_eq!(b, [4, 6]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn advance_too_far() {
+        let a = [1, 2, 3];
+        let mut bufs = [IoSlice::new(&a)];
+        advance_slices(&mut &mut bufs[..], 4);
+    }
+}